@@ -1,20 +1,25 @@
-use super::process_message_listener::ProcessMessageListener;
+use super::player_core::PlayerCore;
+use crate::audio::ab_loop::LoopMarker;
+use crate::layouts::equalizer_layout::*;
 use crate::layouts::main_layout::*;
+use iced::keyboard::{self, key::Named, Key};
 use iced::{event, window, Element, Event, Renderer, Subscription, Task, Theme};
-use std::path::PathBuf;
 use std::time::Instant;
 
 /// Send refresh UI messages every N seconds.
 const APP_VISUAL_UPDATE_INTERVAL_SEC: u64 = 1;
 
-#[derive(Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Layout {
     Main,
+    Equalizer,
 }
 
 #[derive(Debug, Clone)]
 pub enum ApplicationMessage {
     MainLayoutMessage(MainLayoutMessage),
+    EqualizerLayoutMessage(EqualizerLayoutMessage),
+    SwitchLayout(Layout),
     OsEvent(Event),
     VisualUpdate(Instant),
 }
@@ -23,23 +28,32 @@ pub struct ApplicationState {
     current_layout: Layout,
 
     main_layout: MainLayout,
+    equalizer_layout: EqualizerLayout,
 
-    process_message_listener: ProcessMessageListener,
+    player_core: PlayerCore,
 }
 
 impl ApplicationState {
     pub fn new() -> (Self, Task<ApplicationMessage>) {
-        let listener = ProcessMessageListener::new();
-        if listener.is_none() {
+        let player_core = PlayerCore::new(false);
+        if player_core.is_none() {
             // Exit.
             std::process::exit(0);
         }
+        let player_core = player_core.unwrap();
 
         (
             Self {
                 current_layout: Layout::Main,
-                main_layout: MainLayout::new(),
-                process_message_listener: listener.unwrap(),
+                main_layout: MainLayout::new(
+                    player_core.get_audio_player(),
+                    player_core.get_settings(),
+                ),
+                equalizer_layout: EqualizerLayout::new(
+                    player_core.get_audio_player(),
+                    player_core.get_settings(),
+                ),
+                player_core,
             },
             Task::none(),
         )
@@ -65,12 +79,23 @@ impl ApplicationState {
                 .main_layout
                 .view()
                 .map(ApplicationMessage::MainLayoutMessage),
+            Layout::Equalizer => self
+                .equalizer_layout
+                .view()
+                .map(ApplicationMessage::EqualizerLayoutMessage),
         }
     }
 
     pub fn update(&mut self, message: ApplicationMessage) -> Task<ApplicationMessage> {
         match message {
             ApplicationMessage::MainLayoutMessage(message) => self.main_layout.update(message),
+            ApplicationMessage::EqualizerLayoutMessage(message) => {
+                self.equalizer_layout.update(message)
+            }
+            ApplicationMessage::SwitchLayout(layout) => {
+                self.current_layout = layout;
+                Task::none()
+            }
             ApplicationMessage::OsEvent(os_event) => match os_event {
                 Event::Window(event) => {
                     if let window::Event::FileHovered(_) = event {
@@ -85,14 +110,32 @@ impl ApplicationState {
 
                     Task::none()
                 }
+                Event::Keyboard(keyboard::Event::KeyPressed { key, .. })
+                    if self.current_layout == Layout::Equalizer =>
+                {
+                    if key == Key::Named(Named::Escape) {
+                        self.current_layout = Layout::Main;
+                    }
+                    Task::none()
+                }
+                Event::Keyboard(keyboard::Event::KeyPressed { key, .. }) => {
+                    // Shortcuts of the A-B loop.
+                    let message = match key.as_ref() {
+                        Key::Character("a") => {
+                            MainLayoutMessage::SetLoopMarkerAtPlayhead(LoopMarker::Start)
+                        }
+                        Key::Character("b") => {
+                            MainLayoutMessage::SetLoopMarkerAtPlayhead(LoopMarker::End)
+                        }
+                        Key::Named(Named::Escape) => MainLayoutMessage::ClearLoop,
+                        _ => return Task::none(),
+                    };
+                    self.main_layout.update(message)
+                }
                 _ => Task::none(),
             },
             ApplicationMessage::VisualUpdate(_) => {
-                let paths = self.process_message_listener.process_messages();
-                for path in paths {
-                    self.main_layout
-                        .try_importing_track_from_path(PathBuf::from(path).as_path());
-                }
+                self.player_core.process_messages();
                 Task::none()
             }
         }
@@ -0,0 +1,49 @@
+use super::player_core::PlayerCore;
+use crate::misc::{command_line, message_box};
+use std::time::Duration;
+
+/// Check for messages from other processes every N seconds.
+const PROCESS_MESSAGES_INTERVAL_SEC: u64 = 1;
+
+/// Runs the player without a window until the process is killed.
+///
+/// The player is controlled through remote control servers (enabled in the settings)
+/// and paths passed from other processes.
+pub fn run() {
+    // We don't have a display to show dialogs.
+    message_box::disable_dialogs();
+
+    let mut player_core = match PlayerCore::new(true) {
+        None => return, // another instance is running
+        Some(core) => core,
+    };
+
+    {
+        let audio_player = player_core.get_audio_player();
+        let mut audio_player = audio_player.lock().unwrap();
+
+        if !command_line::get_paths().is_empty() {
+            if let Err(msg) = audio_player.play_track(0) {
+                message_box::show_error("Error", &msg);
+            }
+        }
+
+        println!(
+            "running in headless mode, loaded {} track(s)",
+            audio_player.get_tracklist().len()
+        );
+    }
+
+    if !player_core.is_remote_control_available() {
+        message_box::show_warning(
+            "Warning",
+            "no remote control servers are enabled in the settings, \
+            the player can only be controlled by passing paths to new instances",
+        );
+    }
+
+    loop {
+        player_core.process_messages();
+        std::thread::sleep(Duration::from_secs(PROCESS_MESSAGES_INTERVAL_SEC));
+    }
+}
@@ -1,2 +1,4 @@
 pub mod application;
+pub mod headless;
+pub mod player_core;
 pub mod process_message_listener;
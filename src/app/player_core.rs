@@ -0,0 +1,119 @@
+use super::process_message_listener::ProcessMessageListener;
+use crate::audio::audio_player::AudioPlayer;
+use crate::audio::{wave_cache::WaveCache, wave_precomputer::WavePrecomputer};
+use crate::integrations::{event_hooks, now_playing::NowPlayingWriter};
+use crate::misc::{
+    command_line,
+    config_manger::{ConfigManager, SettingsStore},
+};
+use crate::remote::remote_servers::RemoteServers;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Part of the application that is shared by all frontends (window, terminal, headless):
+/// the audio player, remote control servers and the listener of paths sent by other instances.
+pub struct PlayerCore {
+    audio_player: Arc<Mutex<AudioPlayer>>,
+    process_message_listener: ProcessMessageListener,
+    remote_servers: RemoteServers,
+    _now_playing_writer: Option<NowPlayingWriter>,
+    _wave_precomputer: Option<WavePrecomputer>,
+    settings: Arc<Mutex<SettingsStore>>,
+}
+
+impl PlayerCore {
+    /// Creates the player, returns `None` if another instance is already running
+    /// (in this case paths from the command line are sent to that instance).
+    ///
+    /// If `restore_session` is `true` and no paths were passed on the command line
+    /// the tracklist of the last session is loaded.
+    pub fn new(restore_session: bool) -> Option<Self> {
+        let process_message_listener = ProcessMessageListener::new()?;
+
+        let audio_player = AudioPlayer::new();
+        let settings_store = SettingsStore::load();
+        let settings = settings_store.get();
+
+        let wave_cache = WaveCache::new(&settings.waveform_cache);
+        let session = if restore_session && command_line::get_paths().is_empty() {
+            ConfigManager::load_session()
+        } else {
+            None
+        };
+
+        {
+            let mut audio_player = audio_player.lock().unwrap();
+
+            event_hooks::register(&mut audio_player, &settings.hooks);
+            audio_player.set_wave_cache(wave_cache.clone());
+            audio_player.set_time_stretch_mode(settings.playback.time_stretch_mode);
+            audio_player.set_fade_config(settings.fade.clone());
+            audio_player.set_ab_loop_options(
+                settings.ab_loop.repetitions,
+                Duration::from_secs_f64(settings.ab_loop.gap_sec.max(0.0)),
+            );
+            audio_player.set_equalizer_gains(&settings.equalizer.gains_db);
+            audio_player.set_equalizer_bypass(settings.equalizer.bypass);
+            audio_player.set_replay_gain_config(settings.replay_gain.clone());
+            audio_player.set_stereo_config(settings.stereo.clone());
+            audio_player.set_compressor_config(settings.compressor.clone());
+            audio_player.set_vocal_reduction_config(settings.vocal_reduction.clone());
+            audio_player.set_spectrogram_config(
+                Some(settings.spectrogram.clone()).filter(|config| config.enabled),
+            );
+
+            if restore_session {
+                audio_player.enable_session_saving();
+            }
+            if let Some(session) = session {
+                for track in session.get_tracks() {
+                    audio_player.add_track_from_config(&track);
+                }
+            }
+        }
+
+        let remote_servers = RemoteServers::new(audio_player.clone(), settings);
+        let now_playing_writer = NowPlayingWriter::new(audio_player.clone(), settings);
+        let wave_precomputer = wave_cache
+            .filter(|_| settings.waveform_cache.precompute_tracklist)
+            .map(|cache| WavePrecomputer::new(audio_player.clone(), cache));
+
+        Some(Self {
+            audio_player,
+            process_message_listener,
+            remote_servers,
+            _now_playing_writer: now_playing_writer,
+            _wave_precomputer: wave_precomputer,
+            settings: Arc::new(Mutex::new(settings_store)),
+        })
+    }
+
+    pub fn get_audio_player(&self) -> Arc<Mutex<AudioPlayer>> {
+        self.audio_player.clone()
+    }
+
+    /// Returns settings that were loaded when the player was created
+    /// (the user interface changes and saves them).
+    pub fn get_settings(&self) -> Arc<Mutex<SettingsStore>> {
+        self.settings.clone()
+    }
+
+    /// Tells if at least one remote control server is running.
+    pub fn is_remote_control_available(&self) -> bool {
+        self.remote_servers.is_any_running()
+    }
+
+    /// Adds tracks that were sent by other instances of the application.
+    pub fn process_messages(&mut self) {
+        let paths = self.process_message_listener.process_messages();
+        if paths.is_empty() {
+            return;
+        }
+
+        let mut audio_player = self.audio_player.lock().unwrap();
+        for path in paths {
+            audio_player.add_track(PathBuf::from(path).as_path());
+        }
+    }
+}
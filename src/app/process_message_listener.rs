@@ -1,5 +1,4 @@
-use native_dialog::MessageDialog;
-use native_dialog::MessageType;
+use crate::misc::{command_line, message_box};
 use std::net::IpAddr;
 use std::net::Ipv4Addr;
 use std::net::SocketAddr;
@@ -21,70 +20,55 @@ impl ProcessMessageListener {
             Ok(socket) => socket,
             Err(error) => {
                 if error.kind() == std::io::ErrorKind::AddrInUse {
-                    if let Some(path) = std::env::args().nth(1) {
-                        if path.len() >= MAX_MESSAGE_SIZE {
-                            MessageDialog::new()
-                                .set_type(MessageType::Error)
-                                .set_title("Error")
-                                .set_text("path is too long")
-                                .show_alert()
-                                .unwrap();
-                            return None;
-                        }
+                    let paths = command_line::get_paths();
+                    if paths.is_empty() {
+                        return None;
+                    }
+
+                    if paths.iter().any(|path| path.len() >= MAX_MESSAGE_SIZE) {
+                        message_box::show_error("Error", "path is too long");
+                        return None;
+                    }
 
-                        // Send message to the listener.
-                        let client = match UdpSocket::bind(SocketAddr::new(
-                            IpAddr::V4(Ipv4Addr::LOCALHOST),
-                            0,
-                        ))
-                        .and_then(|socket| socket.connect(addr).map(|()| socket))
+                    // Send message to the listener.
+                    let client =
+                        match UdpSocket::bind(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 0))
+                            .and_then(|socket| socket.connect(addr).map(|()| socket))
                         {
                             Err(msg) => {
-                                MessageDialog::new()
-                                    .set_type(MessageType::Error)
-                                    .set_title("Error")
-                                    .set_text(&format!("failed to notify listener, error: {}", msg))
-                                    .show_alert()
-                                    .unwrap();
+                                message_box::show_error(
+                                    "Error",
+                                    &format!("failed to notify listener, error: {}", msg),
+                                );
                                 return None;
                             }
                             Ok(client) => client,
                         };
 
+                    // Send each path as a separate message.
+                    for path in paths {
                         if let Err(msg) = client.send(path.as_bytes()) {
-                            MessageDialog::new()
-                                .set_type(MessageType::Error)
-                                .set_title("Error")
-                                .set_text(&format!("failed to notify the listener, error: {}", msg))
-                                .show_alert()
-                                .unwrap();
+                            message_box::show_error(
+                                "Error",
+                                &format!("failed to notify the listener, error: {}", msg),
+                            );
                             return None;
                         }
                     }
 
                     return None;
                 } else {
-                    MessageDialog::new()
-                        .set_type(MessageType::Error)
-                        .set_title("Error")
-                        .set_text(&format!("error: {}", error))
-                        .show_alert()
-                        .unwrap();
+                    message_box::show_error("Error", &format!("error: {}", error));
                     return None;
                 }
             }
         };
 
         if let Err(msg) = socket.set_nonblocking(true) {
-            MessageDialog::new()
-                .set_type(MessageType::Error)
-                .set_title("Error")
-                .set_text(&format!(
-                    "failed to set non-blocking for a socket, error: {}",
-                    msg
-                ))
-                .show_alert()
-                .unwrap();
+            message_box::show_error(
+                "Error",
+                &format!("failed to set non-blocking for a socket, error: {}", msg),
+            );
             return None;
         }
 
@@ -106,15 +90,10 @@ impl ProcessMessageListener {
             let path = match std::str::from_utf8(&buf) {
                 Ok(path) => path,
                 Err(msg) => {
-                    MessageDialog::new()
-                        .set_type(MessageType::Error)
-                        .set_title("Error")
-                        .set_text(&format!(
-                            "failed convert received message to string, error: {}",
-                            msg
-                        ))
-                        .show_alert()
-                        .unwrap();
+                    message_box::show_error(
+                        "Error",
+                        &format!("failed convert received message to string, error: {}", msg),
+                    );
                     panic!();
                 }
             };
@@ -0,0 +1,279 @@
+use crate::misc::time_format::format_duration;
+use std::time::{Duration, Instant};
+
+/// Loops shorter than this are widened (seeking back every check would make the player stutter).
+pub const MIN_LOOP_LENGTH: Duration = Duration::from_millis(100);
+
+/// One of the two ends of the loop.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoopMarker {
+    /// Marker "A", playback returns here.
+    Start,
+    /// Marker "B", playback returns to "A" when it passes this marker.
+    End,
+}
+
+/// What the player should do to keep the loop going.
+#[derive(Debug, PartialEq)]
+pub enum LoopAction {
+    None,
+    Seek(Duration),
+    /// Wait for the gap between repetitions (the sound fades out past "B").
+    Pause,
+    /// The gap between repetitions is over, continue from "A".
+    SeekAndResume(Duration),
+}
+
+/// Part of the current track that is played repeatedly (A-B loop).
+#[derive(Default)]
+pub struct AbLoop {
+    start: Option<Duration>,
+    end: Option<Duration>,
+    /// How many times the part is played (0 to repeat until the loop is cleared).
+    repetitions: u32,
+    /// Silence between repetitions.
+    gap: Duration,
+    completed_repetitions: u32,
+    /// When the current gap between repetitions ends.
+    gap_end: Option<Instant>,
+}
+
+impl AbLoop {
+    pub fn get_start(&self) -> Option<Duration> {
+        self.start
+    }
+
+    pub fn get_end(&self) -> Option<Duration> {
+        self.end
+    }
+
+    /// Tells if both markers are set.
+    pub fn is_active(&self) -> bool {
+        self.start.is_some() && self.end.is_some()
+    }
+
+    /// Tells if the part was played the configured number of times.
+    pub fn is_finished(&self) -> bool {
+        self.repetitions != 0 && self.completed_repetitions >= self.repetitions
+    }
+
+    /// Sets the number of repetitions (0 to repeat until the loop is cleared) and the gap
+    /// between them.
+    pub fn set_options(&mut self, repetitions: u32, gap: Duration) {
+        self.repetitions = repetitions;
+        self.gap = gap;
+    }
+
+    /// Moves a marker (markers are swapped if "B" ends up before "A", a too short loop is
+    /// widened by moving "A" back) and restarts counting repetitions.
+    pub fn set_marker(&mut self, marker: LoopMarker, position: Duration) {
+        match marker {
+            LoopMarker::Start => self.start = Some(position),
+            LoopMarker::End => self.end = Some(position),
+        }
+        if let (Some(start), Some(end)) = (self.start, self.end) {
+            let (start, end) = if end < start {
+                (end, start)
+            } else {
+                (start, end)
+            };
+            if end - start < MIN_LOOP_LENGTH {
+                let start = end.saturating_sub(MIN_LOOP_LENGTH);
+                self.start = Some(start);
+                self.end = Some(start + MIN_LOOP_LENGTH);
+            } else {
+                self.start = Some(start);
+                self.end = Some(end);
+            }
+        }
+
+        self.completed_repetitions = 0;
+        self.gap_end = None;
+    }
+
+    /// Removes both markers.
+    pub fn clear(&mut self) {
+        self.start = None;
+        self.end = None;
+        self.completed_repetitions = 0;
+        self.gap_end = None;
+    }
+
+    /// Should be called when the user seeks or pauses, stops waiting for the gap
+    /// and restarts the finished loop if the new position is before "B".
+    pub fn on_user_action(&mut self, position: Duration) {
+        self.gap_end = None;
+        if self.is_finished() && self.end.is_some_and(|end| position < end) {
+            self.completed_repetitions = 0;
+        }
+    }
+
+    /// Returns what the player should do at the specified position of the track.
+    pub fn check(&mut self, position: Duration, is_paused: bool) -> LoopAction {
+        let (start, end) = match (self.start, self.end) {
+            (Some(start), Some(end)) => (start, end),
+            _ => return LoopAction::None,
+        };
+
+        if let Some(gap_end) = self.gap_end {
+            if Instant::now() < gap_end {
+                return LoopAction::None;
+            }
+            self.gap_end = None;
+            return LoopAction::SeekAndResume(start);
+        }
+
+        if is_paused || position < end || self.is_finished() {
+            return LoopAction::None;
+        }
+
+        self.completed_repetitions += 1;
+        if self.is_finished() {
+            // Continue playing after "B".
+            return LoopAction::None;
+        }
+
+        if self.gap.is_zero() {
+            LoopAction::Seek(start)
+        } else {
+            self.gap_end = Some(Instant::now() + self.gap);
+            LoopAction::Pause
+        }
+    }
+
+    /// Returns text like "Loop: 00:10 - 00:20 (2/4)" (`None` if no markers are set).
+    pub fn get_status_text(&self, show_milliseconds: bool) -> Option<String> {
+        let format_marker = |marker: Option<Duration>| match marker {
+            Some(position) => format_duration(position, show_milliseconds),
+            None => "?".to_string(),
+        };
+        if self.start.is_none() && self.end.is_none() {
+            return None;
+        }
+
+        let mut text = format!(
+            "Loop: {} - {}",
+            format_marker(self.start),
+            format_marker(self.end)
+        );
+        if self.repetitions != 0 && self.is_active() {
+            text += &format!(
+                " ({}/{})",
+                (self.completed_repetitions + 1).min(self.repetitions),
+                self.repetitions
+            );
+        }
+
+        Some(text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn secs(seconds: u64) -> Duration {
+        Duration::from_secs(seconds)
+    }
+
+    fn create_loop(start: Duration, end: Duration) -> AbLoop {
+        let mut ab_loop = AbLoop::default();
+        ab_loop.set_marker(LoopMarker::Start, start);
+        ab_loop.set_marker(LoopMarker::End, end);
+        ab_loop
+    }
+
+    #[test]
+    fn set_marker_swaps_markers_in_wrong_order() {
+        let mut ab_loop = AbLoop::default();
+        ab_loop.set_marker(LoopMarker::Start, secs(20));
+        assert!(!ab_loop.is_active());
+
+        ab_loop.set_marker(LoopMarker::End, secs(10));
+        assert!(ab_loop.is_active());
+        assert_eq!(ab_loop.get_start(), Some(secs(10)));
+        assert_eq!(ab_loop.get_end(), Some(secs(20)));
+    }
+
+    #[test]
+    fn set_marker_widens_short_loop() {
+        let ab_loop = create_loop(secs(10), secs(10) + Duration::from_millis(30));
+        assert_eq!(
+            ab_loop.get_start(),
+            Some(secs(10) + Duration::from_millis(30) - MIN_LOOP_LENGTH)
+        );
+        assert_eq!(
+            ab_loop.get_end(),
+            Some(secs(10) + Duration::from_millis(30))
+        );
+
+        // The loop at the beginning of the track can only be widened forward.
+        let ab_loop = create_loop(Duration::ZERO, Duration::from_millis(30));
+        assert_eq!(ab_loop.get_start(), Some(Duration::ZERO));
+        assert_eq!(ab_loop.get_end(), Some(MIN_LOOP_LENGTH));
+    }
+
+    #[test]
+    fn check_seeks_back_until_repetitions_are_played() {
+        let mut ab_loop = create_loop(secs(10), secs(20));
+        ab_loop.set_options(2, Duration::ZERO);
+
+        assert_eq!(ab_loop.check(secs(15), false), LoopAction::None);
+        assert_eq!(ab_loop.check(secs(20), true), LoopAction::None);
+        assert_eq!(ab_loop.check(secs(20), false), LoopAction::Seek(secs(10)));
+        assert!(!ab_loop.is_finished());
+        assert_eq!(
+            ab_loop.get_status_text(false).unwrap(),
+            "Loop: 00:10 - 00:20 (2/2)"
+        );
+
+        // The second repetition continues playing after "B".
+        assert_eq!(ab_loop.check(secs(20), false), LoopAction::None);
+        assert!(ab_loop.is_finished());
+        assert_eq!(ab_loop.check(secs(25), false), LoopAction::None);
+
+        // Seeking before "B" restarts the loop.
+        ab_loop.on_user_action(secs(12));
+        assert!(!ab_loop.is_finished());
+        assert_eq!(ab_loop.check(secs(20), false), LoopAction::Seek(secs(10)));
+    }
+
+    #[test]
+    fn check_repeats_until_cleared_without_repetitions() {
+        let mut ab_loop = create_loop(secs(10), secs(20));
+        for _ in 0..100 {
+            assert_eq!(ab_loop.check(secs(21), false), LoopAction::Seek(secs(10)));
+        }
+
+        ab_loop.clear();
+        assert_eq!(ab_loop.check(secs(21), false), LoopAction::None);
+        assert!(ab_loop.get_status_text(false).is_none());
+    }
+
+    #[test]
+    fn check_waits_for_gap_between_repetitions() {
+        let gap = Duration::from_millis(50);
+        let mut ab_loop = create_loop(secs(10), secs(20));
+        ab_loop.set_options(0, gap);
+
+        assert_eq!(ab_loop.check(secs(20), false), LoopAction::Pause);
+        assert_eq!(ab_loop.check(secs(20), true), LoopAction::None);
+
+        std::thread::sleep(gap);
+        assert_eq!(
+            ab_loop.check(secs(20), true),
+            LoopAction::SeekAndResume(secs(10))
+        );
+        assert_eq!(ab_loop.check(secs(11), false), LoopAction::None);
+    }
+
+    #[test]
+    fn user_action_cancels_gap() {
+        let mut ab_loop = create_loop(secs(10), secs(20));
+        ab_loop.set_options(0, Duration::from_secs(60));
+
+        assert_eq!(ab_loop.check(secs(20), false), LoopAction::Pause);
+        ab_loop.on_user_action(secs(15));
+        assert_eq!(ab_loop.check(secs(15), false), LoopAction::None);
+    }
+}
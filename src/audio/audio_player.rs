@@ -1,6 +1,12 @@
-use kira::sound::{streaming::StreamingSoundData, PlaybackState};
+use kira::effect::compressor::{CompressorBuilder, CompressorHandle};
+use kira::effect::volume_control::{VolumeControlBuilder, VolumeControlHandle};
+use kira::sound::{
+    streaming::{Decoder, StreamingSoundData},
+    PlaybackState,
+};
+use kira::track::MainTrackBuilder;
 use kira::{AudioManager, AudioManagerSettings, Decibels, DefaultBackend, Tween};
-use native_dialog::MessageDialog;
+use std::ops::RangeInclusive;
 use std::path::Path;
 use std::path::PathBuf;
 use std::sync::atomic::AtomicBool;
@@ -9,12 +15,68 @@ use std::sync::{Arc, Mutex};
 use std::thread::JoinHandle;
 use std::time::Duration;
 
+use super::ab_loop::{AbLoop, LoopAction, LoopMarker};
+use super::equalizer::Equalizer;
+use super::limiter::{LimiterBuilder, LimiterHandle};
+use super::loudness_cache::LoudnessCache;
+use super::output_analyzer::OutputAnalyzer;
+use super::output_tap::OutputTapBuilder;
+use super::pitch_shifter::{PitchShifterBuilder, PitchShifterHandle};
+use super::player_event::{PlayerEvent, PlayerEventListener, PlayingTrackInfo};
+use super::replay_gain::get_replay_gain_db;
+use super::sleep_timer::{SleepTarget, SleepTimer};
 use super::sound_data::CurrentSoundData;
+use super::sound_decoder::SoundDecoder;
+use super::sound_wave::SoundWave;
+use super::spectrogram::Spectrogram;
+use super::stereo_control::{StereoControlBuilder, StereoControlHandle};
+use super::vocal_reducer::{VocalReducerBuilder, VocalReducerHandle};
+use super::wave_cache::WaveCache;
+use crate::misc::{
+    command_line,
+    config_manger::{
+        CompressorConfig, ConfigManager, FadeConfig, ReplayGainConfig, ReplayGainMode,
+        SpectrogramConfig, StereoConfig, TimeStretchMode, TrackConfig, TracklistConfig,
+        VocalReductionConfig,
+    },
+    message_box,
+};
+
+/// Maximum volume multiplier.
+pub const MAX_VOLUME: f64 = 1.25;
+
+/// How often to check if the current track has finished.
+const TRACK_END_CHECK_INTERVAL_MS: u64 = 100;
+
+/// How often to check if the gap between repetitions of the A-B loop is over.
+const AB_LOOP_CHECK_INTERVAL_MS: u64 = 10;
+
+/// The shortest sleep of the track switch thread (used right before the end of the A-B loop).
+const MIN_CHECK_INTERVAL_MS: u64 = 1;
+
+/// Allowed playback speed multipliers.
+pub const PLAYBACK_RATE_RANGE: RangeInclusive<f64> = 0.6..=1.4;
+
+/// Allowed playback speed multipliers when the pitch is preserved.
+pub const STRETCH_PLAYBACK_RATE_RANGE: RangeInclusive<f64> = 0.5..=2.0;
+
+/// Duration of the volume change when ReplayGain of a playing sound changes
+/// (for example when its loudness scan finishes).
+const REPLAY_GAIN_CHANGE_DURATION_MS: u64 = 500;
+
+/// Allowed pitch shift of a track in cents (one octave down or up).
+pub const PITCH_CENTS_RANGE: RangeInclusive<i32> = -1200..=1200;
 
 #[derive(Clone)]
 pub struct TrackInfo {
+    /// Unique (per application run) ID of the track that does not change when the track is moved.
+    pub id: u32,
     pub name: String,
     pub path: String,
+    /// Pitch shift in cents that is applied when the track is playing.
+    pub pitch_cents: i32,
+    /// Tells if vocals are reduced when the track is playing.
+    pub vocal_reduction: bool,
 }
 
 pub struct AudioPlayer {
@@ -24,8 +86,39 @@ pub struct AudioPlayer {
     volume: f64,
     current_track_index: Option<usize>,
     tracklist: Vec<TrackInfo>,
+    tracklist_version: u32,
+    session_tracklist_version: u32,
+    /// Save the tracklist as the session when it changes.
+    save_session: bool,
+    next_track_id: u32,
     track_switch_thread: Option<JoinHandle<()>>,
     stop_track_switch_thread: Arc<AtomicBool>,
+    event_listeners: Vec<PlayerEventListener>,
+    wave_cache: Option<WaveCache>,
+    spectrogram_config: Option<SpectrogramConfig>,
+    /// The spectrogram is only computed while it's shown.
+    is_spectrogram_shown: bool,
+    output_analyzer: Arc<Mutex<OutputAnalyzer>>,
+    ab_loop: AbLoop,
+    time_stretch_mode: TimeStretchMode,
+    fade_config: FadeConfig,
+    pitch_shifter: PitchShifterHandle,
+    vocal_reducer: VocalReducerHandle,
+    vocal_reduction_config: VocalReductionConfig,
+    equalizer: Equalizer,
+    stereo_control: StereoControlHandle,
+    stereo_config: StereoConfig,
+    compressor: CompressorHandle,
+    compressor_config: CompressorConfig,
+    volume_control: VolumeControlHandle,
+    sleep_timer: SleepTimer,
+    sleep_fader: VolumeControlHandle,
+    /// Volume multiplier that was applied to `sleep_fader`.
+    sleep_fade_gain: f32,
+    limiter: LimiterHandle,
+    replay_gain_config: ReplayGainConfig,
+    /// `None` if loudness of sounds without ReplayGain tags should not be scanned.
+    loudness_cache: Option<LoudnessCache>,
 }
 
 impl Drop for AudioPlayer {
@@ -38,19 +131,42 @@ impl Drop for AudioPlayer {
 
 impl AudioPlayer {
     pub fn new() -> Arc<Mutex<Self>> {
-        // Create audio manager.
-        let audio_manager =
-            match AudioManager::<DefaultBackend>::new(AudioManagerSettings::default()) {
-                Err(msg) => {
-                    MessageDialog::new()
-                        .set_title("Critical error")
-                        .set_text(&format!("failed to create audio manager, error: {}", msg))
-                        .show_alert()
-                        .unwrap();
-                    panic!();
-                }
-                Ok(manager) => manager,
-            };
+        // Create audio manager with a pitch shifter (to preserve pitch when the playback rate
+        // is changed), a vocal reducer, an equalizer, balance and other channel options,
+        // a compressor, the volume and the fade of the sleep timer (applied before the limiter
+        // that keeps loud volumes from clipping) and a tap on the main track to analyze what
+        // is playing.
+        let (pitch_shifter, pitch_shifter_handle) = PitchShifterBuilder::new();
+        let (vocal_reducer, vocal_reducer_handle) = VocalReducerBuilder::new();
+        let (stereo_control, stereo_control_handle) = StereoControlBuilder::new();
+        let (limiter, limiter_handle) = LimiterBuilder::new();
+        let (output_tap, output_tap_consumer) = OutputTapBuilder::new();
+        let mut main_track_builder = MainTrackBuilder::new()
+            .with_effect(pitch_shifter)
+            .with_effect(vocal_reducer);
+        let equalizer = Equalizer::add_to_track(&mut main_track_builder);
+        main_track_builder.add_effect(stereo_control);
+        let compressor = main_track_builder.add_effect(CompressorBuilder::new());
+        let volume_control =
+            main_track_builder.add_effect(VolumeControlBuilder::new(Decibels::IDENTITY));
+        let sleep_fader =
+            main_track_builder.add_effect(VolumeControlBuilder::new(Decibels::IDENTITY));
+        let main_track_builder = main_track_builder
+            .with_effect(limiter)
+            .with_effect(output_tap);
+        let audio_manager = match AudioManager::<DefaultBackend>::new(AudioManagerSettings {
+            main_track_builder,
+            ..Default::default()
+        }) {
+            Err(msg) => {
+                message_box::show_error(
+                    "Critical error",
+                    &format!("failed to create audio manager, error: {}", msg),
+                );
+                panic!();
+            }
+            Ok(manager) => manager,
+        };
 
         let stop_signal = Arc::new(AtomicBool::new(false));
         let stop_signal_clone = stop_signal.clone();
@@ -62,37 +178,88 @@ impl AudioPlayer {
             volume: 1.0,
             current_track_index: None,
             tracklist: Vec::new(),
+            tracklist_version: 0,
+            session_tracklist_version: 0,
+            save_session: false,
+            next_track_id: 0,
             track_switch_thread: None,
             stop_track_switch_thread: stop_signal,
+            event_listeners: Vec::new(),
+            wave_cache: None,
+            spectrogram_config: None,
+            is_spectrogram_shown: false,
+            output_analyzer: Arc::new(Mutex::new(OutputAnalyzer::new(output_tap_consumer))),
+            ab_loop: AbLoop::default(),
+            time_stretch_mode: TimeStretchMode::default(),
+            fade_config: FadeConfig::default(),
+            pitch_shifter: pitch_shifter_handle,
+            vocal_reducer: vocal_reducer_handle,
+            vocal_reduction_config: VocalReductionConfig::default(),
+            equalizer,
+            stereo_control: stereo_control_handle,
+            stereo_config: StereoConfig::default(),
+            compressor,
+            compressor_config: CompressorConfig::default(),
+            volume_control,
+            sleep_timer: SleepTimer::default(),
+            sleep_fader,
+            sleep_fade_gain: 1.0,
+            limiter: limiter_handle,
+            replay_gain_config: ReplayGainConfig::default(),
+            loudness_cache: None,
         }));
 
         // Spawn a thread that checks if the track is finished (since I can't find a callback in audio manager).
         let this_clone = this.clone();
         let track_switch_thread = Some(std::thread::spawn(move || {
             while !stop_signal_clone.load(Ordering::SeqCst) {
-                {
+                let (check_interval, session) = {
                     let mut this = this_clone.lock().unwrap();
 
+                    this.update_ab_loop();
+                    this.update_sleep_timer();
+                    this.update_replay_gain(Tween {
+                        duration: Duration::from_millis(REPLAY_GAIN_CHANGE_DURATION_MS),
+                        ..Default::default()
+                    });
+
                     if let Some(sound) = this.current_sound.as_ref() {
-                        if let Some(mut curren_track_index) = this.current_track_index {
-                            if sound.handle.position() + 0.01 >= sound.duration {
-                                // Switch to the next track.
-                                if curren_track_index + 1 == this.tracklist.len() {
-                                    curren_track_index = 0;
-                                } else {
-                                    curren_track_index += 1;
+                        if let Some(index) = this.current_track_index {
+                            if sound.handle.state() == PlaybackState::Stopped
+                                || sound.handle.position() >= sound.duration.as_secs_f64()
+                            {
+                                this.emit_event(PlayerEvent::TrackEnded);
+                                let is_last_track = index + 1 == this.tracklist.len();
+                                if is_last_track {
+                                    this.emit_event(PlayerEvent::TracklistEnded);
                                 }
 
-                                this.current_track_index = Some(curren_track_index);
-
-                                // Play it.
-                                this.play_track(curren_track_index);
+                                // The sleep timer pauses at the start of the next track
+                                // so that resuming continues from there.
+                                let should_sleep =
+                                    this.sleep_timer.is_waiting_for_track_end(is_last_track);
+                                if let Err(msg) = this.play_next_track() {
+                                    message_box::log_error(&msg);
+                                }
+                                if should_sleep {
+                                    this.sleep_timer.cancel();
+                                    this.set_paused(true);
+                                }
                             }
                         }
                     }
+
+                    (this.get_check_interval(), this.take_changed_session())
+                };
+
+                // Write the session without blocking the player.
+                if let Some(session) = session {
+                    if let Err(msg) = ConfigManager::save_session(&session) {
+                        message_box::log_error(&msg);
+                    }
                 }
 
-                std::thread::sleep(Duration::from_secs(1));
+                std::thread::sleep(check_interval);
             }
         }));
 
@@ -102,8 +269,8 @@ impl AudioPlayer {
             // Save thread handle.
             this_data.track_switch_thread = track_switch_thread;
 
-            // See if a command line argument was provided.
-            if let Some(path) = std::env::args().nth(1) {
+            // See if command line arguments were provided.
+            for path in command_line::get_paths() {
                 this_data.add_track(PathBuf::from(path).as_path());
             }
         }
@@ -111,6 +278,113 @@ impl AudioPlayer {
         this
     }
 
+    /// Adds a function that will be called every time a player event happens.
+    pub fn add_event_listener(&mut self, listener: PlayerEventListener) {
+        self.event_listeners.push(listener);
+    }
+
+    /// Sets the cache used to store waves of played sounds (`None` to not use the cache).
+    pub fn set_wave_cache(&mut self, wave_cache: Option<WaveCache>) {
+        self.wave_cache = wave_cache;
+    }
+
+    /// Sets how spectrograms of played sounds are computed (`None` to not compute them).
+    pub fn set_spectrogram_config(&mut self, config: Option<SpectrogramConfig>) {
+        self.spectrogram_config = config;
+    }
+
+    /// Tells if the spectrogram is shown, it's computed only while it is
+    /// (starts computing the spectrogram of the current sound if needed).
+    pub fn set_spectrogram_shown(&mut self, shown: bool) {
+        self.is_spectrogram_shown = shown;
+
+        if let (true, Some(config), Some(sound_data)) = (
+            shown,
+            self.spectrogram_config.clone(),
+            self.current_sound.as_mut(),
+        ) {
+            sound_data.start_spectrogram(config);
+        }
+    }
+
+    /// Sets how the loudness of played sounds is normalized.
+    pub fn set_replay_gain_config(&mut self, config: ReplayGainConfig) {
+        self.loudness_cache =
+            (config.mode != ReplayGainMode::Off && config.scan_untagged).then(LoudnessCache::new);
+        self.replay_gain_config = config;
+        self.update_replay_gain(Tween::default());
+    }
+
+    /// Applies ReplayGain to the current sound if it changed (for example because
+    /// the loudness scan of the sound has finished).
+    fn update_replay_gain(&mut self, tween: Tween) {
+        let sound = match self.current_sound.as_mut() {
+            Some(sound) => sound,
+            None => return,
+        };
+
+        let loudness = *sound.loudness.lock().unwrap();
+        let gain_db = get_replay_gain_db(
+            &self.replay_gain_config,
+            &sound.metadata.replay_gain,
+            loudness,
+        );
+        if gain_db == sound.applied_replay_gain_db {
+            return;
+        }
+
+        sound
+            .handle
+            .set_volume(Decibels(gain_db.unwrap_or(0.0)), tween);
+        sound.applied_replay_gain_db = gain_db;
+    }
+
+    /// Returns information about the current sound (`None` if no sound).
+    pub fn get_playing_track_info(&self) -> Option<PlayingTrackInfo> {
+        let sound_data = self.current_sound.as_ref()?;
+
+        let title = sound_data.metadata.title.clone().unwrap_or_else(|| {
+            Path::new(&sound_data.path)
+                .file_stem()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default()
+        });
+
+        Some(PlayingTrackInfo {
+            index: self.current_track_index,
+            path: sound_data.path.clone(),
+            title,
+            artist: sound_data.metadata.artist.clone(),
+            album: sound_data.metadata.album.clone(),
+            duration: sound_data.duration,
+            position: Self::get_sound_position(sound_data),
+            is_paused: self.is_paused(),
+        })
+    }
+
+    /// Notifies listeners about the event related to the current sound (does nothing if no sound).
+    fn emit_event(&mut self, event: PlayerEvent) {
+        if self.event_listeners.is_empty() {
+            return;
+        }
+
+        let mut info = match self.get_playing_track_info() {
+            None => return,
+            Some(info) => info,
+        };
+
+        // The handle might not have processed pause/resume commands yet.
+        match event {
+            PlayerEvent::Paused => info.is_paused = true,
+            PlayerEvent::Resumed | PlayerEvent::TrackStarted => info.is_paused = false,
+            _ => {}
+        }
+
+        for listener in self.event_listeners.iter_mut() {
+            listener(event, &info);
+        }
+    }
+
     fn is_format_supported(extension: &str) -> bool {
         extension == "mp3" || extension == "wav" || extension == "ogg" || extension == "flac"
     }
@@ -123,33 +397,86 @@ impl AudioPlayer {
         &self.tracklist
     }
 
-    pub fn add_track(&mut self, path: &Path) {
+    /// Returns a number that changes every time the tracklist is modified.
+    pub fn get_tracklist_version(&self) -> u32 {
+        self.tracklist_version
+    }
+
+    /// Returns index of the track with the specified ID.
+    pub fn find_track_index(&self, track_id: u32) -> Option<usize> {
+        self.tracklist.iter().position(|track| track.id == track_id)
+    }
+
+    /// Adds a track to the end of the tracklist and returns its ID
+    /// (or `None` if the file is not supported).
+    pub fn add_track(&mut self, path: &Path) -> Option<u32> {
         // Make sure it's a file.
         if !path.is_file() {
-            return;
+            return None;
         }
 
         // Get file extension.
-        let file_extension = match path.extension() {
-            None => return,
-            Some(ext) => ext,
-        };
+        let file_extension = path.extension()?;
 
         // Make sure it has a correct extension.
         if !Self::is_format_supported(file_extension.to_str().unwrap()) {
-            return;
+            return None;
         }
 
+        let id = self.next_track_id;
+        self.next_track_id += 1;
+
         self.tracklist.push(TrackInfo {
+            id,
             name: path.file_stem().unwrap().to_str().unwrap().to_string(),
             path: path.display().to_string(),
+            pitch_cents: 0,
+            vocal_reduction: false,
         });
+        self.on_tracklist_changed();
+
+        Some(id)
+    }
+
+    /// Adds a track of a saved tracklist with its settings and returns its ID
+    /// (or `None` if the file is not supported).
+    pub fn add_track_from_config(&mut self, track: &TrackConfig) -> Option<u32> {
+        let id = self.add_track(Path::new(&track.path))?;
+        self.set_track_pitch(id, track.pitch_cents);
+        self.set_track_vocal_reduction(id, track.vocal_reduction);
+
+        Some(id)
     }
 
     pub fn clear_tracklist(&mut self) {
         self.stop();
         self.current_track_index = None;
         self.tracklist.clear();
+        self.on_tracklist_changed();
+    }
+
+    /// Moves a track to the specified position shifting other tracks.
+    pub fn move_track(&mut self, from_index: usize, to_index: usize) {
+        // Make sure the indices are not out of bounds.
+        if from_index >= self.tracklist.len() || to_index >= self.tracklist.len() {
+            return;
+        }
+
+        let track = self.tracklist.remove(from_index);
+        self.tracklist.insert(to_index, track);
+
+        // Update current index.
+        if let Some(current_index) = self.current_track_index {
+            if current_index == from_index {
+                self.current_track_index = Some(to_index);
+            } else if from_index < current_index && to_index >= current_index {
+                self.current_track_index = Some(current_index - 1);
+            } else if from_index > current_index && to_index <= current_index {
+                self.current_track_index = Some(current_index + 1);
+            }
+        }
+
+        self.on_tracklist_changed();
     }
 
     pub fn move_track_up(&mut self, track_index: usize) {
@@ -186,6 +513,8 @@ impl AudioPlayer {
                 }
             }
         }
+
+        self.on_tracklist_changed();
     }
 
     pub fn move_track_down(&mut self, track_index: usize) {
@@ -222,6 +551,8 @@ impl AudioPlayer {
                 }
             }
         }
+
+        self.on_tracklist_changed();
     }
 
     pub fn remove_track(&mut self, track_index: usize) {
@@ -242,85 +573,213 @@ impl AudioPlayer {
                 self.current_track_index = Some(index - 1);
             }
         }
+
+        self.on_tracklist_changed();
+    }
+
+    /// Returns all tracks of the tracklist with their settings (pitch and vocal reduction).
+    pub fn get_tracklist_config(&self) -> TracklistConfig {
+        let mut config = TracklistConfig::new();
+        config.paths = self
+            .tracklist
+            .iter()
+            .map(|track_info| track_info.path.clone())
+            .collect();
+        config.tracks = self
+            .tracklist
+            .iter()
+            .map(|track_info| TrackConfig {
+                path: track_info.path.clone(),
+                pitch_cents: track_info.pitch_cents,
+                vocal_reduction: track_info.vocal_reduction,
+            })
+            .collect();
+        config
+    }
+
+    /// Makes the player save the tracklist as the session whenever it changes
+    /// (used by frontends that restore the last session).
+    pub fn enable_session_saving(&mut self) {
+        self.save_session = true;
+    }
+
+    /// Returns the tracklist to save as the session if it changed since the last call.
+    fn take_changed_session(&mut self) -> Option<TracklistConfig> {
+        if !self.save_session || self.session_tracklist_version == self.tracklist_version {
+            return None;
+        }
+
+        self.session_tracklist_version = self.tracklist_version;
+        Some(self.get_tracklist_config())
+    }
+
+    fn on_tracklist_changed(&mut self) {
+        self.tracklist_version = self.tracklist_version.wrapping_add(1);
+    }
+
+    /// Plays the track from the tracklist, returns an error if the file can't be played.
+    pub fn play_track(&mut self, track_index: usize) -> Result<(), String> {
+        // Make sure the index is not out of bounds.
+        if track_index >= self.tracklist.len() {
+            return Ok(());
+        }
+
+        let decoder = Self::open_sound(&self.tracklist[track_index].path);
+        self.play_track_with_decoder(track_index, decoder)
     }
 
-    pub fn play_track(&mut self, track_index: usize) {
+    /// Plays the track with the file that was opened by `open_sound`
+    /// (so the file can be opened without locking the player).
+    pub fn play_track_with_decoder(
+        &mut self,
+        track_index: usize,
+        decoder: Result<SoundDecoder, String>,
+    ) -> Result<(), String> {
         // Make sure the index is not out of bounds.
         if track_index >= self.tracklist.len() {
-            return;
+            return Ok(());
         }
 
         self.current_track_index = Some(track_index);
-        self.play(&self.tracklist[track_index].path.clone());
+        self.play_decoder(&self.tracklist[track_index].path.clone(), decoder)
+    }
+
+    /// Returns index of the track after the current one (wraps around to the first track).
+    pub fn get_next_track_index(&self) -> Option<usize> {
+        if self.tracklist.is_empty() {
+            return None;
+        }
+
+        match self.current_track_index {
+            None => Some(0),
+            Some(index) => Some((index + 1) % self.tracklist.len()),
+        }
+    }
+
+    /// Returns index of the track before the current one (wraps around to the last track).
+    pub fn get_previous_track_index(&self) -> Option<usize> {
+        if self.tracklist.is_empty() {
+            return None;
+        }
+
+        match self.current_track_index {
+            None | Some(0) => Some(self.tracklist.len() - 1),
+            Some(index) => Some(index - 1),
+        }
     }
 
-    pub fn play(&mut self, path: &str) {
+    /// Plays the track after the current one (wraps around to the first track).
+    pub fn play_next_track(&mut self) -> Result<(), String> {
+        match self.get_next_track_index() {
+            None => Ok(()),
+            Some(index) => self.play_track(index),
+        }
+    }
+
+    /// Plays the track before the current one (wraps around to the last track).
+    pub fn play_previous_track(&mut self) -> Result<(), String> {
+        match self.get_previous_track_index() {
+            None => Ok(()),
+            Some(index) => self.play_track(index),
+        }
+    }
+
+    /// Plays the file, returns an error if it can't be decoded or played
+    /// (the previous sound is stopped in any case).
+    pub fn play(&mut self, path: &str) -> Result<(), String> {
+        self.play_decoder(path, Self::open_sound(path))
+    }
+
+    /// Opens the file for playing, does not need the player so it can be called
+    /// without locking it (opening a file can take a while).
+    pub fn open_sound(path: &str) -> Result<SoundDecoder, String> {
+        SoundDecoder::new(path)
+            .map_err(|msg| format!("failed to create sound data, error: {}", msg))
+    }
+
+    fn play_decoder(
+        &mut self,
+        path: &str,
+        decoder: Result<SoundDecoder, String>,
+    ) -> Result<(), String> {
         // Stop any sound if we are playing.
-        if let Some(data) = self.current_sound.as_mut() {
-            data.handle.stop(Tween::default());
+        let stop_tween = Self::get_fade_tween(self.fade_config.stop_ms);
+        if let Some(mut data) = self.current_sound.take() {
+            data.handle.stop(stop_tween);
         }
 
-        // Create sound data.
-        let sound_data = match StreamingSoundData::from_file(path) {
-            Err(msg) => {
-                MessageDialog::new()
-                    .set_title("Critical error")
-                    .set_text(&format!("failed to create sound data, error: {}", msg))
-                    .show_alert()
-                    .unwrap();
-                panic!();
-            }
-            Ok(data) => data,
-        };
+        // Loop markers belong to the previous track.
+        self.ab_loop.clear();
 
-        let duration = sound_data.duration();
+        let decoder = decoder?;
+        let num_frames = decoder.num_frames();
+        let sample_rate = decoder.sample_rate();
+        let sound_data = StreamingSoundData::from_decoder(decoder);
 
         // Play sound.
-        self.current_sound = match self.audio_manager.play(sound_data) {
-            Ok(handle) => Some(CurrentSoundData::new(
-                path,
-                handle,
-                duration.as_secs() as f64,
-            )),
-            Err(msg) => {
-                MessageDialog::new()
-                    .set_title("Critical error")
-                    .set_text(&format!("failed to play sound data, error: {}", msg))
-                    .show_alert()
-                    .unwrap();
-                panic!();
-            }
-        };
-
-        // Set playback rate because we set it per-sound.
+        let handle = self
+            .audio_manager
+            .play(sound_data)
+            .map_err(|msg| format!("failed to play sound data, error: {}", msg))?;
+        self.current_sound = Some(CurrentSoundData::new(
+            path,
+            handle,
+            num_frames,
+            sample_rate,
+            self.wave_cache.clone(),
+            self.spectrogram_config
+                .clone()
+                .filter(|_| self.is_spectrogram_shown),
+            self.loudness_cache.clone(),
+        ));
+
+        // Set playback rate because we set it per-sound (also applies pitch of the track).
         self.set_playback_rate(self.playback_rate);
+        self.update_vocal_reduction();
+        self.update_replay_gain(Tween::default());
+
+        self.emit_event(PlayerEvent::TrackStarted);
+        Ok(())
     }
 
-    pub fn get_current_sound_wave(&self) -> Arc<Mutex<Vec<u8>>> {
+    pub fn get_current_sound_wave(&self) -> Arc<Mutex<SoundWave>> {
         if let Some(data) = self.current_sound.as_ref() {
             return data.wave.clone();
         }
 
-        Arc::new(Mutex::new(Vec::new()))
+        Arc::new(Mutex::new(SoundWave::default()))
+    }
+
+    /// Returns the analyzer of the audio that is actually playing.
+    pub fn get_output_analyzer(&self) -> Arc<Mutex<OutputAnalyzer>> {
+        self.output_analyzer.clone()
+    }
+
+    pub fn get_current_sound_spectrogram(&self) -> Arc<Mutex<Spectrogram>> {
+        if let Some(data) = self.current_sound.as_ref() {
+            return data.spectrogram.clone();
+        }
+
+        Arc::new(Mutex::new(Spectrogram::default()))
     }
 
-    /// Returns the number of seconds passed since the start of the sound.
-    pub fn get_current_sound_position(&self) -> f64 {
+    /// Returns time passed since the start of the sound.
+    pub fn get_current_sound_position(&self) -> Duration {
         // Quit if no sound.
         if self.current_sound.is_none() {
-            return 0.0;
+            return Duration::ZERO;
         }
 
         let sound_data = self.current_sound.as_ref().unwrap();
 
-        sound_data.handle.position()
+        Self::get_sound_position(sound_data)
     }
 
-    /// Returns length of the sound in seconds.
-    pub fn get_current_sound_duration(&self) -> f64 {
+    /// Returns length of the sound.
+    pub fn get_current_sound_duration(&self) -> Duration {
         // Quit if no sound.
         if self.current_sound.is_none() {
-            return 0.0;
+            return Duration::ZERO;
         }
 
         let sound_data = self.current_sound.as_ref().unwrap();
@@ -328,8 +787,8 @@ impl AudioPlayer {
         sound_data.duration
     }
 
-    /// Sets position of the sound in seconds.
-    pub fn set_current_sound_pos(&mut self, pos: f64) {
+    /// Sets position of the sound (clamped to the sound's length).
+    pub fn set_current_sound_pos(&mut self, pos: Duration) {
         // Quit if no sound.
         if self.current_sound.is_none() {
             return;
@@ -337,7 +796,64 @@ impl AudioPlayer {
 
         let sound_data = self.current_sound.as_mut().unwrap();
 
-        sound_data.handle.seek_to(pos);
+        let pos = pos.min(sound_data.duration);
+        self.seek_current_sound(pos);
+        self.ab_loop.on_user_action(pos);
+    }
+
+    /// Moves the current sound to the position. While playing, the sound is restarted
+    /// at the new position and crossfaded with the old one to avoid a click.
+    fn seek_current_sound(&mut self, pos: Duration) {
+        let crossfade = Self::get_fade_tween(self.fade_config.seek_crossfade_ms);
+        let sound_data = match self.current_sound.as_mut() {
+            Some(sound_data) => sound_data,
+            None => return,
+        };
+
+        if crossfade.duration.is_zero() || sound_data.handle.state() != PlaybackState::Playing {
+            sound_data.handle.seek_to(pos.as_secs_f64());
+            return;
+        }
+
+        // The length is known so the file is not scanned again.
+        let new_handle = SoundDecoder::with_frame_count(&sound_data.path, sound_data.num_frames)
+            .map_err(|error| error.to_string())
+            .and_then(|decoder| {
+                let sound = StreamingSoundData::from_decoder(decoder)
+                    .start_position(pos.as_secs_f64())
+                    .playback_rate(self.playback_rate)
+                    .volume(Decibels(sound_data.applied_replay_gain_db.unwrap_or(0.0)))
+                    .fade_in_tween(crossfade);
+                self.audio_manager
+                    .play(sound)
+                    .map_err(|error| error.to_string())
+            });
+
+        match new_handle {
+            Ok(new_handle) => {
+                let mut old_handle = std::mem::replace(&mut sound_data.handle, new_handle);
+                old_handle.stop(crossfade);
+            }
+            // Fall back to the usual seek.
+            Err(_) => sound_data.handle.seek_to(pos.as_secs_f64()),
+        }
+    }
+
+    /// Returns a linear fade of the specified duration.
+    fn get_fade_tween(duration_ms: u64) -> Tween {
+        Tween {
+            duration: Duration::from_millis(duration_ms),
+            ..Default::default()
+        }
+    }
+
+    /// Sets durations of fades on pause, resume, stop and seek.
+    pub fn set_fade_config(&mut self, config: FadeConfig) {
+        self.fade_config = config;
+    }
+
+    fn get_sound_position(sound_data: &CurrentSoundData) -> Duration {
+        Duration::from_secs_f64(sound_data.handle.position().max(0.0)).min(sound_data.duration)
     }
 
     /// Stops the sound (if playing).
@@ -347,9 +863,12 @@ impl AudioPlayer {
             return;
         }
 
+        self.emit_event(PlayerEvent::Stopped);
+
+        let stop_tween = Self::get_fade_tween(self.fade_config.stop_ms);
         let sound_data = self.current_sound.as_mut().unwrap();
 
-        sound_data.handle.stop(Tween::default());
+        sound_data.handle.stop(stop_tween);
         self.current_sound = None;
     }
 
@@ -361,12 +880,117 @@ impl AudioPlayer {
             return;
         }
 
+        let position = self.get_current_sound_position();
+        self.ab_loop.on_user_action(position);
+
+        let pause_tween = Self::get_fade_tween(self.fade_config.pause_ms);
+        let resume_tween = Self::get_fade_tween(self.fade_config.resume_ms);
         let sound_data = self.current_sound.as_mut().unwrap();
 
-        if sound_data.handle.state() == PlaybackState::Paused {
-            sound_data.handle.resume(Tween::default());
+        // The sound can be resumed while it's fading out.
+        if matches!(
+            sound_data.handle.state(),
+            PlaybackState::Paused | PlaybackState::Pausing
+        ) {
+            sound_data.handle.resume(resume_tween);
+            self.emit_event(PlayerEvent::Resumed);
         } else {
-            sound_data.handle.pause(Tween::default());
+            sound_data.handle.pause(pause_tween);
+            self.emit_event(PlayerEvent::Paused);
+        }
+    }
+
+    /// Tells if there is a sound that is currently playing or paused.
+    pub fn is_sound_loaded(&self) -> bool {
+        self.current_sound.is_some()
+    }
+
+    /// Tells if the current sound is paused (returns `false` if no sound).
+    pub fn is_paused(&self) -> bool {
+        match self.current_sound.as_ref() {
+            None => false,
+            Some(sound_data) => matches!(
+                sound_data.handle.state(),
+                PlaybackState::Paused | PlaybackState::Pausing
+            ),
+        }
+    }
+
+    /// Pauses or resumes the current sound (if there is one).
+    pub fn set_paused(&mut self, pause: bool) {
+        if self.is_paused() != pause {
+            self.pause_resume();
+        }
+    }
+
+    pub fn get_ab_loop(&self) -> &AbLoop {
+        &self.ab_loop
+    }
+
+    /// Sets the number of times the A-B loop is played (0 to repeat until the loop is cleared)
+    /// and the gap between repetitions.
+    pub fn set_ab_loop_options(&mut self, repetitions: u32, gap: Duration) {
+        self.ab_loop.set_options(repetitions, gap);
+    }
+
+    /// Places a marker of the A-B loop at the specified position of the current sound.
+    pub fn set_ab_loop_marker(&mut self, marker: LoopMarker, position: Duration) {
+        if self.current_sound.is_none() {
+            return;
+        }
+
+        let position = position.min(self.get_current_sound_duration());
+        self.ab_loop.set_marker(marker, position);
+    }
+
+    /// Removes markers of the A-B loop.
+    pub fn clear_ab_loop(&mut self) {
+        self.ab_loop.clear();
+    }
+
+    /// Returns how long the track switch thread sleeps before the next check. While looping
+    /// it wakes up when the position reaches "B" so that playback doesn't go past it.
+    fn get_check_interval(&self) -> Duration {
+        let interval = Duration::from_millis(TRACK_END_CHECK_INTERVAL_MS);
+        if !self.ab_loop.is_active() || self.ab_loop.is_finished() {
+            return interval;
+        }
+
+        // Wait for the end of the gap between repetitions.
+        if self.is_paused() {
+            return Duration::from_millis(AB_LOOP_CHECK_INTERVAL_MS);
+        }
+
+        let end = self.ab_loop.get_end().unwrap_or_default();
+        let until_end = end
+            .saturating_sub(self.get_current_sound_position())
+            .div_f64(self.playback_rate);
+        until_end.clamp(Duration::from_millis(MIN_CHECK_INTERVAL_MS), interval)
+    }
+
+    /// Returns to the start of the A-B loop when the position passes its end.
+    fn update_ab_loop(&mut self) {
+        let position = self.get_current_sound_position();
+        let is_paused = self.is_paused();
+        let pause_tween = Self::get_fade_tween(self.fade_config.pause_ms);
+        let resume_tween = Self::get_fade_tween(self.fade_config.resume_ms);
+        if self.current_sound.is_none() {
+            return;
+        }
+
+        match self.ab_loop.check(position, is_paused) {
+            LoopAction::None => {}
+            LoopAction::Seek(start) => self.seek_current_sound(start),
+            LoopAction::Pause => {
+                let sound_data = self.current_sound.as_mut().unwrap();
+                sound_data.handle.pause(pause_tween);
+            }
+            // The seek waits until the fade out is over so that the start isn't heard in it.
+            LoopAction::SeekAndResume(start) => {
+                let sound_data = self.current_sound.as_mut().unwrap();
+                sound_data.handle.seek_to(start.as_secs_f64());
+                sound_data.handle.resume(resume_tween);
+            }
         }
     }
 
@@ -377,8 +1001,7 @@ impl AudioPlayer {
         // Convert from linear to decibels.
         let decibels = 20.0 * volume.log10();
 
-        self.audio_manager
-            .main_track()
+        self.volume_control
             .set_volume(decibels as f32, Tween::default())
     }
 
@@ -387,11 +1010,16 @@ impl AudioPlayer {
         self.volume
     }
 
-    /// Sets playback speed multiplier where 1.0 is "original speed".
+    /// Sets playback speed multiplier where 1.0 is "original speed"
+    /// (clamped to [`AudioPlayer::get_playback_rate_range`]).
     pub fn set_playback_rate(&mut self, rate: f64) {
         // Save rate.
+        let range = self.get_playback_rate_range();
+        let rate = rate.clamp(*range.start(), *range.end());
         self.playback_rate = rate;
 
+        self.update_pitch_shift();
+
         // Quit if no sound.
         if self.current_sound.is_none() {
             return;
@@ -409,4 +1037,244 @@ impl AudioPlayer {
     pub fn get_playback_rate(&self) -> f64 {
         self.playback_rate
     }
+
+    /// Returns allowed playback speed multipliers for the current time stretch mode.
+    pub fn get_playback_rate_range(&self) -> RangeInclusive<f64> {
+        match self.time_stretch_mode {
+            TimeStretchMode::Tape => PLAYBACK_RATE_RANGE,
+            TimeStretchMode::Stretch => STRETCH_PLAYBACK_RATE_RANGE,
+        }
+    }
+
+    /// Sets whether the playback rate changes the pitch, the rate is clamped to the new range.
+    pub fn set_time_stretch_mode(&mut self, mode: TimeStretchMode) {
+        self.time_stretch_mode = mode;
+        self.set_playback_rate(self.playback_rate);
+    }
+
+    pub fn get_time_stretch_mode(&self) -> TimeStretchMode {
+        self.time_stretch_mode
+    }
+
+    /// Sets pitch shift (in cents, clamped to [`PITCH_CENTS_RANGE`]) of the track
+    /// with the specified ID.
+    pub fn set_track_pitch(&mut self, track_id: u32, cents: i32) {
+        let Some(index) = self.find_track_index(track_id) else {
+            return;
+        };
+
+        let cents = cents.clamp(*PITCH_CENTS_RANGE.start(), *PITCH_CENTS_RANGE.end());
+        if self.tracklist[index].pitch_cents == cents {
+            return;
+        }
+        self.tracklist[index].pitch_cents = cents;
+        self.on_tracklist_changed();
+
+        if self.current_track_index == Some(index) {
+            self.update_pitch_shift();
+        }
+    }
+
+    /// Returns pitch shift (in cents) of the current track.
+    pub fn get_current_pitch_cents(&self) -> i32 {
+        self.current_track_index
+            .and_then(|index| self.tracklist.get(index))
+            .map_or(0, |track| track.pitch_cents)
+    }
+
+    /// Sets the pitch shifter to the pitch of the current track, compensating the pitch
+    /// change caused by resampling if the pitch should not depend on the playback rate.
+    fn update_pitch_shift(&mut self) {
+        let rate_compensation = match self.time_stretch_mode {
+            TimeStretchMode::Tape => 1.0,
+            TimeStretchMode::Stretch => 1.0 / self.playback_rate,
+        };
+        let transposition = 2.0f64.powf(self.get_current_pitch_cents() as f64 / 1200.0);
+
+        self.pitch_shifter
+            .set_pitch((transposition * rate_compensation) as f32);
+    }
+
+    /// Turns vocal reduction of the track with the specified ID on or off.
+    pub fn set_track_vocal_reduction(&mut self, track_id: u32, enabled: bool) {
+        let Some(index) = self.find_track_index(track_id) else {
+            return;
+        };
+        if self.tracklist[index].vocal_reduction == enabled {
+            return;
+        }
+        self.tracklist[index].vocal_reduction = enabled;
+        self.on_tracklist_changed();
+
+        if self.current_track_index == Some(index) {
+            self.update_vocal_reduction();
+        }
+    }
+
+    /// Tells if vocals of the current track are reduced.
+    pub fn is_current_vocal_reduction_enabled(&self) -> bool {
+        self.current_track_index
+            .and_then(|index| self.tracklist.get(index))
+            .is_some_and(|track| track.vocal_reduction)
+    }
+
+    /// Sets strength of vocal reduction and whether the bass is kept.
+    pub fn set_vocal_reduction_config(&mut self, config: VocalReductionConfig) {
+        self.vocal_reducer
+            .set_preserved_bass(Some(config.bass_cutoff_hz).filter(|_| config.preserve_bass));
+        self.vocal_reduction_config = config;
+        self.update_vocal_reduction();
+    }
+
+    /// Enables the vocal reducer if the current track needs it.
+    fn update_vocal_reduction(&mut self) {
+        self.vocal_reducer
+            .set_strength(if self.is_current_vocal_reduction_enabled() {
+                self.vocal_reduction_config.strength
+            } else {
+                0.0
+            });
+    }
+
+    /// Starts the sleep timer (replaces the previous one).
+    pub fn set_sleep_timer(&mut self, target: SleepTarget) {
+        self.sleep_timer.set_target(target);
+    }
+
+    /// Starts the sleep timer that pauses after the specified time.
+    pub fn set_sleep_timer_duration(&mut self, duration: Duration) {
+        self.sleep_timer.set_duration(duration);
+    }
+
+    /// Starts the sleep timer that pauses at the clock time.
+    pub fn set_sleep_timer_clock_time(&mut self, time: chrono::NaiveTime) {
+        self.sleep_timer.set_clock_time(time);
+    }
+
+    /// Stops the sleep timer (the volume returns if it was fading).
+    pub fn cancel_sleep_timer(&mut self) {
+        self.sleep_timer.cancel();
+    }
+
+    pub fn get_sleep_timer_target(&self) -> Option<SleepTarget> {
+        self.sleep_timer.get_target()
+    }
+
+    /// Returns real time left until the sleep timer pauses the playback
+    /// (`None` if the timer is not active or the time is not known yet).
+    pub fn get_sleep_timer_remaining(&self) -> Option<Duration> {
+        let track_remaining = self
+            .get_current_sound_duration()
+            .saturating_sub(self.get_current_sound_position())
+            .div_f64(self.playback_rate);
+        let is_last_track = self
+            .current_track_index
+            .is_some_and(|index| index + 1 == self.tracklist.len());
+
+        self.sleep_timer
+            .get_remaining(track_remaining, is_last_track)
+    }
+
+    /// Returns text like "Sleep: 12:34" (`None` if the sleep timer is not active).
+    pub fn get_sleep_timer_status_text(&self) -> Option<String> {
+        self.sleep_timer
+            .get_status_text(self.get_sleep_timer_remaining())
+    }
+
+    /// Fades the volume out before the sleep timer goes off and pauses when it does
+    /// (timers waiting for the end of a track are handled when the track ends).
+    fn update_sleep_timer(&mut self) {
+        let remaining = self.get_sleep_timer_remaining();
+        if let Some(SleepTarget::Deadline(_)) = self.sleep_timer.get_target() {
+            if remaining.is_some_and(|remaining| remaining.is_zero()) {
+                self.sleep_timer.cancel();
+                self.set_paused(true);
+            }
+        }
+
+        // Keep the volume down while paused by the timer, bring it back when the playback
+        // continues.
+        let gain = self.sleep_timer.get_fade_gain(remaining);
+        if gain == self.sleep_fade_gain || (!self.sleep_timer.is_active() && self.is_paused()) {
+            return;
+        }
+        self.sleep_fade_gain = gain;
+
+        let decibels = if gain > 0.001 {
+            Decibels(20.0 * gain.log10())
+        } else {
+            Decibels::SILENCE
+        };
+        self.sleep_fader.set_volume(
+            decibels,
+            Tween {
+                duration: Duration::from_millis(TRACK_END_CHECK_INTERVAL_MS),
+                ..Default::default()
+            },
+        );
+    }
+
+    /// Returns the handle of the limiter that keeps the output from clipping.
+    pub fn get_limiter(&self) -> LimiterHandle {
+        self.limiter.clone()
+    }
+
+    pub fn get_equalizer(&self) -> &Equalizer {
+        &self.equalizer
+    }
+
+    /// Sets gains of the equalizer bands in decibels (from the lowest frequency).
+    pub fn set_equalizer_gains(&mut self, gains: &[f32]) {
+        self.equalizer.set_gains(gains);
+    }
+
+    /// Sets gain of one equalizer band in decibels.
+    pub fn set_equalizer_band_gain(&mut self, band: usize, gain: f32) {
+        self.equalizer.set_band_gain(band, gain);
+    }
+
+    pub fn set_equalizer_bypass(&mut self, bypass: bool) {
+        self.equalizer.set_bypass(bypass);
+    }
+
+    /// Sets balance, mono downmix, channel swap and muted channels.
+    pub fn set_stereo_config(&mut self, config: StereoConfig) {
+        self.stereo_control.set_options(&config);
+        self.stereo_config = config;
+    }
+
+    pub fn get_stereo_config(&self) -> &StereoConfig {
+        &self.stereo_config
+    }
+
+    /// Sets compressor parameters, the compressor only changes the sound in the night mode.
+    pub fn set_compressor_config(&mut self, config: CompressorConfig) {
+        // Ratio of 1:1 with no makeup gain leaves the sound as is.
+        let (ratio, makeup_gain_db) = if config.night_mode {
+            (config.ratio.max(1.0), config.makeup_gain_db)
+        } else {
+            (1.0, 0.0)
+        };
+
+        let tween = Tween::default();
+        self.compressor
+            .set_threshold(config.threshold_db as f64, tween);
+        self.compressor.set_ratio(ratio as f64, tween);
+        self.compressor.set_attack_duration(
+            Duration::from_secs_f32(config.attack_ms.max(0.1) / 1000.0),
+            tween,
+        );
+        self.compressor.set_release_duration(
+            Duration::from_secs_f32(config.release_ms.max(0.1) / 1000.0),
+            tween,
+        );
+        self.compressor
+            .set_makeup_gain(Decibels(makeup_gain_db), tween);
+
+        self.compressor_config = config;
+    }
+
+    pub fn get_compressor_config(&self) -> &CompressorConfig {
+        &self.compressor_config
+    }
 }
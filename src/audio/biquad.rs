@@ -0,0 +1,48 @@
+use std::f64::consts::PI;
+
+/// Second order IIR filter.
+#[derive(Clone, Copy, Default)]
+pub struct Biquad {
+    /// Feedforward coefficients.
+    b: [f64; 3],
+    /// Feedback coefficients (normalized so that `a0` is 1.0).
+    a: [f64; 2],
+    x: [f64; 2],
+    y: [f64; 2],
+}
+
+impl Biquad {
+    pub fn new(b: [f64; 3], a: [f64; 2]) -> Self {
+        Self {
+            b,
+            a,
+            ..Default::default()
+        }
+    }
+
+    /// Creates a low-pass filter (as in the Audio EQ Cookbook).
+    pub fn low_pass(sample_rate: f64, cutoff_hz: f64, q: f64) -> Self {
+        let w0 = 2.0 * PI * (cutoff_hz / sample_rate).clamp(1e-5, 0.49);
+        let alpha = w0.sin() / (2.0 * q);
+        let cos_w0 = w0.cos();
+        let a0 = 1.0 + alpha;
+
+        Self::new(
+            [
+                (1.0 - cos_w0) / 2.0 / a0,
+                (1.0 - cos_w0) / a0,
+                (1.0 - cos_w0) / 2.0 / a0,
+            ],
+            [-2.0 * cos_w0 / a0, (1.0 - alpha) / a0],
+        )
+    }
+
+    pub fn process(&mut self, input: f64) -> f64 {
+        let output = self.b[0] * input + self.b[1] * self.x[0] + self.b[2] * self.x[1]
+            - self.a[0] * self.y[0]
+            - self.a[1] * self.y[1];
+        self.x = [input, self.x[0]];
+        self.y = [output, self.y[0]];
+        output
+    }
+}
@@ -0,0 +1,134 @@
+use kira::effect::eq_filter::{EqFilterBuilder, EqFilterHandle, EqFilterKind};
+use kira::track::MainTrackBuilder;
+use kira::{Decibels, Tween};
+use std::ops::RangeInclusive;
+
+/// Center frequencies of the bands (one octave apart).
+pub const EQ_BAND_FREQUENCIES_HZ: [f64; EQ_BAND_COUNT] = [
+    31.0, 62.0, 125.0, 250.0, 500.0, 1000.0, 2000.0, 4000.0, 8000.0, 16000.0,
+];
+
+pub const EQ_BAND_COUNT: usize = 10;
+
+/// Allowed gain of a band in decibels.
+pub const EQ_GAIN_RANGE_DB: RangeInclusive<f32> = -12.0..=12.0;
+
+/// Width of a band, this value makes neighboring bands overlap a bit so that
+/// setting all bands to the same gain gives a mostly flat response.
+const EQ_BAND_Q: f64 = 1.41;
+
+/// Named gains of all bands.
+pub struct EqPreset {
+    pub name: &'static str,
+    /// Gains of the bands in decibels (from the lowest frequency).
+    pub gains: [f32; EQ_BAND_COUNT],
+}
+
+pub const BUILTIN_EQ_PRESETS: &[EqPreset] = &[
+    EqPreset {
+        name: "Flat",
+        gains: [0.0; EQ_BAND_COUNT],
+    },
+    EqPreset {
+        name: "Bass Boost",
+        gains: [6.0, 5.0, 4.0, 2.0, 0.5, 0.0, 0.0, 0.0, 0.0, 0.0],
+    },
+    EqPreset {
+        name: "Treble Boost",
+        gains: [0.0, 0.0, 0.0, 0.0, 0.0, 0.5, 2.0, 4.0, 5.0, 6.0],
+    },
+    EqPreset {
+        name: "Vocal",
+        gains: [-3.0, -2.0, -1.0, 1.0, 3.0, 4.0, 3.0, 1.5, 0.0, -1.0],
+    },
+    EqPreset {
+        name: "Rock",
+        gains: [4.5, 3.5, 2.0, 0.0, -1.0, -1.0, 1.0, 2.5, 3.5, 4.0],
+    },
+    EqPreset {
+        name: "Pop",
+        gains: [-1.0, 1.0, 3.0, 4.0, 3.0, 0.0, -1.0, -1.0, 1.0, 2.0],
+    },
+    EqPreset {
+        name: "Jazz",
+        gains: [3.0, 2.0, 1.0, 2.0, -1.0, -1.0, 0.0, 1.0, 2.0, 3.0],
+    },
+    EqPreset {
+        name: "Classical",
+        gains: [4.0, 3.0, 2.0, 1.0, 0.0, 0.0, 0.0, 1.0, 2.0, 3.0],
+    },
+    EqPreset {
+        name: "Loudness",
+        gains: [6.0, 4.0, 1.5, 0.0, -1.0, 0.0, 0.0, 1.0, 4.0, 6.0],
+    },
+];
+
+/// Graphic equalizer made of bell filters on the main track.
+pub struct Equalizer {
+    handles: Vec<EqFilterHandle>,
+    gains: [f32; EQ_BAND_COUNT],
+    bypass: bool,
+}
+
+impl Equalizer {
+    /// Adds filters of all bands (with no gain) to the track.
+    pub fn add_to_track(track_builder: &mut MainTrackBuilder) -> Self {
+        let handles = EQ_BAND_FREQUENCIES_HZ
+            .iter()
+            .map(|&frequency| {
+                track_builder.add_effect(EqFilterBuilder::new(
+                    EqFilterKind::Bell,
+                    frequency,
+                    Decibels::IDENTITY,
+                    EQ_BAND_Q,
+                ))
+            })
+            .collect();
+
+        Self {
+            handles,
+            gains: [0.0; EQ_BAND_COUNT],
+            bypass: false,
+        }
+    }
+
+    /// Returns gains of the bands in decibels (from the lowest frequency).
+    pub fn get_gains(&self) -> &[f32; EQ_BAND_COUNT] {
+        &self.gains
+    }
+
+    /// Sets gains of the bands in decibels (missing bands are set to 0 dB).
+    pub fn set_gains(&mut self, gains: &[f32]) {
+        for band in 0..EQ_BAND_COUNT {
+            self.set_band_gain(band, gains.get(band).copied().unwrap_or(0.0));
+        }
+    }
+
+    /// Sets gain of a band in decibels (clamped to [`EQ_GAIN_RANGE_DB`]).
+    pub fn set_band_gain(&mut self, band: usize, gain: f32) {
+        if band >= EQ_BAND_COUNT {
+            return;
+        }
+
+        self.gains[band] = gain.clamp(*EQ_GAIN_RANGE_DB.start(), *EQ_GAIN_RANGE_DB.end());
+        self.apply_band(band);
+    }
+
+    pub fn is_bypassed(&self) -> bool {
+        self.bypass
+    }
+
+    /// Disables (or enables back) all bands without forgetting their gains.
+    pub fn set_bypass(&mut self, bypass: bool) {
+        self.bypass = bypass;
+        for band in 0..EQ_BAND_COUNT {
+            self.apply_band(band);
+        }
+    }
+
+    fn apply_band(&mut self, band: usize) {
+        // A bell filter with no gain does not change the sound.
+        let gain = if self.bypass { 0.0 } else { self.gains[band] };
+        self.handles[band].set_gain(Decibels(gain), Tween::default());
+    }
+}
@@ -0,0 +1,95 @@
+use crate::misc::config_manger::WindowFunction;
+use std::f32::consts::PI;
+
+/// Returns coefficients of the window function that samples are multiplied by before
+/// the Fourier transform.
+pub fn create_window(function: WindowFunction, size: usize) -> Vec<f32> {
+    let n = (size.max(2) - 1) as f32;
+    (0..size)
+        .map(|i| {
+            let x = 2.0 * PI * i as f32 / n;
+            match function {
+                WindowFunction::Rectangular => 1.0,
+                WindowFunction::Hann => 0.5 - 0.5 * x.cos(),
+                WindowFunction::Hamming => 0.54 - 0.46 * x.cos(),
+                WindowFunction::Blackman => 0.42 - 0.5 * x.cos() + 0.08 * (2.0 * x).cos(),
+            }
+        })
+        .collect()
+}
+
+/// Returns the power of the bin of a full scale sine after the window is applied
+/// (used to convert powers to decibels relative to full scale).
+pub fn get_full_scale_power(window: &[f32]) -> f32 {
+    let window_sum: f32 = window.iter().sum();
+    (window_sum / 2.0).powi(2).max(f32::EPSILON)
+}
+
+/// Splits frequencies from `min_frequency` to half of the sample rate into bands that are
+/// spread evenly on the logarithmic axis, returns range of bins that each band covers.
+pub fn get_log_band_bins(
+    band_count: usize,
+    window_size: usize,
+    sample_rate: u32,
+    min_frequency: f32,
+) -> Vec<(usize, usize)> {
+    let max_frequency = sample_rate.max(1) as f32 / 2.0;
+    let min_frequency = min_frequency.clamp(1.0, max_frequency / 2.0);
+    let bin_width = sample_rate.max(1) as f32 / window_size as f32;
+    let frequency_at = |band: usize| {
+        min_frequency * (max_frequency / min_frequency).powf(band as f32 / band_count as f32)
+    };
+
+    (0..band_count)
+        .map(|band| {
+            let start = ((frequency_at(band) / bin_width) as usize).min(window_size / 2 - 1);
+            let end = ((frequency_at(band + 1) / bin_width).ceil() as usize)
+                .clamp(start + 1, window_size / 2);
+            (start, end)
+        })
+        .collect()
+}
+
+/// In-place radix-2 fast Fourier transform (the length must be a power of two).
+pub fn fft(real: &mut [f32], imag: &mut [f32]) {
+    let n = real.len();
+
+    // Reorder the input in bit-reversed order.
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            real.swap(i, j);
+            imag.swap(i, j);
+        }
+    }
+
+    let mut len = 2;
+    while len <= n {
+        let angle = -2.0 * PI / len as f32;
+        let (step_imag, step_real) = angle.sin_cos();
+        for start in (0..n).step_by(len) {
+            let (mut w_real, mut w_imag) = (1.0f32, 0.0f32);
+            for k in 0..len / 2 {
+                let a = start + k;
+                let b = a + len / 2;
+                let t_real = real[b] * w_real - imag[b] * w_imag;
+                let t_imag = real[b] * w_imag + imag[b] * w_real;
+                real[b] = real[a] - t_real;
+                imag[b] = imag[a] - t_imag;
+                real[a] += t_real;
+                imag[a] += t_imag;
+
+                let next_w_real = w_real * step_real - w_imag * step_imag;
+                w_imag = w_real * step_imag + w_imag * step_real;
+                w_real = next_w_real;
+            }
+        }
+        len <<= 1;
+    }
+}
@@ -0,0 +1,178 @@
+use kira::effect::{Effect, EffectBuilder};
+use kira::info::Info;
+use kira::Frame;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+
+/// The highest allowed sample value (just below full scale).
+const CEILING: f32 = 0.98;
+
+/// How far ahead the limiter looks for peaks (the output is delayed by this time).
+const LOOK_AHEAD_SEC: f64 = 0.005;
+
+/// Time constant of the gain returning back to 1.0 after a peak.
+const RELEASE_SEC: f64 = 0.1;
+
+/// Keeps samples from going over 0 dBFS (for example when the volume is above 100%).
+///
+/// Every frame gets the gain it needs to fit under the ceiling. The gain is the minimum of
+/// the needed gains over the look-ahead window smoothed by a moving average of the same
+/// length, so it reaches the needed value exactly when the peak is played without a step.
+pub struct LimiterBuilder {
+    lowest_gain: Arc<AtomicU32>,
+}
+
+/// Tells how much the [`LimiterBuilder`] effect is reducing the gain.
+#[derive(Clone)]
+pub struct LimiterHandle {
+    lowest_gain: Arc<AtomicU32>,
+}
+
+struct Limiter {
+    /// The lowest gain since the handle was last asked (as bits of `f32`, for positive
+    /// floats comparing bits is the same as comparing values).
+    lowest_gain: Arc<AtomicU32>,
+    /// Delayed input frames (circular buffer).
+    delay_line: Vec<Frame>,
+    delay_pos: usize,
+    /// Needed gains of the look-ahead window that may become the minimum
+    /// (frame index, gain), increasing gains.
+    min_candidates: VecDeque<(usize, f32)>,
+    /// The last minimums of the window (circular buffer) and their sum.
+    window_minimums: Vec<f32>,
+    window_sum: f64,
+    frame_index: usize,
+    gain: f32,
+    release_factor: f32,
+}
+
+impl LimiterBuilder {
+    pub fn new() -> (Self, LimiterHandle) {
+        let lowest_gain = Arc::new(AtomicU32::new(1.0f32.to_bits()));
+
+        (
+            Self {
+                lowest_gain: lowest_gain.clone(),
+            },
+            LimiterHandle { lowest_gain },
+        )
+    }
+}
+
+impl LimiterHandle {
+    /// Returns how much the gain was reduced (in decibels, 0.0 if the limiter was not
+    /// limiting) since the last call.
+    pub fn take_gain_reduction_db(&self) -> f32 {
+        let lowest_gain =
+            f32::from_bits(self.lowest_gain.swap(1.0f32.to_bits(), Ordering::Relaxed));
+        -20.0 * lowest_gain.max(1e-10).log10()
+    }
+}
+
+impl EffectBuilder for LimiterBuilder {
+    type Handle = ();
+
+    fn build(self) -> (Box<dyn Effect>, Self::Handle) {
+        (
+            Box::new(Limiter {
+                lowest_gain: self.lowest_gain,
+                delay_line: Vec::new(),
+                delay_pos: 0,
+                min_candidates: VecDeque::new(),
+                window_minimums: Vec::new(),
+                window_sum: 0.0,
+                frame_index: 0,
+                gain: 1.0,
+                release_factor: 0.0,
+            }),
+            (),
+        )
+    }
+}
+
+impl Limiter {
+    fn allocate(&mut self, sample_rate: u32) {
+        let sample_rate = sample_rate.max(1) as f64;
+        let window_length = ((LOOK_AHEAD_SEC * sample_rate) as usize).max(2);
+
+        // The frame leaves the delay line when the moving average covers only the windows
+        // that contain the frame.
+        self.delay_line = vec![Frame::ZERO; window_length - 1];
+        self.delay_pos = 0;
+        self.min_candidates = VecDeque::with_capacity(window_length + 1);
+        self.window_minimums = vec![1.0; window_length];
+        self.window_sum = window_length as f64;
+        self.frame_index = 0;
+        self.gain = 1.0;
+        self.release_factor = (-1.0 / (RELEASE_SEC * sample_rate)).exp() as f32;
+    }
+}
+
+impl Effect for Limiter {
+    fn init(&mut self, sample_rate: u32, _internal_buffer_size: usize) {
+        self.allocate(sample_rate);
+    }
+
+    fn on_change_sample_rate(&mut self, sample_rate: u32) {
+        self.allocate(sample_rate);
+    }
+
+    fn process(&mut self, input: &mut [Frame], _dt: f64, _info: &Info) {
+        if self.delay_line.is_empty() {
+            return;
+        }
+
+        let window_length = self.window_minimums.len();
+        let mut lowest_gain = 1.0f32;
+        for frame in input.iter_mut() {
+            let peak = frame.left.abs().max(frame.right.abs());
+            let needed_gain = if peak > CEILING { CEILING / peak } else { 1.0 };
+
+            // Find the minimum of the needed gains of the window.
+            while self
+                .min_candidates
+                .back()
+                .is_some_and(|&(_, gain)| gain >= needed_gain)
+            {
+                self.min_candidates.pop_back();
+            }
+            self.min_candidates
+                .push_back((self.frame_index, needed_gain));
+            while self
+                .min_candidates
+                .front()
+                .is_some_and(|&(index, _)| index + window_length <= self.frame_index)
+            {
+                self.min_candidates.pop_front();
+            }
+            let window_minimum = self.min_candidates.front().map_or(1.0, |&(_, gain)| gain);
+
+            // Smooth minimums with a moving average.
+            let slot = self.frame_index % window_length;
+            self.window_sum += (window_minimum - self.window_minimums[slot]) as f64;
+            self.window_minimums[slot] = window_minimum;
+            if slot == 0 {
+                // Don't let rounding errors accumulate.
+                self.window_sum = self.window_minimums.iter().map(|&gain| gain as f64).sum();
+            }
+            let target_gain = (self.window_sum / window_length as f64).min(1.0) as f32;
+            self.frame_index += 1;
+
+            // Reduce the gain at once but bring it back slowly.
+            self.gain = if target_gain < self.gain {
+                target_gain
+            } else {
+                target_gain + (self.gain - target_gain) * self.release_factor
+            };
+            lowest_gain = lowest_gain.min(self.gain);
+
+            let delayed = std::mem::replace(&mut self.delay_line[self.delay_pos], *frame);
+            self.delay_pos = (self.delay_pos + 1) % self.delay_line.len();
+            *frame = delayed * self.gain;
+        }
+
+        self.lowest_gain
+            .fetch_min(lowest_gain.to_bits(), Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,235 @@
+use super::biquad::Biquad;
+use std::f64::consts::PI;
+
+/// Duration of the blocks that loudness is measured in (blocks overlap by 75%).
+const BLOCK_DURATION_SEC: f64 = 0.4;
+const STEPS_PER_BLOCK: usize = 4;
+
+/// Blocks quieter than this are ignored (silence between tracks, fade outs and so on).
+const ABSOLUTE_GATE_LUFS: f64 = -70.0;
+
+/// Blocks that are this much quieter than the average of the loud blocks are ignored.
+const RELATIVE_GATE_LU: f64 = -10.0;
+
+/// Integrated loudness and sample peak of a sound.
+#[derive(Clone, Copy, Debug)]
+pub struct Loudness {
+    /// Loudness in LUFS (as defined by EBU R128).
+    pub integrated_lufs: f32,
+    /// The highest absolute sample value (1.0 is full scale).
+    pub peak: f32,
+}
+
+/// Measures integrated loudness as described in ITU-R BS.1770 (used by EBU R128):
+/// channels are K-weighted, loudness is computed for overlapping 400 ms blocks
+/// and quiet blocks are gated out.
+pub struct LoudnessMeter {
+    /// Two K-weighting filter stages per channel.
+    filters: Vec<[Biquad; 2]>,
+    channel_weights: Vec<f64>,
+    frames_per_step: usize,
+    /// Weighted sum of squared samples of the current step (a quarter of a block).
+    step_energy: f64,
+    step_frame_count: usize,
+    /// Mean energies of the last steps.
+    recent_steps: Vec<f64>,
+    /// Mean energies of all blocks.
+    block_energies: Vec<f64>,
+    peak: f32,
+}
+
+impl LoudnessMeter {
+    pub fn new(sample_rate: u32, channel_count: usize) -> Self {
+        let sample_rate = sample_rate.max(1) as f64;
+
+        // Channel weights of the usual layouts (the LFE channel is ignored and surround
+        // channels are louder).
+        let channel_weights = (0..channel_count)
+            .map(|channel| match channel {
+                0..=2 => 1.0,
+                3 => 0.0,
+                _ => 1.41,
+            })
+            .collect();
+
+        Self {
+            filters: vec![Self::create_k_weighting(sample_rate); channel_count],
+            channel_weights,
+            frames_per_step: ((BLOCK_DURATION_SEC / STEPS_PER_BLOCK as f64 * sample_rate) as usize)
+                .max(1),
+            step_energy: 0.0,
+            step_frame_count: 0,
+            recent_steps: Vec::with_capacity(STEPS_PER_BLOCK),
+            block_energies: Vec::new(),
+            peak: 0.0,
+        }
+    }
+
+    /// Returns the shelf that models the head and the high-pass filter
+    /// (coefficients are computed for the sample rate).
+    fn create_k_weighting(sample_rate: f64) -> [Biquad; 2] {
+        // High shelf (+4 dB above ~1.7 kHz).
+        let k = (PI * 1681.974450955533 / sample_rate).tan();
+        let q = 0.7071752369554196;
+        let vh = 10.0f64.powf(3.999843853973347 / 20.0);
+        let vb = vh.powf(0.4996667741545416);
+        let a0 = 1.0 + k / q + k * k;
+        let shelf = Biquad::new(
+            [
+                (vh + vb * k / q + k * k) / a0,
+                2.0 * (k * k - vh) / a0,
+                (vh - vb * k / q + k * k) / a0,
+            ],
+            [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+        );
+
+        // High-pass (cuts below ~38 Hz).
+        let k = (PI * 38.13547087602444 / sample_rate).tan();
+        let q = 0.5003270373238773;
+        let a0 = 1.0 + k / q + k * k;
+        let high_pass = Biquad::new(
+            [1.0, -2.0, 1.0],
+            [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+        );
+
+        [shelf, high_pass]
+    }
+
+    /// Adds one frame (a sample per channel).
+    pub fn push_frame(&mut self, frame: &[f32]) {
+        for (channel, &sample) in frame.iter().enumerate().take(self.filters.len()) {
+            self.peak = self.peak.max(sample.abs());
+
+            let [shelf, high_pass] = &mut self.filters[channel];
+            let weighted = high_pass.process(shelf.process(sample as f64));
+            self.step_energy += self.channel_weights[channel] * weighted * weighted;
+        }
+
+        self.step_frame_count += 1;
+        if self.step_frame_count < self.frames_per_step {
+            return;
+        }
+
+        // Finish the step, a block is made of the last steps.
+        if self.recent_steps.len() == STEPS_PER_BLOCK {
+            self.recent_steps.remove(0);
+        }
+        self.recent_steps
+            .push(self.step_energy / self.step_frame_count as f64);
+        self.step_energy = 0.0;
+        self.step_frame_count = 0;
+
+        if self.recent_steps.len() == STEPS_PER_BLOCK {
+            self.block_energies
+                .push(self.recent_steps.iter().sum::<f64>() / STEPS_PER_BLOCK as f64);
+        }
+    }
+
+    /// Returns loudness of everything that was pushed (`None` if it's too short or silent).
+    pub fn get_loudness(&self) -> Option<Loudness> {
+        let energy_to_lufs = |energy: f64| -0.691 + 10.0 * energy.max(1e-20).log10();
+        let get_mean_energy = |threshold_lufs: f64| {
+            let (sum, count) = self
+                .block_energies
+                .iter()
+                .filter(|&&energy| energy_to_lufs(energy) > threshold_lufs)
+                .fold((0.0, 0), |(sum, count), energy| (sum + energy, count + 1));
+            (count > 0).then(|| sum / count as f64)
+        };
+
+        let relative_gate = energy_to_lufs(get_mean_energy(ABSOLUTE_GATE_LUFS)?) + RELATIVE_GATE_LU;
+        let energy = get_mean_energy(relative_gate.max(ABSOLUTE_GATE_LUFS))?;
+
+        Some(Loudness {
+            integrated_lufs: energy_to_lufs(energy) as f32,
+            peak: self.peak,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: u32 = 48000;
+
+    /// Pushes a 1 kHz sine with the loudness (in LUFS) to every channel.
+    fn push_sine(meter: &mut LoudnessMeter, lufs: f64, duration_sec: f64) {
+        // A 1 kHz sine in both channels of a stereo sound has loudness of its peak in dBFS.
+        let amplitude = 10.0f64.powf(lufs / 20.0);
+        let channel_count = meter.filters.len();
+        for index in 0..(duration_sec * SAMPLE_RATE as f64) as usize {
+            let time = index as f64 / SAMPLE_RATE as f64;
+            let sample = (amplitude * (2.0 * PI * 1000.0 * time).sin()) as f32;
+            meter.push_frame(&vec![sample; channel_count]);
+        }
+    }
+
+    fn assert_lufs(meter: &LoudnessMeter, expected_lufs: f64) {
+        let loudness = meter.get_loudness().unwrap();
+        assert!(
+            (loudness.integrated_lufs as f64 - expected_lufs).abs() < 0.1,
+            "{} LUFS instead of {expected_lufs} LUFS",
+            loudness.integrated_lufs
+        );
+    }
+
+    #[test]
+    fn sine_has_reference_loudness() {
+        let mut meter = LoudnessMeter::new(SAMPLE_RATE, 2);
+        push_sine(&mut meter, -23.0, 5.0);
+
+        assert_lufs(&meter, -23.0);
+        assert!((meter.get_loudness().unwrap().peak - 10.0f32.powf(-23.0 / 20.0)).abs() < 1e-3);
+    }
+
+    #[test]
+    fn short_or_silent_sound_has_no_loudness() {
+        let mut meter = LoudnessMeter::new(SAMPLE_RATE, 2);
+        push_sine(&mut meter, -23.0, 0.3);
+        assert!(meter.get_loudness().is_none());
+
+        let mut meter = LoudnessMeter::new(SAMPLE_RATE, 2);
+        push_sine(&mut meter, -80.0, 5.0);
+        assert!(meter.get_loudness().is_none());
+    }
+
+    #[test]
+    fn absolute_gate_ignores_silence() {
+        let mut meter = LoudnessMeter::new(SAMPLE_RATE, 2);
+        push_sine(&mut meter, -23.0, 10.0);
+        push_sine(&mut meter, -80.0, 10.0);
+
+        assert_lufs(&meter, -23.0);
+    }
+
+    #[test]
+    fn relative_gate_ignores_only_much_quieter_blocks() {
+        // -40 LUFS is more than 10 LU below the average, so it's gated out.
+        let mut meter = LoudnessMeter::new(SAMPLE_RATE, 2);
+        push_sine(&mut meter, -23.0, 10.0);
+        push_sine(&mut meter, -40.0, 10.0);
+        assert_lufs(&meter, -23.0);
+
+        // -30 LUFS is kept and averaged with -23 LUFS by energy.
+        let mut meter = LoudnessMeter::new(SAMPLE_RATE, 2);
+        push_sine(&mut meter, -23.0, 10.0);
+        push_sine(&mut meter, -30.0, 10.0);
+        assert_lufs(
+            &meter,
+            -23.0 + 10.0 * ((1.0 + 10.0f64.powf(-0.7)) / 2.0).log10(),
+        );
+    }
+
+    #[test]
+    fn lfe_channel_is_ignored() {
+        let mut meter = LoudnessMeter::new(SAMPLE_RATE, 4);
+        for index in 0..SAMPLE_RATE as usize {
+            let sample =
+                (0.5 * (2.0 * PI * 1000.0 * index as f64 / SAMPLE_RATE as f64).sin()) as f32;
+            meter.push_frame(&[0.0, 0.0, 0.0, sample]);
+        }
+
+        assert!(meter.get_loudness().is_none());
+    }
+}
@@ -0,0 +1,99 @@
+use super::loudness::Loudness;
+use super::wave_cache::get_sound_file_key;
+use crate::misc::config_manger::ConfigManager;
+use crate::misc::message_box;
+use std::fs::File;
+use std::io::Write;
+use std::path::PathBuf;
+
+const CACHE_DIRECTORY_NAME: &str = "loudness";
+const CACHE_FILE_EXTENSION: &str = "loud";
+
+/// Written at the start of cache files, the version is increased when the format changes.
+const CACHE_FILE_MAGIC: &[u8; 4] = b"TAPL";
+const CACHE_FILE_VERSION: u32 = 1;
+
+/// Stores scanned loudness of sounds on disk so that every file is only scanned once.
+///
+/// Entries are keyed the same way as in the waveform cache and are tiny, so the cache
+/// has no size limit. Nothing is stored if the cache directory is not available.
+#[derive(Clone)]
+pub struct LoudnessCache {
+    directory: Option<PathBuf>,
+}
+
+impl LoudnessCache {
+    pub fn new() -> Self {
+        let directory = ConfigManager::get_cache_directory()
+            .map(|directory| directory.join(CACHE_DIRECTORY_NAME))
+            .filter(|directory| match std::fs::create_dir_all(directory) {
+                Ok(()) => true,
+                Err(msg) => {
+                    message_box::log_error(&format!(
+                        "failed to create loudness cache directory, error: {}",
+                        msg
+                    ));
+                    false
+                }
+            });
+
+        Self { directory }
+    }
+
+    /// Returns the cached loudness of the specified sound (if it's in the cache).
+    pub fn load(&self, path: &str) -> Option<Loudness> {
+        let data = std::fs::read(self.get_cache_file_path(path)?).ok()?;
+
+        let magic_size = CACHE_FILE_MAGIC.len();
+        if data.len() != magic_size + 12 || &data[..magic_size] != CACHE_FILE_MAGIC {
+            return None;
+        }
+        let read_bytes =
+            |offset: usize| -> [u8; 4] { data[offset..offset + 4].try_into().unwrap_or_default() };
+        if u32::from_le_bytes(read_bytes(magic_size)) != CACHE_FILE_VERSION {
+            return None;
+        }
+
+        Some(Loudness {
+            integrated_lufs: f32::from_le_bytes(read_bytes(magic_size + 4)),
+            peak: f32::from_le_bytes(read_bytes(magic_size + 8)),
+        })
+    }
+
+    pub fn store(&self, path: &str, loudness: &Loudness) {
+        let cache_path = match self.get_cache_file_path(path) {
+            None => return,
+            Some(cache_path) => cache_path,
+        };
+
+        let mut data = Vec::with_capacity(CACHE_FILE_MAGIC.len() + 12);
+        data.extend_from_slice(CACHE_FILE_MAGIC);
+        data.extend_from_slice(&CACHE_FILE_VERSION.to_le_bytes());
+        data.extend_from_slice(&loudness.integrated_lufs.to_le_bytes());
+        data.extend_from_slice(&loudness.peak.to_le_bytes());
+
+        // Write to a temporary file first so that other instances never read partial entries.
+        let temp_path = cache_path.with_extension("tmp");
+        let result = File::create(&temp_path)
+            .and_then(|mut file| file.write_all(&data))
+            .and_then(|_| std::fs::rename(&temp_path, &cache_path));
+        if let Err(msg) = result {
+            message_box::log_error(&format!(
+                "failed to write loudness cache entry, error: {}",
+                msg
+            ));
+            let _ = std::fs::remove_file(&temp_path);
+        }
+    }
+
+    /// Returns path to the cache entry of the specified sound (`None` if the sound does not exist).
+    fn get_cache_file_path(&self, path: &str) -> Option<PathBuf> {
+        let key = get_sound_file_key(path)?;
+
+        Some(
+            self.directory
+                .as_ref()?
+                .join(format!("{}.{}", key, CACHE_FILE_EXTENSION)),
+        )
+    }
+}
@@ -1,2 +1,23 @@
+pub mod ab_loop;
 pub mod audio_player;
+pub mod biquad;
+pub mod equalizer;
+pub mod fft;
+pub mod limiter;
+pub mod loudness;
+pub mod loudness_cache;
+pub mod output_analyzer;
+pub mod output_tap;
+pub mod pitch_shifter;
+pub mod player_event;
+pub mod replay_gain;
+pub mod sleep_timer;
 pub mod sound_data;
+pub mod sound_decoder;
+pub mod sound_wave;
+pub mod spectrogram;
+pub mod stereo_control;
+pub mod track_metadata;
+pub mod vocal_reducer;
+pub mod wave_cache;
+pub mod wave_precomputer;
@@ -0,0 +1,164 @@
+use super::fft::{create_window, fft, get_full_scale_power, get_log_band_bins};
+use super::output_tap::OutputTapConsumer;
+use crate::misc::config_manger::WindowFunction;
+use kira::Frame;
+use std::time::{Duration, Instant};
+
+/// Number of bars of the spectrum.
+pub const SPECTRUM_BAND_COUNT: usize = 40;
+
+/// Number of the last played frames that the spectrum is computed from.
+const SPECTRUM_WINDOW_SIZE: usize = 2048;
+const SPECTRUM_MIN_FREQUENCY_HZ: f32 = 30.0;
+
+/// Levels lower than this number of decibels below full scale are shown as silence.
+pub const DISPLAY_RANGE_DB: f32 = 72.0;
+
+/// Shown levels fall by this number of decibels per second when the sound gets quieter.
+const FALL_RATE_DB_PER_SEC: f32 = 30.0;
+
+/// Time constant of the RMS level averaging.
+const RMS_TIME_CONSTANT_SEC: f32 = 0.3;
+
+const PEAK_HOLD_TIME_MS: u64 = 1500;
+
+/// Levels of one channel as linear amplitudes (1.0 is full scale).
+#[derive(Clone, Copy, Default)]
+pub struct ChannelLevel {
+    pub peak: f32,
+    pub rms: f32,
+    /// The highest peak of the last few moments.
+    pub peak_hold: f32,
+}
+
+/// Computes the spectrum and levels of the audio that was played recently
+/// (reads frames from the output tap of the main track).
+pub struct OutputAnalyzer {
+    tap: OutputTapConsumer,
+    /// The last played frames (circular buffer).
+    history: Vec<Frame>,
+    history_pos: usize,
+    window: Vec<f32>,
+    reference_power: f32,
+    /// Range of FFT bins that each band covers (computed for `band_sample_rate`).
+    band_bins: Vec<(usize, usize)>,
+    band_sample_rate: u32,
+    real: Vec<f32>,
+    imag: Vec<f32>,
+    /// Levels of bands in range [0.0; 1.0].
+    spectrum: Vec<f32>,
+    levels: [ChannelLevel; 2],
+    mean_squares: [f32; 2],
+    peak_hold_times: [Instant; 2],
+    last_update: Instant,
+}
+
+impl OutputAnalyzer {
+    pub fn new(tap: OutputTapConsumer) -> Self {
+        let window = create_window(WindowFunction::Hann, SPECTRUM_WINDOW_SIZE);
+        let reference_power = get_full_scale_power(&window);
+        let now = Instant::now();
+
+        Self {
+            tap,
+            history: vec![Frame::ZERO; SPECTRUM_WINDOW_SIZE],
+            history_pos: 0,
+            window,
+            reference_power,
+            band_bins: Vec::new(),
+            band_sample_rate: 0,
+            real: vec![0.0; SPECTRUM_WINDOW_SIZE],
+            imag: vec![0.0; SPECTRUM_WINDOW_SIZE],
+            spectrum: vec![0.0; SPECTRUM_BAND_COUNT],
+            levels: [ChannelLevel::default(); 2],
+            mean_squares: [0.0; 2],
+            peak_hold_times: [now; 2],
+            last_update: now,
+        }
+    }
+
+    /// Reads the frames played since the last update and recomputes the spectrum and levels.
+    pub fn update(&mut self) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_update).as_secs_f32();
+        self.last_update = now;
+        let fall_db = FALL_RATE_DB_PER_SEC * elapsed;
+
+        // Read new frames.
+        let sample_rate = self.tap.get_sample_rate().max(1);
+        let rms_factor = 1.0 - (-1.0 / (RMS_TIME_CONSTANT_SEC * sample_rate as f32)).exp();
+        let mut new_peaks = [0.0f32; 2];
+        let mut new_frame_count = 0;
+        while let Some(frame) = self.tap.pop() {
+            self.history[self.history_pos] = frame;
+            self.history_pos = (self.history_pos + 1) % self.history.len();
+            new_frame_count += 1;
+
+            for (channel, sample) in [frame.left, frame.right].into_iter().enumerate() {
+                new_peaks[channel] = new_peaks[channel].max(sample.abs());
+                self.mean_squares[channel] +=
+                    (sample * sample - self.mean_squares[channel]) * rms_factor;
+            }
+        }
+
+        // Update levels.
+        let fall_factor = 10.0f32.powf(-fall_db / 20.0);
+        for (channel, level) in self.levels.iter_mut().enumerate() {
+            level.peak = new_peaks[channel].max(level.peak * fall_factor);
+            level.rms = self.mean_squares[channel].sqrt();
+
+            let hold_expired = now.duration_since(self.peak_hold_times[channel])
+                > Duration::from_millis(PEAK_HOLD_TIME_MS);
+            if level.peak >= level.peak_hold || hold_expired {
+                level.peak_hold = level.peak;
+                self.peak_hold_times[channel] = now;
+            }
+        }
+
+        // Update spectrum, bars fall smoothly when the sound gets quieter.
+        let fall = fall_db / DISPLAY_RANGE_DB;
+        if new_frame_count == 0 {
+            for value in self.spectrum.iter_mut() {
+                *value = (*value - fall).max(0.0);
+            }
+            return;
+        }
+
+        if self.band_sample_rate != sample_rate {
+            self.band_sample_rate = sample_rate;
+            self.band_bins = get_log_band_bins(
+                SPECTRUM_BAND_COUNT,
+                SPECTRUM_WINDOW_SIZE,
+                sample_rate,
+                SPECTRUM_MIN_FREQUENCY_HZ,
+            );
+        }
+
+        // The oldest frame is at the current position of the circular buffer.
+        for i in 0..SPECTRUM_WINDOW_SIZE {
+            let frame = self.history[(self.history_pos + i) % SPECTRUM_WINDOW_SIZE];
+            self.real[i] = (frame.left + frame.right) / 2.0 * self.window[i];
+            self.imag[i] = 0.0;
+        }
+        fft(&mut self.real, &mut self.imag);
+
+        for (value, &(start, end)) in self.spectrum.iter_mut().zip(&self.band_bins) {
+            let power = (start..end)
+                .map(|bin| self.real[bin].powi(2) + self.imag[bin].powi(2))
+                .fold(0.0, f32::max);
+            let db = 10.0 * (power / self.reference_power).max(1e-20).log10();
+            let new_value = ((db + DISPLAY_RANGE_DB) / DISPLAY_RANGE_DB).clamp(0.0, 1.0);
+            *value = new_value.max(*value - fall);
+        }
+    }
+
+    /// Returns levels of bands (from the lowest frequency) in range [0.0; 1.0].
+    pub fn get_spectrum(&self) -> &[f32] {
+        &self.spectrum
+    }
+
+    /// Returns levels of the left and right channels.
+    pub fn get_levels(&self) -> &[ChannelLevel; 2] {
+        &self.levels
+    }
+}
@@ -0,0 +1,90 @@
+use kira::effect::{Effect, EffectBuilder};
+use kira::info::Info;
+use kira::Frame;
+use rtrb::{Consumer, Producer, RingBuffer};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+
+/// Number of frames that can wait in the ring buffer until they are read
+/// (newer frames are dropped if nobody reads them).
+const TAP_BUFFER_CAPACITY: usize = 16384;
+
+/// Effect that passes audio unchanged and copies it into a lock-free ring buffer
+/// so that other threads can see what is actually playing.
+pub struct OutputTapBuilder {
+    producer: Producer<Frame>,
+    sample_rate: Arc<AtomicU32>,
+}
+
+/// Reading end of the [`OutputTapBuilder`] effect.
+pub struct OutputTapConsumer {
+    consumer: Consumer<Frame>,
+    sample_rate: Arc<AtomicU32>,
+}
+
+struct OutputTap {
+    producer: Producer<Frame>,
+    sample_rate: Arc<AtomicU32>,
+}
+
+impl OutputTapBuilder {
+    pub fn new() -> (Self, OutputTapConsumer) {
+        let (producer, consumer) = RingBuffer::new(TAP_BUFFER_CAPACITY);
+        let sample_rate = Arc::new(AtomicU32::new(0));
+
+        (
+            Self {
+                producer,
+                sample_rate: sample_rate.clone(),
+            },
+            OutputTapConsumer {
+                consumer,
+                sample_rate,
+            },
+        )
+    }
+}
+
+impl EffectBuilder for OutputTapBuilder {
+    type Handle = ();
+
+    fn build(self) -> (Box<dyn Effect>, Self::Handle) {
+        (
+            Box::new(OutputTap {
+                producer: self.producer,
+                sample_rate: self.sample_rate,
+            }),
+            (),
+        )
+    }
+}
+
+impl Effect for OutputTap {
+    fn init(&mut self, sample_rate: u32, _internal_buffer_size: usize) {
+        self.sample_rate.store(sample_rate, Ordering::Relaxed);
+    }
+
+    fn on_change_sample_rate(&mut self, sample_rate: u32) {
+        self.sample_rate.store(sample_rate, Ordering::Relaxed);
+    }
+
+    fn process(&mut self, input: &mut [Frame], _dt: f64, _info: &Info) {
+        for frame in input.iter() {
+            if self.producer.push(*frame).is_err() {
+                break;
+            }
+        }
+    }
+}
+
+impl OutputTapConsumer {
+    /// Returns the next frame that was played (`None` if all frames were read).
+    pub fn pop(&mut self) -> Option<Frame> {
+        self.consumer.pop().ok()
+    }
+
+    /// Returns the sample rate of the output (0 until the audio starts).
+    pub fn get_sample_rate(&self) -> u32 {
+        self.sample_rate.load(Ordering::Relaxed)
+    }
+}
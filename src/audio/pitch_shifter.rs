@@ -0,0 +1,231 @@
+use kira::effect::{Effect, EffectBuilder};
+use kira::info::Info;
+use kira::Frame;
+use std::f64::consts::PI;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+
+/// Allowed pitch multipliers (two octaves down or up).
+pub const PITCH_FACTOR_RANGE: std::ops::RangeInclusive<f32> = 0.25..=4.0;
+
+/// Duration of one grain, longer grains smear transients, shorter ones sound rougher.
+const GRAIN_DURATION_SEC: f64 = 0.05;
+
+/// How far from the ideal position a new grain may start to match the waveform
+/// that is currently playing.
+const SEARCH_DURATION_SEC: f64 = 0.01;
+
+/// Length of the part of the waveform that is compared when searching for a grain start.
+const CORRELATION_DURATION_SEC: f64 = 0.01;
+
+/// Only every N-th sample is compared to make the search cheaper.
+const CORRELATION_STEP: usize = 2;
+
+/// Changes pitch of the audio without changing its tempo.
+///
+/// Input is written to a delay line that is read by two overlapping grains moving at
+/// the speed of the pitch multiplier. Every new grain starts where the waveform is most
+/// similar to what the other grain is playing (as in WSOLA) to avoid phase cancellation.
+pub struct PitchShifterBuilder {
+    pitch: Arc<AtomicU32>,
+}
+
+/// Controls the pitch of the [`PitchShifterBuilder`] effect from other threads.
+#[derive(Clone)]
+pub struct PitchShifterHandle {
+    pitch: Arc<AtomicU32>,
+}
+
+#[derive(Clone, Copy, Default)]
+struct Grain {
+    /// Read position in the delay line (in frames since the effect was created).
+    position: f64,
+    /// Number of frames played since the grain started.
+    age: usize,
+}
+
+struct PitchShifter {
+    pitch: Arc<AtomicU32>,
+    buffer: Vec<Frame>,
+    /// Total number of written frames.
+    write_pos: usize,
+    grains: [Grain; 2],
+    grain_length: usize,
+    search_length: usize,
+    correlation_length: usize,
+    is_active: bool,
+}
+
+impl PitchShifterBuilder {
+    pub fn new() -> (Self, PitchShifterHandle) {
+        let pitch = Arc::new(AtomicU32::new(1.0f32.to_bits()));
+
+        (
+            Self {
+                pitch: pitch.clone(),
+            },
+            PitchShifterHandle { pitch },
+        )
+    }
+}
+
+impl PitchShifterHandle {
+    /// Sets the pitch multiplier where 1.0 keeps the original pitch.
+    pub fn set_pitch(&self, pitch: f32) {
+        let pitch = pitch.clamp(*PITCH_FACTOR_RANGE.start(), *PITCH_FACTOR_RANGE.end());
+        self.pitch.store(pitch.to_bits(), Ordering::Relaxed);
+    }
+}
+
+impl EffectBuilder for PitchShifterBuilder {
+    type Handle = ();
+
+    fn build(self) -> (Box<dyn Effect>, Self::Handle) {
+        (
+            Box::new(PitchShifter {
+                pitch: self.pitch,
+                buffer: Vec::new(),
+                write_pos: 0,
+                grains: [Grain::default(); 2],
+                grain_length: 0,
+                search_length: 0,
+                correlation_length: 0,
+                is_active: false,
+            }),
+            (),
+        )
+    }
+}
+
+impl PitchShifter {
+    fn allocate(&mut self, sample_rate: u32) {
+        let sample_rate = sample_rate.max(1) as f64;
+        self.grain_length = ((GRAIN_DURATION_SEC * sample_rate) as usize).max(2);
+        self.search_length = (SEARCH_DURATION_SEC * sample_rate) as usize;
+        self.correlation_length = (CORRELATION_DURATION_SEC * sample_rate) as usize;
+
+        let max_delay = self.get_delay(*PITCH_FACTOR_RANGE.end() as f64) + self.search_length;
+        self.buffer = vec![Frame::ZERO; (max_delay * 2).next_power_of_two()];
+        self.write_pos = 0;
+        self.is_active = false;
+    }
+
+    /// Returns how far behind the input a new grain ideally starts so that it never
+    /// overtakes the input while playing.
+    fn get_delay(&self, pitch: f64) -> usize {
+        ((pitch - 1.0).max(0.0) * self.grain_length as f64).ceil() as usize
+            + self.search_length
+            + self.correlation_length
+            + 2
+    }
+
+    fn read(&self, position: f64) -> Frame {
+        let mask = self.buffer.len() - 1;
+        let index = position.floor();
+        let fraction = (position - index) as f32;
+        let index = index as usize;
+
+        let a = self.buffer[index & mask];
+        let b = self.buffer[(index + 1) & mask];
+        a + (b - a) * fraction
+    }
+
+    fn read_mono(&self, position: usize) -> f32 {
+        let frame = self.buffer[position & (self.buffer.len() - 1)];
+        frame.left + frame.right
+    }
+
+    /// Starts the grain at the position where the waveform best matches the other grain.
+    fn restart_grain(&mut self, index: usize, pitch: f64) {
+        let target = self.write_pos.saturating_sub(self.get_delay(pitch));
+        let other_position = self.grains[1 - index].position as usize;
+
+        let first_candidate = target.saturating_sub(self.search_length);
+        let last_candidate = target + self.search_length;
+        let mut best_position = target;
+        let mut best_correlation = f32::MIN;
+        for candidate in (first_candidate..=last_candidate).step_by(CORRELATION_STEP) {
+            let correlation: f32 = (0..self.correlation_length)
+                .step_by(CORRELATION_STEP)
+                .map(|offset| {
+                    self.read_mono(candidate + offset) * self.read_mono(other_position + offset)
+                })
+                .sum();
+            if correlation > best_correlation {
+                best_correlation = correlation;
+                best_position = candidate;
+            }
+        }
+
+        self.grains[index] = Grain {
+            position: best_position as f64,
+            age: 0,
+        };
+    }
+}
+
+impl Effect for PitchShifter {
+    fn init(&mut self, sample_rate: u32, _internal_buffer_size: usize) {
+        self.allocate(sample_rate);
+    }
+
+    fn on_change_sample_rate(&mut self, sample_rate: u32) {
+        self.allocate(sample_rate);
+    }
+
+    fn process(&mut self, input: &mut [Frame], _dt: f64, _info: &Info) {
+        if self.buffer.is_empty() {
+            return;
+        }
+
+        let pitch = f32::from_bits(self.pitch.load(Ordering::Relaxed)) as f64;
+        let mask = self.buffer.len() - 1;
+
+        // Pass the audio unchanged (but keep the history) if the pitch is not changed.
+        if (pitch - 1.0).abs() < 1e-3 {
+            for frame in input.iter() {
+                self.buffer[self.write_pos & mask] = *frame;
+                self.write_pos += 1;
+            }
+            self.is_active = false;
+            return;
+        }
+
+        if !self.is_active {
+            // Grains are half a grain apart so that their windows always sum to 1.
+            let start = self.write_pos.saturating_sub(self.get_delay(pitch)) as f64;
+            self.grains = [
+                Grain {
+                    position: start,
+                    age: 0,
+                },
+                Grain {
+                    position: start,
+                    age: self.grain_length / 2,
+                },
+            ];
+            self.is_active = true;
+        }
+
+        for frame in input.iter_mut() {
+            self.buffer[self.write_pos & mask] = *frame;
+            self.write_pos += 1;
+
+            let mut output = Frame::ZERO;
+            for index in 0..self.grains.len() {
+                let grain = self.grains[index];
+                let weight = (PI * grain.age as f64 / self.grain_length as f64)
+                    .sin()
+                    .powi(2);
+                output += self.read(grain.position) * weight as f32;
+
+                self.grains[index].position += pitch;
+                self.grains[index].age += 1;
+                if self.grains[index].age >= self.grain_length {
+                    self.restart_grain(index, pitch);
+                }
+            }
+            *frame = output;
+        }
+    }
+}
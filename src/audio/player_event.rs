@@ -0,0 +1,35 @@
+use std::time::Duration;
+
+/// Things that happen in the audio player that other parts of the application can react to.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum PlayerEvent {
+    TrackStarted,
+    /// The track was played until the end.
+    TrackEnded,
+    /// The last track of the tracklist was played until the end.
+    TracklistEnded,
+    Paused,
+    Resumed,
+    Stopped,
+}
+
+/// Information about the track that an event is related to.
+#[derive(Clone)]
+pub struct PlayingTrackInfo {
+    /// Index in the tracklist (`None` if the track is not in the tracklist).
+    pub index: Option<usize>,
+    pub path: String,
+    /// Title from tags or the file name if there is no title tag.
+    pub title: String,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub duration: Duration,
+    /// Time passed since the start of the track.
+    pub position: Duration,
+    pub is_paused: bool,
+}
+
+/// Function that is called from the audio player when an event happens.
+///
+/// Called while the player is locked so it should not try to lock the player and should not block.
+pub type PlayerEventListener = Box<dyn FnMut(PlayerEvent, &PlayingTrackInfo) + Send>;
@@ -0,0 +1,77 @@
+use super::loudness::Loudness;
+use crate::misc::config_manger::{ReplayGainConfig, ReplayGainMode};
+use symphonia::core::meta::StandardTagKey;
+
+/// Loudness that ReplayGain 2.0 brings all tracks to.
+const REFERENCE_LOUDNESS_LUFS: f32 = -18.0;
+
+/// ReplayGain values read from tags of an audio file.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct ReplayGainTags {
+    pub track_gain_db: Option<f32>,
+    pub track_peak: Option<f32>,
+    pub album_gain_db: Option<f32>,
+    pub album_peak: Option<f32>,
+}
+
+impl ReplayGainTags {
+    /// Stores the value of the tag if it's a ReplayGain tag, values look like "-6.20 dB"
+    /// or "0.988525".
+    pub fn apply_tag(&mut self, key: StandardTagKey, value: &str) {
+        let field = match key {
+            StandardTagKey::ReplayGainTrackGain => &mut self.track_gain_db,
+            StandardTagKey::ReplayGainTrackPeak => &mut self.track_peak,
+            StandardTagKey::ReplayGainAlbumGain => &mut self.album_gain_db,
+            StandardTagKey::ReplayGainAlbumPeak => &mut self.album_peak,
+            _ => return,
+        };
+
+        if let Some(number) = value
+            .split_whitespace()
+            .next()
+            .and_then(|number| number.parse::<f32>().ok())
+            .filter(|number| number.is_finite())
+        {
+            *field = Some(number);
+        }
+    }
+
+    /// Tells if the file has a gain that can be used without scanning it.
+    pub fn has_gain(&self) -> bool {
+        self.track_gain_db.is_some() || self.album_gain_db.is_some()
+    }
+}
+
+/// Returns gain (in decibels) that should be applied to the sound, `None` if there is
+/// nothing to apply (disabled or untagged and not scanned yet).
+pub fn get_replay_gain_db(
+    config: &ReplayGainConfig,
+    tags: &ReplayGainTags,
+    loudness: Option<Loudness>,
+) -> Option<f32> {
+    // Use album gain in the album mode if it's there, otherwise use track gain.
+    let tagged = match config.mode {
+        ReplayGainMode::Off => return None,
+        ReplayGainMode::Track => None,
+        ReplayGainMode::Album => tags.album_gain_db.zip(Some(tags.album_peak)),
+    }
+    .or(tags.track_gain_db.zip(Some(tags.track_peak)))
+    .or(tags.album_gain_db.zip(Some(tags.album_peak)));
+
+    // Untagged files are normalized using the scanned loudness.
+    let (gain_db, peak) = match (tagged, loudness) {
+        (Some(tagged), _) => tagged,
+        (None, Some(loudness)) => (
+            REFERENCE_LOUDNESS_LUFS - loudness.integrated_lufs,
+            Some(loudness.peak),
+        ),
+        (None, None) => return None,
+    };
+    let gain_db = gain_db + config.preamp_db;
+
+    // Don't let the gain push the peak over full scale.
+    match peak.filter(|peak| config.prevent_clipping && *peak > 0.0) {
+        Some(peak) => Some(gain_db.min(-20.0 * peak.log10())),
+        None => Some(gain_db),
+    }
+}
@@ -0,0 +1,200 @@
+use crate::misc::time_format::format_duration;
+use chrono::{Local, NaiveTime, TimeDelta};
+use std::time::{Duration, Instant};
+
+/// The volume goes down during this time before the playback is paused.
+pub const SLEEP_FADE_DURATION: Duration = Duration::from_secs(60);
+
+/// When the sleep timer pauses the playback.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SleepTarget {
+    /// At the moment (set as a number of minutes or a clock time).
+    Deadline(Instant),
+    /// When the current track ends.
+    EndOfTrack,
+    /// When the last track of the tracklist ends.
+    EndOfTracklist,
+}
+
+/// Pauses the playback at the target and slowly fades the volume out before that.
+#[derive(Default)]
+pub struct SleepTimer {
+    target: Option<SleepTarget>,
+}
+
+impl SleepTimer {
+    pub fn get_target(&self) -> Option<SleepTarget> {
+        self.target
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.target.is_some()
+    }
+
+    /// Starts the timer (replaces the previous one).
+    pub fn set_target(&mut self, target: SleepTarget) {
+        self.target = Some(target);
+    }
+
+    /// Starts the timer that pauses after the specified time.
+    pub fn set_duration(&mut self, duration: Duration) {
+        self.set_target(SleepTarget::Deadline(Instant::now() + duration));
+    }
+
+    /// Starts the timer that pauses at the next occurrence of the clock time
+    /// (tomorrow if the time has passed today).
+    pub fn set_clock_time(&mut self, time: NaiveTime) {
+        let now = Local::now().naive_local();
+        let mut target = now.date().and_time(time);
+        if target <= now {
+            target += TimeDelta::days(1);
+        }
+
+        self.set_duration((target - now).to_std().unwrap_or_default());
+    }
+
+    pub fn cancel(&mut self) {
+        self.target = None;
+    }
+
+    /// Returns real time left until the timer goes off, `None` if it's not known yet
+    /// (the timer waits for the end of the tracklist and the last track is not playing).
+    ///
+    /// `track_remaining` is real time left until the end of the current track
+    /// and `is_last_track` tells if it's the last track of the tracklist.
+    pub fn get_remaining(
+        &self,
+        track_remaining: Duration,
+        is_last_track: bool,
+    ) -> Option<Duration> {
+        match self.target? {
+            SleepTarget::Deadline(deadline) => {
+                Some(deadline.saturating_duration_since(Instant::now()))
+            }
+            SleepTarget::EndOfTrack => Some(track_remaining),
+            SleepTarget::EndOfTracklist => is_last_track.then_some(track_remaining),
+        }
+    }
+
+    /// Tells if the timer goes off when the current track ends.
+    pub fn is_waiting_for_track_end(&self, is_last_track: bool) -> bool {
+        match self.target {
+            Some(SleepTarget::EndOfTrack) => true,
+            Some(SleepTarget::EndOfTracklist) => is_last_track,
+            _ => false,
+        }
+    }
+
+    /// Returns the volume multiplier (from 0.0 to 1.0) for the remaining time.
+    pub fn get_fade_gain(&self, remaining: Option<Duration>) -> f32 {
+        match remaining {
+            Some(remaining) if self.is_active() && remaining < SLEEP_FADE_DURATION => {
+                remaining.as_secs_f32() / SLEEP_FADE_DURATION.as_secs_f32()
+            }
+            _ => 1.0,
+        }
+    }
+
+    /// Returns text like "Sleep: 12:34" (`None` if the timer is not active).
+    pub fn get_status_text(&self, remaining: Option<Duration>) -> Option<String> {
+        let target = self.target?;
+
+        Some(match (target, remaining) {
+            (SleepTarget::Deadline(_), Some(remaining)) => {
+                format!("Sleep: {}", format_duration(remaining, false))
+            }
+            (SleepTarget::EndOfTrack, Some(remaining)) => {
+                format!(
+                    "Sleep: end of track ({})",
+                    format_duration(remaining, false)
+                )
+            }
+            (SleepTarget::EndOfTracklist, Some(remaining)) => format!(
+                "Sleep: end of tracklist ({})",
+                format_duration(remaining, false)
+            ),
+            _ => "Sleep: end of tracklist".to_string(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_deadline_remaining(timer: &SleepTimer) -> Duration {
+        match timer.get_target() {
+            Some(SleepTarget::Deadline(deadline)) => deadline - Instant::now(),
+            target => panic!("unexpected target {target:?}"),
+        }
+    }
+
+    fn assert_near(value: Duration, expected: Duration) {
+        let difference = value.abs_diff(expected);
+        assert!(
+            difference < Duration::from_secs(5),
+            "{value:?} instead of {expected:?}"
+        );
+    }
+
+    #[test]
+    fn fade_gain_goes_down_in_last_minute() {
+        let mut timer = SleepTimer::default();
+        assert_eq!(timer.get_fade_gain(Some(Duration::from_secs(30))), 1.0);
+
+        timer.set_duration(Duration::from_secs(600));
+        assert_eq!(timer.get_fade_gain(Some(Duration::from_secs(600))), 1.0);
+        assert_eq!(timer.get_fade_gain(Some(SLEEP_FADE_DURATION)), 1.0);
+        assert_eq!(timer.get_fade_gain(Some(Duration::from_secs(45))), 0.75);
+        assert_eq!(timer.get_fade_gain(Some(Duration::from_secs(15))), 0.25);
+        assert_eq!(timer.get_fade_gain(Some(Duration::ZERO)), 0.0);
+
+        // The end of the tracklist is not known yet.
+        assert_eq!(timer.get_fade_gain(None), 1.0);
+    }
+
+    #[test]
+    fn upcoming_clock_time_is_not_delayed_by_a_day() {
+        let mut timer = SleepTimer::default();
+        timer.set_clock_time(Local::now().time() + TimeDelta::minutes(10));
+
+        assert_near(get_deadline_remaining(&timer), Duration::from_secs(10 * 60));
+    }
+
+    #[test]
+    fn passed_clock_time_is_used_tomorrow() {
+        let mut timer = SleepTimer::default();
+        timer.set_clock_time(Local::now().time() - TimeDelta::minutes(10));
+
+        assert_near(
+            get_deadline_remaining(&timer),
+            Duration::from_secs(24 * 60 * 60 - 10 * 60),
+        );
+    }
+
+    #[test]
+    fn remaining_time_depends_on_target() {
+        let mut timer = SleepTimer::default();
+        let track_remaining = Duration::from_secs(90);
+        assert_eq!(timer.get_remaining(track_remaining, true), None);
+
+        timer.set_target(SleepTarget::EndOfTrack);
+        assert_eq!(
+            timer.get_remaining(track_remaining, false),
+            Some(track_remaining)
+        );
+        assert!(timer.is_waiting_for_track_end(false));
+
+        timer.set_target(SleepTarget::EndOfTracklist);
+        assert_eq!(timer.get_remaining(track_remaining, false), None);
+        assert_eq!(
+            timer.get_remaining(track_remaining, true),
+            Some(track_remaining)
+        );
+        assert!(!timer.is_waiting_for_track_end(false));
+        assert!(timer.is_waiting_for_track_end(true));
+
+        timer.cancel();
+        assert!(!timer.is_active());
+    }
+}
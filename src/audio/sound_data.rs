@@ -1,9 +1,19 @@
+use super::loudness::{Loudness, LoudnessMeter};
+use super::loudness_cache::LoudnessCache;
+use super::sound_wave::{SoundWave, WavePeak};
+use super::spectrogram::{Spectrogram, SpectrogramAnalyzer};
+use super::track_metadata::TrackMetadata;
+use super::wave_cache::WaveCache;
+use crate::misc::config_manger::SpectrogramConfig;
+use crate::misc::message_box;
 use kira::sound::streaming::StreamingSoundHandle;
+use std::fmt::Display;
 use std::io::ErrorKind;
 use std::sync::atomic::AtomicBool;
 use std::sync::atomic::Ordering;
 use std::sync::{Arc, Mutex};
 use std::thread::JoinHandle;
+use std::time::Duration;
 use symphonia::core::codecs::DecoderOptions;
 use symphonia::core::codecs::CODEC_TYPE_NULL;
 use symphonia::core::formats::FormatOptions;
@@ -14,9 +24,21 @@ use symphonia::core::{audio::SampleBuffer, errors::*};
 
 pub struct CurrentSoundData {
     pub handle: StreamingSoundHandle<kira::sound::FromFileError>,
-    pub wave: Arc<Mutex<Vec<u8>>>,
-    pub duration: f64,
+    pub wave: Arc<Mutex<SoundWave>>,
+    pub spectrogram: Arc<Mutex<Spectrogram>>,
+    pub duration: Duration,
+    /// Length of the sound in frames (as reported by the decoder).
+    pub num_frames: usize,
+    pub path: String,
+    pub metadata: TrackMetadata,
+    /// Loudness of the sound if it was scanned (or loaded from the cache).
+    pub loudness: Arc<Mutex<Option<Loudness>>>,
+    /// ReplayGain (in decibels) that was applied to the handle.
+    pub applied_replay_gain_db: Option<f32>,
+    /// Tells if the spectrogram is computed (or was computed).
+    is_spectrogram_started: bool,
     wave_calc_thread_handle: Option<JoinHandle<()>>,
+    spectrogram_thread_handle: Option<JoinHandle<()>>,
     stop_wave_calc_signal: Arc<AtomicBool>,
 }
 
@@ -24,6 +46,7 @@ impl Drop for CurrentSoundData {
     fn drop(&mut self) {
         self.stop_wave_calc_signal.store(true, Ordering::SeqCst);
         self.wave_calc_thread_handle.take().map(JoinHandle::join);
+        self.spectrogram_thread_handle.take().map(JoinHandle::join);
     }
 }
 
@@ -31,39 +54,136 @@ impl CurrentSoundData {
     pub fn new(
         path: &str,
         handle: StreamingSoundHandle<kira::sound::FromFileError>,
-        duration: f64,
+        num_frames: usize,
+        sample_rate: u32,
+        wave_cache: Option<WaveCache>,
+        spectrogram_config: Option<SpectrogramConfig>,
+        loudness_cache: Option<LoudnessCache>,
     ) -> Self {
-        let wave_data = Arc::new(Mutex::new(Vec::new()));
         let stop_signal = Arc::new(AtomicBool::new(false));
+        let metadata = TrackMetadata::read(path);
 
-        // Spawn a thread that will calculate the wave.
-        let wave_data_clone = wave_data.clone();
-        let stop_signal_clone = stop_signal.clone();
-        let path_clone = path.to_string();
-        let wave_calc_thread_handle = Some(std::thread::spawn(move || {
-            Self::try_generating_wave_for_sound(&path_clone, wave_data_clone, stop_signal_clone);
+        // Loudness of files without ReplayGain tags is scanned (`loudness_cache` is `None`
+        // if it should not be), use the cached result if it was scanned before.
+        let loudness_cache = loudness_cache.filter(|_| !metadata.replay_gain.has_gain());
+        let cached_loudness = loudness_cache.as_ref().and_then(|cache| cache.load(path));
+        let loudness_cache = loudness_cache.filter(|_| cached_loudness.is_none());
+        let loudness = Arc::new(Mutex::new(cached_loudness));
+
+        // Use cached wave if it was computed before.
+        let cached_wave = wave_cache
+            .as_ref()
+            .and_then(|cache| cache.load(path, num_frames));
+        let is_wave_cached = cached_wave.is_some();
+        let wave_data = Arc::new(Mutex::new(
+            cached_wave.unwrap_or_else(|| SoundWave::new(num_frames)),
+        ));
+        let is_spectrogram_started = spectrogram_config.is_some();
+        let spectrogram = Arc::new(Mutex::new(match spectrogram_config.as_ref() {
+            Some(config) => Spectrogram::new(num_frames, config.band_count),
+            None => Spectrogram::default(),
         }));
 
+        // Spawn a thread that will calculate the wave, the spectrogram and the loudness.
+        let wave_calc_thread_handle =
+            if !is_wave_cached || spectrogram_config.is_some() || loudness_cache.is_some() {
+                let wave_data_clone = wave_data.clone();
+                let spectrogram_clone = spectrogram.clone();
+                let loudness_clone = loudness.clone();
+                let stop_signal_clone = stop_signal.clone();
+                let path_clone = path.to_string();
+                Some(std::thread::spawn(move || {
+                    let is_complete = Self::try_analyzing_sound(
+                        &path_clone,
+                        Some(wave_data_clone.clone()).filter(|_| !is_wave_cached),
+                        spectrogram_config
+                            .as_ref()
+                            .map(|config| (spectrogram_clone, config)),
+                        Some(loudness_clone.clone()).filter(|_| loudness_cache.is_some()),
+                        stop_signal_clone,
+                    );
+                    if !is_complete {
+                        return;
+                    }
+                    if let (false, Some(cache)) = (is_wave_cached, wave_cache) {
+                        cache.store(&path_clone, &wave_data_clone.lock().unwrap());
+                    }
+                    if let (Some(cache), Some(loudness)) =
+                        (loudness_cache, *loudness_clone.lock().unwrap())
+                    {
+                        cache.store(&path_clone, &loudness);
+                    }
+                }))
+            } else {
+                None
+            };
+
         Self {
             handle,
             wave: wave_data,
-            duration,
+            spectrogram,
+            duration: Self::frames_to_duration(num_frames, sample_rate),
+            num_frames,
+            path: path.to_string(),
+            metadata,
+            loudness,
+            applied_replay_gain_db: None,
+            is_spectrogram_started,
             wave_calc_thread_handle,
+            spectrogram_thread_handle: None,
             stop_wave_calc_signal: stop_signal,
         }
     }
 
-    fn try_generating_wave_for_sound(
+    /// Starts computing the spectrogram if it's not computed yet
+    /// (it's only computed while it's shown, the sound is decoded again for it).
+    pub fn start_spectrogram(&mut self, config: SpectrogramConfig) {
+        if self.is_spectrogram_started {
+            return;
+        }
+        self.is_spectrogram_started = true;
+
+        *self.spectrogram.lock().unwrap() = Spectrogram::new(self.num_frames, config.band_count);
+
+        let spectrogram_clone = self.spectrogram.clone();
+        let stop_signal_clone = self.stop_wave_calc_signal.clone();
+        let path_clone = self.path.clone();
+        self.spectrogram_thread_handle = Some(std::thread::spawn(move || {
+            Self::try_analyzing_sound(
+                &path_clone,
+                None,
+                Some((spectrogram_clone, &config)),
+                None,
+                stop_signal_clone,
+            );
+        }));
+    }
+
+    /// Converts the number of frames to time without losing precision.
+    pub fn frames_to_duration(frames: usize, sample_rate: u32) -> Duration {
+        let frames = frames as u64;
+        let sample_rate = sample_rate.max(1) as u64;
+
+        Duration::from_secs(frames / sample_rate)
+            + Duration::from_nanos((frames % sample_rate) * 1_000_000_000 / sample_rate)
+    }
+
+    /// Decodes the sound and fills the wave, the spectrogram and the loudness (if passed),
+    /// returns `true` if everything was computed (`false` if failed or stopped, in this case
+    /// the results are incomplete and should not be cached).
+    pub fn try_analyzing_sound(
         path: &str,
-        wave: Arc<Mutex<Vec<u8>>>,
+        wave: Option<Arc<Mutex<SoundWave>>>,
+        spectrogram: Option<(Arc<Mutex<Spectrogram>>, &SpectrogramConfig)>,
+        loudness: Option<Arc<Mutex<Option<Loudness>>>>,
         should_stop: Arc<AtomicBool>,
-    ) {
+    ) -> bool {
         // Open the media source.
         let src = match std::fs::File::open(path) {
             Ok(s) => s,
             Err(msg) => {
-                println!("error: {}", msg);
-                return;
+                log_analysis_error(path, &msg);
+                return false;
             }
         };
 
@@ -78,9 +198,14 @@ impl CurrentSoundData {
         let fmt_opts: FormatOptions = Default::default();
 
         // Probe the media source.
-        let probed = symphonia::default::get_probe()
-            .format(&hint, mss, &fmt_opts, &meta_opts)
-            .expect("unsupported format");
+        let probed = match symphonia::default::get_probe().format(&hint, mss, &fmt_opts, &meta_opts)
+        {
+            Ok(probed) => probed,
+            Err(msg) => {
+                log_analysis_error(path, &msg);
+                return false;
+            }
+        };
 
         // Get the instantiated format reader.
         let mut format = probed.format;
@@ -93,8 +218,8 @@ impl CurrentSoundData {
         {
             Some(t) => t,
             None => {
-                println!("unable to find a codec");
-                return;
+                log_analysis_error(path, &"unable to find a codec");
+                return false;
             }
         };
 
@@ -106,42 +231,42 @@ impl CurrentSoundData {
             match symphonia::default::get_codecs().make(&track.codec_params, &dec_opts) {
                 Ok(d) => d,
                 Err(msg) => {
-                    println!("error: {}", msg);
-                    return;
+                    log_analysis_error(path, &msg);
+                    return false;
                 }
             };
 
         // Store the track identifier, it will be used to filter packets.
         let track_id = track.id;
 
-        let packet_count_to_average: usize = 20;
-        let mut packets_to_average: Vec<f32> = Vec::with_capacity(packet_count_to_average);
+        // Buckets have fixed duration so that the wave matches the timeline.
+        let frames_per_bucket = wave
+            .as_ref()
+            .map_or(0, |wave| wave.lock().unwrap().get_frames_per_bucket());
+        let mut bucket_peaks: Vec<WavePeak> = Vec::new(); // one per channel
+        let mut bucket_frame_count = 0;
+
+        // Created when the sample rate is known.
+        let mut spectrogram_analyzer: Option<SpectrogramAnalyzer> = None;
+        let mut loudness_meter: Option<LoudnessMeter> = None;
 
         // The decode loop.
         loop {
             // Check if we should stop.
             if should_stop.load(Ordering::SeqCst) {
-                break;
+                return false;
             }
 
             // Get the next packet from the media format.
             let packet = match format.next_packet() {
                 Ok(packet) => packet,
-                Err(Error::ResetRequired) => {
-                    println!("unexpected reset");
-                    return;
-                }
-                Err(Error::IoError(err)) => {
-                    if err.kind() == ErrorKind::UnexpectedEof {
-                        // Finished reading.
-                        break;
-                    }
-                    println!("error: {:?}", err);
+                Err(Error::IoError(err)) if err.kind() == ErrorKind::UnexpectedEof => {
+                    // Finished reading.
                     break;
                 }
                 Err(msg) => {
-                    println!("error: {:?}", msg);
-                    break;
+                    log_analysis_error(path, &msg);
+                    return false;
                 }
             };
 
@@ -162,34 +287,63 @@ impl CurrentSoundData {
             match decoder.decode(&packet) {
                 Ok(decoded_packet) => {
                     let spec = *decoded_packet.spec();
-                    let duration = decoded_packet.capacity() as u64;
-                    let mut read_buffer = SampleBuffer::<f32>::new(duration, spec);
-                    read_buffer.copy_planar_ref(decoded_packet);
-                    let read_samples = read_buffer.samples();
-
-                    // Find mean value.
-                    let mut mean_value: f32 = 0.0;
-                    for sample in read_samples {
-                        mean_value += sample.abs();
+                    let channel_count = spec.channels.count().max(1);
+                    let mut read_buffer =
+                        SampleBuffer::<f32>::new(decoded_packet.capacity() as u64, spec);
+                    read_buffer.copy_interleaved_ref(decoded_packet);
+
+                    if let Some((spectrogram, config)) = spectrogram.as_ref() {
+                        let analyzer = spectrogram_analyzer.get_or_insert_with(|| {
+                            let spectrogram = spectrogram.lock().unwrap();
+                            SpectrogramAnalyzer::new(&spectrogram, spec.rate, config)
+                        });
+                        for frame in read_buffer.samples().chunks_exact(channel_count) {
+                            analyzer.push_sample(frame.iter().sum::<f32>() / channel_count as f32);
+                        }
+
+                        let levels = analyzer.take_levels();
+                        if !levels.is_empty() {
+                            spectrogram.lock().unwrap().push_columns(&levels);
+                        }
                     }
-                    mean_value /= read_samples.len() as f32;
 
-                    // Update "processed" count.
-                    packets_to_average.push(mean_value);
+                    if loudness.is_some() {
+                        let meter = loudness_meter
+                            .get_or_insert_with(|| LoudnessMeter::new(spec.rate, channel_count));
+                        for frame in read_buffer.samples().chunks_exact(channel_count) {
+                            meter.push_frame(frame);
+                        }
+                    }
 
-                    if packets_to_average.len() >= packet_count_to_average {
-                        // Average all samples.
-                        let mut average_value = 0.0;
-                        for value in &packets_to_average {
-                            average_value += value;
+                    let wave = match wave.as_ref() {
+                        Some(wave) => wave,
+                        None => continue,
+                    };
+                    let mut finished_buckets = Vec::new();
+                    for frame in read_buffer.samples().chunks_exact(channel_count) {
+                        let frame_peaks = frame.iter().map(|sample| WavePeak {
+                            min: *sample,
+                            max: *sample,
+                        });
+                        if bucket_peaks.is_empty() {
+                            bucket_peaks.extend(frame_peaks);
+                        } else {
+                            for (peak, frame_peak) in bucket_peaks.iter_mut().zip(frame_peaks) {
+                                *peak = peak.merge(frame_peak);
+                            }
                         }
-                        average_value /= packets_to_average.len() as f32;
-                        packets_to_average.clear();
 
-                        // Add as a final sample.
-                        {
-                            let mut wave_data = wave.lock().unwrap();
-                            wave_data.push((average_value * 2.0 * u8::MAX as f32) as u8);
+                        bucket_frame_count += 1;
+                        if bucket_frame_count == frames_per_bucket {
+                            finished_buckets.push(std::mem::take(&mut bucket_peaks));
+                            bucket_frame_count = 0;
+                        }
+                    }
+
+                    if !finished_buckets.is_empty() {
+                        let mut wave_data = wave.lock().unwrap();
+                        for channel_peaks in finished_buckets {
+                            wave_data.push_bucket(&channel_peaks);
                         }
                     }
                 }
@@ -200,10 +354,39 @@ impl CurrentSoundData {
                     continue;
                 }
                 Err(msg) => {
-                    println!("error: {}", msg);
-                    return;
+                    log_analysis_error(path, &msg);
+                    return false;
                 }
             }
         }
+
+        // Add the last (possibly shorter) bucket.
+        if let Some(wave) = wave.as_ref() {
+            let mut wave_data = wave.lock().unwrap();
+            if !bucket_peaks.is_empty() {
+                wave_data.push_bucket(&bucket_peaks);
+            }
+            wave_data.finish();
+        }
+
+        if let (Some(loudness), Some(meter)) = (loudness.as_ref(), loudness_meter.as_ref()) {
+            *loudness.lock().unwrap() = meter.get_loudness();
+        }
+
+        // Compute columns whose windows reach beyond the end of the sound.
+        if let Some((spectrogram, _)) = spectrogram.as_ref() {
+            let mut spectrogram = spectrogram.lock().unwrap();
+            if let Some(analyzer) = spectrogram_analyzer.as_mut() {
+                analyzer.finish();
+                spectrogram.push_columns(&analyzer.take_levels());
+            }
+            spectrogram.finish();
+        }
+
+        true
     }
 }
+
+fn log_analysis_error(path: &str, error: &dyn Display) {
+    message_box::log_error(&format!("failed to analyze \"{}\", error: {}", path, error));
+}
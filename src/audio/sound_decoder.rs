@@ -0,0 +1,162 @@
+use kira::sound::streaming::Decoder;
+use kira::sound::FromFileError;
+use kira::Frame;
+use std::io::ErrorKind;
+use symphonia::core::audio::{AudioBufferRef, SampleBuffer};
+use symphonia::core::codecs::Decoder as SymphoniaDecoder;
+use symphonia::core::errors::Error;
+use symphonia::core::formats::{FormatReader, SeekMode, SeekTo};
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::probe::Hint;
+
+/// Number of silent frames returned when the file ends earlier than its header says.
+const END_OF_STREAM_CHUNK_FRAMES: usize = 1024;
+
+/// Streams frames of an audio file.
+///
+/// Unlike the decoder that kira uses for `StreamingSoundData::from_file` it also supports
+/// files that don't store their length in the header (for example VBR MP3 without Xing header)
+/// by adding up durations of packets when the file is opened.
+pub struct SoundDecoder {
+    format_reader: Box<dyn FormatReader>,
+    decoder: Box<dyn SymphoniaDecoder>,
+    sample_rate: u32,
+    num_frames: usize,
+    track_id: u32,
+}
+
+impl SoundDecoder {
+    pub fn new(path: &str) -> Result<Self, FromFileError> {
+        Self::create(path, None)
+    }
+
+    /// Opens the file whose number of frames is already known (to not count frames again
+    /// if the header doesn't store the length).
+    pub fn with_frame_count(path: &str, num_frames: usize) -> Result<Self, FromFileError> {
+        Self::create(path, Some(num_frames))
+    }
+
+    fn create(path: &str, known_num_frames: Option<usize>) -> Result<Self, FromFileError> {
+        let format_reader = Self::open(path)?;
+
+        let track = format_reader
+            .default_track()
+            .ok_or(FromFileError::NoDefaultTrack)?;
+        let track_id = track.id;
+        let sample_rate = track
+            .codec_params
+            .sample_rate
+            .ok_or(FromFileError::UnknownSampleRate)?;
+        let decoder =
+            symphonia::default::get_codecs().make(&track.codec_params, &Default::default())?;
+
+        let num_frames = match (known_num_frames, track.codec_params.n_frames) {
+            (Some(num_frames), _) => num_frames,
+            (None, Some(num_frames)) => num_frames as usize,
+            (None, None) => Self::count_frames(path, track_id)?,
+        };
+        if num_frames == 0 {
+            return Err(FromFileError::UnknownDuration);
+        }
+
+        Ok(Self {
+            format_reader,
+            decoder,
+            sample_rate,
+            num_frames,
+            track_id,
+        })
+    }
+
+    fn open(path: &str) -> Result<Box<dyn FormatReader>, FromFileError> {
+        let file = std::fs::File::open(path)?;
+        let mss = MediaSourceStream::new(Box::new(file), Default::default());
+
+        Ok(symphonia::default::get_probe()
+            .format(&Hint::new(), mss, &Default::default(), &Default::default())?
+            .format)
+    }
+
+    /// Reads packets of the whole track (without decoding them) to find the number
+    /// of frames in it.
+    fn count_frames(path: &str, track_id: u32) -> Result<usize, FromFileError> {
+        let mut format_reader = Self::open(path)?;
+
+        let mut num_frames = 0;
+        loop {
+            match format_reader.next_packet() {
+                Ok(packet) if packet.track_id() == track_id => num_frames += packet.dur as usize,
+                Ok(_) => {}
+                Err(Error::IoError(error)) if error.kind() == ErrorKind::UnexpectedEof => break,
+                Err(error) => return Err(error.into()),
+            }
+        }
+
+        Ok(num_frames)
+    }
+
+    /// Converts decoded samples to stereo frames (channels after the first two are ignored).
+    fn to_frames(buffer: AudioBufferRef) -> Vec<Frame> {
+        let channel_count = buffer.spec().channels.count();
+        let mut samples = SampleBuffer::<f32>::new(buffer.capacity() as u64, *buffer.spec());
+        samples.copy_interleaved_ref(buffer);
+
+        samples
+            .samples()
+            .chunks_exact(channel_count.max(1))
+            .map(|frame| match frame {
+                [mono] => Frame::from_mono(*mono),
+                [left, right, ..] => Frame::new(*left, *right),
+                [] => Frame::ZERO,
+            })
+            .collect()
+    }
+}
+
+impl Decoder for SoundDecoder {
+    type Error = FromFileError;
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn num_frames(&self) -> usize {
+        self.num_frames
+    }
+
+    fn decode(&mut self) -> Result<Vec<Frame>, Self::Error> {
+        loop {
+            let packet = match self.format_reader.next_packet() {
+                Ok(packet) => packet,
+                Err(Error::IoError(error)) if error.kind() == ErrorKind::UnexpectedEof => {
+                    // The header might report more frames than there are, return silence
+                    // until the player reaches the expected end.
+                    return Ok(vec![Frame::ZERO; END_OF_STREAM_CHUNK_FRAMES]);
+                }
+                Err(error) => return Err(error.into()),
+            };
+            if packet.track_id() != self.track_id {
+                continue;
+            }
+
+            match self.decoder.decode(&packet) {
+                Ok(buffer) => return Ok(Self::to_frames(buffer)),
+                Err(Error::DecodeError(_)) => continue, // skip corrupted packet
+                Err(error) => return Err(error.into()),
+            }
+        }
+    }
+
+    fn seek(&mut self, index: usize) -> Result<usize, Self::Error> {
+        let seeked_to = self.format_reader.seek(
+            SeekMode::Accurate,
+            SeekTo::TimeStamp {
+                ts: index as u64,
+                track_id: self.track_id,
+            },
+        )?;
+        self.decoder.reset();
+
+        Ok(seeked_to.actual_ts as usize)
+    }
+}
@@ -0,0 +1,404 @@
+/// Smallest number of frames that one bucket of the waveform covers (limits maximum zoom).
+pub const MIN_FRAMES_PER_BUCKET: usize = 512;
+
+/// Maximum number of parts that the waveform of a sound is split into
+/// (very long sounds use bigger buckets).
+pub const MAX_WAVE_BUCKET_COUNT: usize = 1 << 19;
+
+/// Number of buckets that are merged into one bucket of the next (coarser) resolution level.
+const LEVEL_MERGE_FACTOR: usize = 4;
+
+/// Minimum and maximum sample values (in range [-1.0; 1.0]) in a part of a sound.
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub struct WavePeak {
+    pub min: f32,
+    pub max: f32,
+}
+
+impl WavePeak {
+    /// Returns a peak that covers both peaks.
+    pub fn merge(self, other: WavePeak) -> WavePeak {
+        WavePeak {
+            min: self.min.min(other.min),
+            max: self.max.max(other.max),
+        }
+    }
+
+    /// Returns the largest absolute sample value.
+    pub fn amplitude(&self) -> f32 {
+        self.max.max(-self.min).min(1.0)
+    }
+}
+
+/// Waveform of a sound split into buckets of equal duration, each bucket stores a peak
+/// per channel.
+///
+/// Buckets are computed from the start of the sound, buckets that are not computed yet are
+/// considered pending. Besides the computed buckets coarser resolution levels are kept
+/// (each merges several buckets of the previous level) so that peaks of long parts of the sound
+/// can be quickly found.
+#[derive(Default)]
+pub struct SoundWave {
+    /// Peaks of all channels of the first bucket, then of the second bucket and so on,
+    /// the first level stores computed buckets.
+    levels: Vec<Vec<WavePeak>>,
+    channel_count: usize,
+    bucket_count: usize,
+    frames_per_bucket: usize,
+}
+
+impl SoundWave {
+    /// Creates an empty waveform for a sound with the specified number of frames.
+    pub fn new(num_frames: usize) -> Self {
+        let frames_per_bucket = num_frames
+            .div_ceil(MAX_WAVE_BUCKET_COUNT)
+            .max(MIN_FRAMES_PER_BUCKET);
+
+        Self {
+            levels: vec![Vec::new()],
+            channel_count: 0,
+            bucket_count: num_frames.div_ceil(frames_per_bucket),
+            frames_per_bucket,
+        }
+    }
+
+    /// Creates a complete waveform from peaks that were computed earlier (stored in the same
+    /// order as returned by [`SoundWave::get_ready_peaks`]).
+    ///
+    /// Returns `None` if the number of peaks does not match the number of frames.
+    pub fn from_peaks(
+        num_frames: usize,
+        channel_count: usize,
+        peaks: Vec<WavePeak>,
+    ) -> Option<Self> {
+        let mut wave = Self::new(num_frames);
+        if channel_count == 0 || peaks.len() != wave.bucket_count * channel_count {
+            return None;
+        }
+
+        wave.channel_count = channel_count;
+        wave.levels = vec![peaks];
+        wave.rebuild_levels();
+        Some(wave)
+    }
+
+    /// Returns the total number of buckets (including pending ones).
+    pub fn get_bucket_count(&self) -> usize {
+        self.bucket_count
+    }
+
+    /// Returns the number of frames that each bucket covers.
+    pub fn get_frames_per_bucket(&self) -> usize {
+        self.frames_per_bucket
+    }
+
+    /// Returns the number of channels (0 until the first bucket is computed).
+    pub fn get_channel_count(&self) -> usize {
+        self.channel_count
+    }
+
+    /// Returns the number of buckets that were computed.
+    pub fn get_ready_bucket_count(&self) -> usize {
+        self.get_level_bucket_count(0)
+    }
+
+    /// Returns peaks of all channels of the buckets that were computed
+    /// (peaks of all channels of the first bucket, then of the second bucket and so on).
+    pub fn get_ready_peaks(&self) -> &[WavePeak] {
+        &self.levels[0]
+    }
+
+    /// Tells if all buckets were computed.
+    pub fn is_complete(&self) -> bool {
+        self.get_ready_bucket_count() >= self.bucket_count
+    }
+
+    /// Returns the peak of all channels in the specified part of the sound
+    /// (`start` and `end` are portions of the sound's duration in range [0.0; 1.0]).
+    ///
+    /// Returns `None` if some buckets of this part were not computed yet.
+    pub fn get_peak(&self, start: f64, end: f64) -> Option<WavePeak> {
+        (0..self.channel_count)
+            .map(|channel| self.get_channel_peak(channel, start, end))
+            .reduce(|a, b| Some(a?.merge(b?)))?
+    }
+
+    /// Same as [`SoundWave::get_peak`] but only for one channel.
+    pub fn get_channel_peak(&self, channel: usize, start: f64, end: f64) -> Option<WavePeak> {
+        if channel >= self.channel_count || self.bucket_count == 0 {
+            return None;
+        }
+
+        let first_bucket = ((start * self.bucket_count as f64) as usize).min(self.bucket_count - 1);
+        let end_bucket = ((end * self.bucket_count as f64).ceil() as usize)
+            .clamp(first_bucket + 1, self.bucket_count);
+        if end_bucket > self.get_ready_bucket_count() {
+            return None;
+        }
+
+        // Use the coarsest level that still has about one bucket per requested part
+        // (falling back to finer levels if the last buckets were not merged yet).
+        let mut level = 0;
+        let mut level_bucket_size = 1;
+        while level + 1 < self.levels.len()
+            && level_bucket_size * LEVEL_MERGE_FACTOR <= end_bucket - first_bucket
+            && end_bucket.div_ceil(level_bucket_size * LEVEL_MERGE_FACTOR)
+                <= self.get_level_bucket_count(level + 1)
+        {
+            level += 1;
+            level_bucket_size *= LEVEL_MERGE_FACTOR;
+        }
+
+        let peaks = &self.levels[level];
+        (first_bucket / level_bucket_size..end_bucket.div_ceil(level_bucket_size))
+            .map(|bucket| peaks[bucket * self.channel_count + channel])
+            .reduce(WavePeak::merge)
+    }
+
+    /// Adds the next computed bucket with one peak per channel
+    /// (ignored if all buckets were already computed or the number of channels is different).
+    pub fn push_bucket(&mut self, channel_peaks: &[WavePeak]) {
+        if self.channel_count == 0 {
+            self.channel_count = channel_peaks.len();
+        }
+        if self.is_complete() || channel_peaks.len() != self.channel_count {
+            return;
+        }
+
+        self.levels[0].extend_from_slice(channel_peaks);
+
+        // Update coarser levels when a group of buckets is complete.
+        let mut level = 0;
+        while self
+            .get_level_bucket_count(level)
+            .is_multiple_of(LEVEL_MERGE_FACTOR)
+        {
+            let merged = self.merge_last_buckets(level);
+            if self.levels.len() == level + 1 {
+                self.levels.push(Vec::new());
+            }
+            self.levels[level + 1].extend(merged);
+            level += 1;
+        }
+    }
+
+    /// Marks remaining buckets as silent (used if the sound turned out to be shorter).
+    pub fn finish(&mut self) {
+        self.channel_count = self.channel_count.max(1);
+        self.levels[0].resize(self.bucket_count * self.channel_count, WavePeak::default());
+        self.rebuild_levels();
+    }
+
+    fn get_level_bucket_count(&self, level: usize) -> usize {
+        self.levels[level]
+            .len()
+            .checked_div(self.channel_count)
+            .unwrap_or(0)
+    }
+
+    /// Merges the last group of buckets of the specified level into peaks of one bucket.
+    fn merge_last_buckets(&self, level: usize) -> Vec<WavePeak> {
+        let group_size = LEVEL_MERGE_FACTOR * self.channel_count;
+        let peaks = &self.levels[level];
+
+        Self::merge_buckets(
+            &peaks[peaks.len().saturating_sub(group_size)..],
+            self.channel_count,
+        )
+    }
+
+    /// Merges peaks of several buckets into peaks of one bucket.
+    fn merge_buckets(peaks: &[WavePeak], channel_count: usize) -> Vec<WavePeak> {
+        (0..channel_count)
+            .map(|channel| {
+                peaks
+                    .iter()
+                    .skip(channel)
+                    .step_by(channel_count)
+                    .copied()
+                    .reduce(WavePeak::merge)
+                    .unwrap_or_default()
+            })
+            .collect()
+    }
+
+    /// Creates coarser levels from the computed buckets (including partial groups at the end).
+    fn rebuild_levels(&mut self) {
+        self.levels.truncate(1);
+
+        while self.get_level_bucket_count(self.levels.len() - 1) > 1 {
+            let level = self
+                .levels
+                .last()
+                .unwrap()
+                .chunks(LEVEL_MERGE_FACTOR * self.channel_count)
+                .flat_map(|group| Self::merge_buckets(group, self.channel_count))
+                .collect();
+            self.levels.push(level);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn peak(min: f32, max: f32) -> WavePeak {
+        WavePeak { min, max }
+    }
+
+    /// Peak of the bucket in a test wave (different for every bucket and channel).
+    fn test_peak(bucket: usize, channel: usize) -> WavePeak {
+        let value = ((bucket * 7 + channel * 3) % 11) as f32 / 10.0;
+        peak(-value / 2.0, value)
+    }
+
+    fn create_wave(bucket_count: usize, channel_count: usize, ready_count: usize) -> SoundWave {
+        let mut wave = SoundWave::new(bucket_count * MIN_FRAMES_PER_BUCKET);
+        for bucket in 0..ready_count {
+            let peaks: Vec<WavePeak> = (0..channel_count)
+                .map(|channel| test_peak(bucket, channel))
+                .collect();
+            wave.push_bucket(&peaks);
+        }
+        wave
+    }
+
+    /// Merges the test peaks of the buckets one by one.
+    fn merge_buckets(channel: usize, first: usize, end: usize) -> WavePeak {
+        (first..end)
+            .map(|bucket| test_peak(bucket, channel))
+            .reduce(WavePeak::merge)
+            .unwrap()
+    }
+
+    /// Returns the part of the sound that covers buckets in range [first; end).
+    fn get_range(wave: &SoundWave, first: usize, end: usize) -> (f64, f64) {
+        let bucket_count = wave.get_bucket_count() as f64;
+        (
+            (first as f64 + 0.5) / bucket_count,
+            (end as f64 - 0.5) / bucket_count,
+        )
+    }
+
+    fn contains(outer: WavePeak, inner: WavePeak) -> bool {
+        outer.min <= inner.min && outer.max >= inner.max
+    }
+
+    #[test]
+    fn merge_covers_both_peaks() {
+        assert_eq!(peak(-0.5, 0.2).merge(peak(-0.1, 0.8)), peak(-0.5, 0.8));
+        assert_eq!(peak(-0.7, 0.3).amplitude(), 0.7);
+        assert_eq!(peak(-2.0, 0.3).amplitude(), 1.0);
+    }
+
+    #[test]
+    fn new_splits_sound_into_buckets() {
+        let wave = SoundWave::new(10 * MIN_FRAMES_PER_BUCKET + 1);
+        assert_eq!(wave.get_frames_per_bucket(), MIN_FRAMES_PER_BUCKET);
+        assert_eq!(wave.get_bucket_count(), 11);
+
+        let wave = SoundWave::new(MAX_WAVE_BUCKET_COUNT * MIN_FRAMES_PER_BUCKET * 3);
+        assert_eq!(wave.get_bucket_count(), MAX_WAVE_BUCKET_COUNT);
+    }
+
+    #[test]
+    fn get_peak_of_few_buckets_is_exact() {
+        let wave = create_wave(103, 2, 103);
+        assert!(wave.is_complete());
+
+        for first in 0..103 {
+            for end in first + 1..(first + LEVEL_MERGE_FACTOR).min(104) {
+                let (start_pos, end_pos) = get_range(&wave, first, end);
+                for channel in 0..2 {
+                    assert_eq!(
+                        wave.get_channel_peak(channel, start_pos, end_pos),
+                        Some(merge_buckets(channel, first, end)),
+                    );
+                }
+                assert_eq!(
+                    wave.get_peak(start_pos, end_pos),
+                    Some(merge_buckets(0, first, end).merge(merge_buckets(1, first, end))),
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn get_peak_of_many_buckets_stays_near_requested_range() {
+        let wave = create_wave(103, 1, 103);
+
+        for first in 0..103 {
+            for end in first + LEVEL_MERGE_FACTOR..=103 {
+                // A bucket of the used level is not longer than the range,
+                // so less than the range's length may be added on each side.
+                let margin = end - first - 1;
+                let outer = merge_buckets(0, first.saturating_sub(margin), (end + margin).min(103));
+                let inner = merge_buckets(0, first, end);
+
+                let (start_pos, end_pos) = get_range(&wave, first, end);
+                let peak = wave.get_peak(start_pos, end_pos).unwrap();
+                assert!(contains(peak, inner) && contains(outer, peak));
+            }
+        }
+        assert_eq!(wave.get_peak(0.0, 1.0), Some(merge_buckets(0, 0, 103)));
+    }
+
+    #[test]
+    fn get_peak_of_pending_buckets_is_none() {
+        let wave = create_wave(64, 1, 21);
+        assert!(!wave.is_complete());
+        assert_eq!(wave.get_ready_bucket_count(), 21);
+
+        let (start_pos, end_pos) = get_range(&wave, 0, 21);
+        assert_eq!(
+            wave.get_peak(start_pos, end_pos),
+            Some(merge_buckets(0, 0, 21))
+        );
+        let (start_pos, end_pos) = get_range(&wave, 0, 22);
+        assert_eq!(wave.get_peak(start_pos, end_pos), None);
+        assert_eq!(wave.get_peak(0.5, 1.0), None);
+        assert_eq!(wave.get_channel_peak(1, 0.0, 0.1), None);
+    }
+
+    #[test]
+    fn push_bucket_ignores_wrong_channel_count_and_extra_buckets() {
+        let mut wave = create_wave(4, 2, 0);
+        wave.push_bucket(&[peak(-0.1, 0.1), peak(-0.2, 0.2)]);
+        wave.push_bucket(&[peak(-0.9, 0.9)]);
+        assert_eq!(wave.get_channel_count(), 2);
+        assert_eq!(wave.get_ready_bucket_count(), 1);
+
+        for _ in 0..5 {
+            wave.push_bucket(&[peak(-0.1, 0.1), peak(-0.2, 0.2)]);
+        }
+        assert_eq!(wave.get_ready_bucket_count(), 4);
+    }
+
+    #[test]
+    fn finish_fills_remaining_buckets_with_silence() {
+        let mut wave = create_wave(10, 1, 3);
+        wave.finish();
+
+        assert!(wave.is_complete());
+        assert_eq!(wave.get_peak(0.5, 1.0), Some(WavePeak::default()));
+        assert_eq!(wave.get_peak(0.0, 1.0), Some(merge_buckets(0, 0, 3)));
+    }
+
+    #[test]
+    fn from_peaks_restores_computed_wave() {
+        let wave = create_wave(37, 2, 37);
+        let num_frames = 37 * MIN_FRAMES_PER_BUCKET;
+        let restored =
+            SoundWave::from_peaks(num_frames, 2, wave.get_ready_peaks().to_vec()).unwrap();
+
+        assert!(restored.is_complete());
+        for start in 0..37 {
+            let (start, end) = (start as f64 / 37.0, 1.0);
+            assert_eq!(restored.get_peak(start, end), wave.get_peak(start, end));
+        }
+
+        assert!(SoundWave::from_peaks(num_frames, 2, vec![WavePeak::default(); 10]).is_none());
+        assert!(SoundWave::from_peaks(num_frames, 0, Vec::new()).is_none());
+    }
+}
@@ -0,0 +1,229 @@
+use super::fft::{create_window, fft, get_full_scale_power, get_log_band_bins};
+use crate::misc::config_manger::SpectrogramConfig;
+
+/// Maximum number of columns (points in time) that the spectrogram of a sound is split into.
+pub const MAX_SPECTROGRAM_COLUMN_COUNT: usize = 8192;
+
+/// Smallest number of frames between two columns (short sounds have fewer columns).
+const MIN_FRAMES_PER_COLUMN: usize = 256;
+
+const MIN_WINDOW_SIZE: usize = 256;
+const MAX_WINDOW_SIZE: usize = 16384;
+
+/// Levels of frequency bands of a sound over time.
+///
+/// Columns are computed from the start of the sound, columns that are not computed yet are
+/// considered pending. Levels are stored in range [0; 255] where 0 is the bottom of the
+/// configured dynamic range and 255 is a full scale sine.
+#[derive(Default)]
+pub struct Spectrogram {
+    /// Levels of all bands of the first column (from the lowest frequency), then of the
+    /// second column and so on.
+    levels: Vec<u8>,
+    band_count: usize,
+    column_count: usize,
+    frames_per_column: usize,
+    num_frames: usize,
+}
+
+impl Spectrogram {
+    /// Creates an empty spectrogram for a sound with the specified number of frames.
+    pub fn new(num_frames: usize, band_count: usize) -> Self {
+        let frames_per_column = num_frames
+            .div_ceil(MAX_SPECTROGRAM_COLUMN_COUNT)
+            .max(MIN_FRAMES_PER_COLUMN);
+
+        Self {
+            levels: Vec::new(),
+            band_count: band_count.max(1),
+            column_count: num_frames.div_ceil(frames_per_column),
+            frames_per_column,
+            num_frames,
+        }
+    }
+
+    /// Returns the number of frequency bands in each column.
+    pub fn get_band_count(&self) -> usize {
+        self.band_count
+    }
+
+    /// Returns the total number of columns (including pending ones).
+    pub fn get_column_count(&self) -> usize {
+        self.column_count
+    }
+
+    /// Returns the number of frames between two columns.
+    pub fn get_frames_per_column(&self) -> usize {
+        self.frames_per_column
+    }
+
+    /// Returns the number of columns that were computed.
+    pub fn get_ready_column_count(&self) -> usize {
+        self.levels.len() / self.band_count.max(1)
+    }
+
+    /// Tells if all columns were computed.
+    pub fn is_complete(&self) -> bool {
+        self.get_ready_column_count() >= self.column_count
+    }
+
+    /// Returns the highest level (in range [0.0; 1.0]) of bands in range
+    /// [`band_start`; `band_end`) in the specified part of the sound (`start` and `end` are
+    /// portions of the sound's duration in range [0.0; 1.0]).
+    ///
+    /// Returns `None` if some columns of this part were not computed yet.
+    pub fn get_level(
+        &self,
+        start: f64,
+        end: f64,
+        band_start: usize,
+        band_end: usize,
+    ) -> Option<f32> {
+        if self.column_count == 0 {
+            return None;
+        }
+
+        let frames_per_column = self.frames_per_column as f64;
+        let first_column = ((start.clamp(0.0, 1.0) * self.num_frames as f64 / frames_per_column)
+            as usize)
+            .min(self.column_count - 1);
+        let last_column =
+            ((end.clamp(0.0, 1.0) * self.num_frames as f64 / frames_per_column).ceil() as usize)
+                .clamp(first_column + 1, self.column_count);
+        if last_column > self.get_ready_column_count() {
+            return None;
+        }
+
+        let band_end = band_end.clamp(band_start + 1, self.band_count);
+        let level = (first_column..last_column)
+            .flat_map(|column| {
+                let offset = column * self.band_count;
+                self.levels[offset + band_start..offset + band_end].iter()
+            })
+            .max()
+            .copied()
+            .unwrap_or(0);
+
+        Some(level as f32 / u8::MAX as f32)
+    }
+
+    /// Adds computed columns (levels of all bands of each column one after another).
+    pub fn push_columns(&mut self, levels: &[u8]) {
+        let max_len = self.column_count * self.band_count;
+        let len = levels.len().min(max_len.saturating_sub(self.levels.len()));
+        self.levels.extend_from_slice(&levels[..len]);
+    }
+
+    /// Marks the rest of the columns as silent.
+    pub fn finish(&mut self) {
+        self.levels.resize(self.column_count * self.band_count, 0);
+    }
+}
+
+/// Computes columns of a [`Spectrogram`] using the short-time Fourier transform
+/// of the samples that are pushed one by one.
+pub struct SpectrogramAnalyzer {
+    window: Vec<f32>,
+    /// The last `window.len()` samples (circular buffer).
+    history: Vec<f32>,
+    history_pos: usize,
+    frame_index: usize,
+    /// Frame index at which the window of the next column is filled.
+    next_column_end: usize,
+    frames_per_column: usize,
+    column_count: usize,
+    computed_column_count: usize,
+    /// Range of FFT bins that each band covers.
+    band_bins: Vec<(usize, usize)>,
+    /// Power of a full scale sine.
+    reference_power: f32,
+    dynamic_range_db: f32,
+    real: Vec<f32>,
+    imag: Vec<f32>,
+    finished_levels: Vec<u8>,
+}
+
+impl SpectrogramAnalyzer {
+    pub fn new(spectrogram: &Spectrogram, sample_rate: u32, config: &SpectrogramConfig) -> Self {
+        let window_size = config
+            .window_size
+            .clamp(MIN_WINDOW_SIZE, MAX_WINDOW_SIZE)
+            .next_power_of_two();
+        let window = create_window(config.window_function, window_size);
+        let reference_power = get_full_scale_power(&window);
+        let band_bins = get_log_band_bins(
+            spectrogram.get_band_count(),
+            window_size,
+            sample_rate,
+            config.min_frequency_hz,
+        );
+
+        let frames_per_column = spectrogram.get_frames_per_column();
+        Self {
+            window,
+            history: vec![0.0; window_size],
+            history_pos: 0,
+            frame_index: 0,
+            // Windows are centered on columns, the first one is padded with silence.
+            next_column_end: frames_per_column / 2 + window_size / 2,
+            frames_per_column,
+            column_count: spectrogram.get_column_count(),
+            computed_column_count: 0,
+            band_bins,
+            reference_power,
+            dynamic_range_db: config.dynamic_range_db.max(1.0),
+            real: vec![0.0; window_size],
+            imag: vec![0.0; window_size],
+            finished_levels: Vec::new(),
+        }
+    }
+
+    /// Adds the next sample (channels mixed to mono).
+    pub fn push_sample(&mut self, sample: f32) {
+        let window_size = self.history.len();
+        self.history[self.history_pos] = sample;
+        self.history_pos = (self.history_pos + 1) % window_size;
+        self.frame_index += 1;
+
+        if self.frame_index == self.next_column_end
+            && self.computed_column_count < self.column_count
+        {
+            self.compute_column();
+            self.next_column_end += self.frames_per_column;
+        }
+    }
+
+    /// Computes the remaining columns as if the sound was followed by silence.
+    pub fn finish(&mut self) {
+        while self.computed_column_count < self.column_count {
+            self.push_sample(0.0);
+        }
+    }
+
+    /// Returns levels of columns computed since the last call (see [`Spectrogram::push_columns`]).
+    pub fn take_levels(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.finished_levels)
+    }
+
+    fn compute_column(&mut self) {
+        // The oldest sample is at the current position of the circular buffer.
+        let window_size = self.history.len();
+        for i in 0..window_size {
+            self.real[i] = self.history[(self.history_pos + i) % window_size] * self.window[i];
+            self.imag[i] = 0.0;
+        }
+        fft(&mut self.real, &mut self.imag);
+
+        for &(start, end) in &self.band_bins {
+            let power = (start..end)
+                .map(|bin| self.real[bin].powi(2) + self.imag[bin].powi(2))
+                .fold(0.0, f32::max);
+            let db = 10.0 * (power / self.reference_power).max(1e-20).log10();
+            let level = ((db + self.dynamic_range_db) / self.dynamic_range_db).clamp(0.0, 1.0);
+            self.finished_levels
+                .push((level * u8::MAX as f32).round() as u8);
+        }
+
+        self.computed_column_count += 1;
+    }
+}
@@ -0,0 +1,138 @@
+use crate::misc::config_manger::StereoConfig;
+use kira::effect::{Effect, EffectBuilder};
+use kira::info::Info;
+use kira::Frame;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::Arc;
+
+/// Options shared between the effect and its handle.
+#[derive(Default)]
+struct SharedOptions {
+    /// Bits of `f32` in range [-1.0; 1.0].
+    balance: AtomicU32,
+    mono: AtomicBool,
+    swap_channels: AtomicBool,
+    mute_left: AtomicBool,
+    mute_right: AtomicBool,
+}
+
+/// Changes balance, mixes channels to mono, swaps and mutes channels.
+///
+/// All options are combined into a 2x2 matrix that mixes input channels into output
+/// channels, the matrix changes smoothly over one processed block to avoid clicks.
+pub struct StereoControlBuilder {
+    options: Arc<SharedOptions>,
+}
+
+/// Controls the [`StereoControlBuilder`] effect from other threads.
+pub struct StereoControlHandle {
+    options: Arc<SharedOptions>,
+}
+
+struct StereoControl {
+    options: Arc<SharedOptions>,
+    /// Rows are output channels, columns are input channels.
+    matrix: [[f32; 2]; 2],
+}
+
+impl StereoControlBuilder {
+    pub fn new() -> (Self, StereoControlHandle) {
+        let options = Arc::new(SharedOptions::default());
+
+        (
+            Self {
+                options: options.clone(),
+            },
+            StereoControlHandle { options },
+        )
+    }
+}
+
+impl StereoControlHandle {
+    pub fn set_options(&self, config: &StereoConfig) {
+        let options = &self.options;
+        options
+            .balance
+            .store(config.balance.clamp(-1.0, 1.0).to_bits(), Ordering::Relaxed);
+        options.mono.store(config.mono, Ordering::Relaxed);
+        options
+            .swap_channels
+            .store(config.swap_channels, Ordering::Relaxed);
+        options.mute_left.store(config.mute_left, Ordering::Relaxed);
+        options
+            .mute_right
+            .store(config.mute_right, Ordering::Relaxed);
+    }
+}
+
+impl EffectBuilder for StereoControlBuilder {
+    type Handle = ();
+
+    fn build(self) -> (Box<dyn Effect>, Self::Handle) {
+        (
+            Box::new(StereoControl {
+                options: self.options,
+                matrix: [[1.0, 0.0], [0.0, 1.0]],
+            }),
+            (),
+        )
+    }
+}
+
+impl StereoControl {
+    fn get_target_matrix(&self) -> [[f32; 2]; 2] {
+        let options = &self.options;
+
+        let mut matrix = if options.mono.load(Ordering::Relaxed) {
+            [[0.5, 0.5], [0.5, 0.5]]
+        } else if options.swap_channels.load(Ordering::Relaxed) {
+            [[0.0, 1.0], [1.0, 0.0]]
+        } else {
+            [[1.0, 0.0], [0.0, 1.0]]
+        };
+
+        // Balance turns down the opposite channel.
+        let balance = f32::from_bits(options.balance.load(Ordering::Relaxed));
+        let gains = [
+            if options.mute_left.load(Ordering::Relaxed) {
+                0.0
+            } else {
+                (1.0 - balance).min(1.0)
+            },
+            if options.mute_right.load(Ordering::Relaxed) {
+                0.0
+            } else {
+                (1.0 + balance).min(1.0)
+            },
+        ];
+        for (row, gain) in matrix.iter_mut().zip(gains) {
+            row[0] *= gain;
+            row[1] *= gain;
+        }
+
+        matrix
+    }
+}
+
+impl Effect for StereoControl {
+    fn process(&mut self, input: &mut [Frame], _dt: f64, _info: &Info) {
+        let target = self.get_target_matrix();
+        let start = self.matrix;
+        let identity = [[1.0, 0.0], [0.0, 1.0]];
+        if start == identity && target == identity {
+            return;
+        }
+
+        let frame_count = input.len().max(1) as f32;
+        for (index, frame) in input.iter_mut().enumerate() {
+            let t = (index + 1) as f32 / frame_count;
+            let mix = |row: usize| {
+                let left_gain = start[row][0] + (target[row][0] - start[row][0]) * t;
+                let right_gain = start[row][1] + (target[row][1] - start[row][1]) * t;
+                frame.left * left_gain + frame.right * right_gain
+            };
+            *frame = Frame::new(mix(0), mix(1));
+        }
+        self.matrix = target;
+    }
+}
@@ -0,0 +1,107 @@
+use symphonia::core::formats::FormatOptions;
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::{MetadataOptions, MetadataRevision, StandardTagKey, StandardVisualKey};
+use symphonia::core::probe::{Hint, ProbeResult};
+
+use super::replay_gain::ReplayGainTags;
+
+/// Image embedded in an audio file.
+pub struct CoverArt {
+    /// For example "image/jpeg".
+    pub media_type: String,
+    pub data: Vec<u8>,
+}
+
+/// Tags read from an audio file.
+#[derive(Clone, Default)]
+pub struct TrackMetadata {
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub replay_gain: ReplayGainTags,
+}
+
+impl TrackMetadata {
+    /// Reads tags from the specified file (returns empty metadata if failed).
+    pub fn read(path: &str) -> Self {
+        let mut metadata = Self::default();
+
+        let mut probed = match Self::probe(path) {
+            Some(probed) => probed,
+            None => return metadata,
+        };
+
+        // Tags can be stored before the container (for example ID3v2) or inside of it.
+        if let Some(mut probed_metadata) = probed.metadata.get() {
+            if let Some(revision) = probed_metadata.skip_to_latest() {
+                metadata.apply_revision(revision);
+            }
+        }
+        if let Some(revision) = probed.format.metadata().skip_to_latest() {
+            metadata.apply_revision(revision);
+        }
+
+        metadata
+    }
+
+    /// Returns an embedded image (front cover if there are multiple images).
+    pub fn read_cover_art(path: &str) -> Option<CoverArt> {
+        let mut probed = Self::probe(path)?;
+
+        let mut visuals = Vec::new();
+        if let Some(mut probed_metadata) = probed.metadata.get() {
+            if let Some(revision) = probed_metadata.skip_to_latest() {
+                visuals.extend(revision.visuals().iter().cloned());
+            }
+        }
+        if let Some(revision) = probed.format.metadata().skip_to_latest() {
+            visuals.extend(revision.visuals().iter().cloned());
+        }
+
+        let visual = visuals
+            .iter()
+            .find(|visual| visual.usage == Some(StandardVisualKey::FrontCover))
+            .or(visuals.first())?;
+
+        Some(CoverArt {
+            media_type: visual.media_type.clone(),
+            data: visual.data.to_vec(),
+        })
+    }
+
+    fn probe(path: &str) -> Option<ProbeResult> {
+        // Open the media source.
+        let src = std::fs::File::open(path).ok()?;
+        let mss = MediaSourceStream::new(Box::new(src), Default::default());
+
+        // Probe the media source.
+        symphonia::default::get_probe()
+            .format(
+                &Hint::new(),
+                mss,
+                &FormatOptions::default(),
+                &MetadataOptions::default(),
+            )
+            .ok()
+    }
+
+    fn apply_revision(&mut self, revision: &MetadataRevision) {
+        for tag in revision.tags() {
+            if let Some(key) = tag.std_key {
+                self.replay_gain.apply_tag(key, &tag.value.to_string());
+            }
+
+            let field = match tag.std_key {
+                Some(StandardTagKey::TrackTitle) => &mut self.title,
+                Some(StandardTagKey::Artist) => &mut self.artist,
+                Some(StandardTagKey::Album) => &mut self.album,
+                _ => continue,
+            };
+
+            let value = tag.value.to_string().trim().to_string();
+            if !value.is_empty() {
+                *field = Some(value);
+            }
+        }
+    }
+}
@@ -0,0 +1,143 @@
+use super::biquad::Biquad;
+use kira::effect::{Effect, EffectBuilder};
+use kira::info::Info;
+use kira::Frame;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::Arc;
+
+/// Resonance of the filter that separates the bass.
+const BASS_FILTER_Q: f64 = 0.707;
+
+/// Reduces vocals (and anything else panned to the center) by removing the mid signal
+/// (the part that is the same in both channels) while keeping the side signal.
+///
+/// Optionally the bass part of the mid signal is kept because bass and kick drums are
+/// usually in the center too.
+pub struct VocalReducerBuilder {
+    options: Arc<SharedOptions>,
+}
+
+/// Controls the [`VocalReducerBuilder`] effect from other threads.
+pub struct VocalReducerHandle {
+    options: Arc<SharedOptions>,
+}
+
+#[derive(Default)]
+struct SharedOptions {
+    /// Bits of `f32` in range [0.0; 1.0], 0.0 disables the effect.
+    strength: AtomicU32,
+    preserve_bass: AtomicBool,
+    /// Bits of `f32`.
+    bass_cutoff_hz: AtomicU32,
+}
+
+struct VocalReducer {
+    options: Arc<SharedOptions>,
+    sample_rate: u32,
+    bass_filter: Biquad,
+    bass_cutoff_hz: f32,
+    /// Strength applied at the end of the last block.
+    strength: f32,
+}
+
+impl VocalReducerBuilder {
+    pub fn new() -> (Self, VocalReducerHandle) {
+        let options = Arc::new(SharedOptions::default());
+
+        (
+            Self {
+                options: options.clone(),
+            },
+            VocalReducerHandle { options },
+        )
+    }
+}
+
+impl VocalReducerHandle {
+    /// Sets how much of the center is removed (0.0 disables the effect).
+    pub fn set_strength(&self, strength: f32) {
+        self.options
+            .strength
+            .store(strength.clamp(0.0, 1.0).to_bits(), Ordering::Relaxed);
+    }
+
+    /// Keeps the center below the cutoff frequency (`None` to remove the whole center).
+    pub fn set_preserved_bass(&self, bass_cutoff_hz: Option<f32>) {
+        self.options
+            .preserve_bass
+            .store(bass_cutoff_hz.is_some(), Ordering::Relaxed);
+        if let Some(bass_cutoff_hz) = bass_cutoff_hz {
+            self.options
+                .bass_cutoff_hz
+                .store(bass_cutoff_hz.max(1.0).to_bits(), Ordering::Relaxed);
+        }
+    }
+}
+
+impl EffectBuilder for VocalReducerBuilder {
+    type Handle = ();
+
+    fn build(self) -> (Box<dyn Effect>, Self::Handle) {
+        (
+            Box::new(VocalReducer {
+                options: self.options,
+                sample_rate: 0,
+                bass_filter: Biquad::default(),
+                bass_cutoff_hz: 0.0,
+                strength: 0.0,
+            }),
+            (),
+        )
+    }
+}
+
+impl Effect for VocalReducer {
+    fn init(&mut self, sample_rate: u32, _internal_buffer_size: usize) {
+        self.sample_rate = sample_rate;
+        self.bass_cutoff_hz = 0.0;
+    }
+
+    fn on_change_sample_rate(&mut self, sample_rate: u32) {
+        self.sample_rate = sample_rate;
+        self.bass_cutoff_hz = 0.0;
+    }
+
+    fn process(&mut self, input: &mut [Frame], _dt: f64, _info: &Info) {
+        let target_strength = f32::from_bits(self.options.strength.load(Ordering::Relaxed));
+        let start_strength = self.strength;
+        self.strength = target_strength;
+        if start_strength == 0.0 && target_strength == 0.0 {
+            return;
+        }
+
+        // Recreate the bass filter if the cutoff was changed.
+        let preserve_bass = self.options.preserve_bass.load(Ordering::Relaxed);
+        let bass_cutoff_hz = f32::from_bits(self.options.bass_cutoff_hz.load(Ordering::Relaxed));
+        if preserve_bass && bass_cutoff_hz != self.bass_cutoff_hz {
+            self.bass_filter = Biquad::low_pass(
+                self.sample_rate.max(1) as f64,
+                bass_cutoff_hz as f64,
+                BASS_FILTER_Q,
+            );
+            self.bass_cutoff_hz = bass_cutoff_hz;
+        }
+
+        // Change the strength smoothly over the block to avoid clicks.
+        let frame_count = input.len().max(1) as f32;
+        for (index, frame) in input.iter_mut().enumerate() {
+            let strength = start_strength
+                + (target_strength - start_strength) * (index + 1) as f32 / frame_count;
+
+            let mid = (frame.left + frame.right) / 2.0;
+            let side = (frame.left - frame.right) / 2.0;
+            let removed = if preserve_bass {
+                mid - self.bass_filter.process(mid as f64) as f32
+            } else {
+                mid
+            };
+
+            let mid = mid - removed * strength;
+            *frame = Frame::new(mid + side, mid - side);
+        }
+    }
+}
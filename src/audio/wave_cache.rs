@@ -0,0 +1,202 @@
+use super::sound_wave::{SoundWave, WavePeak};
+use crate::misc::config_manger::{ConfigManager, WaveformCacheConfig};
+use crate::misc::message_box;
+use sha1::{Digest, Sha1};
+use std::fs::File;
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const CACHE_DIRECTORY_NAME: &str = "waveforms";
+const CACHE_FILE_EXTENSION: &str = "wave";
+
+/// Written at the start of cache files, the version is increased when the format changes.
+const CACHE_FILE_MAGIC: &[u8; 4] = b"TAPW";
+const CACHE_FILE_VERSION: u32 = 2;
+
+/// Stores computed waveforms on disk so that they don't need to be computed again.
+///
+/// Entries are keyed by the file's path, size and modification time. When the cache gets
+/// bigger than the limit the least recently used entries are removed.
+#[derive(Clone)]
+pub struct WaveCache {
+    directory: PathBuf,
+    max_size: u64,
+}
+
+impl WaveCache {
+    /// Returns `None` if the cache is disabled or the cache directory is not available.
+    pub fn new(config: &WaveformCacheConfig) -> Option<Self> {
+        if !config.enabled {
+            return None;
+        }
+
+        let directory = ConfigManager::get_cache_directory()?.join(CACHE_DIRECTORY_NAME);
+        if let Err(msg) = std::fs::create_dir_all(&directory) {
+            message_box::log_error(&format!(
+                "failed to create waveform cache directory, error: {}",
+                msg
+            ));
+            return None;
+        }
+
+        Some(Self {
+            directory,
+            max_size: config.max_size_mb * 1024 * 1024,
+        })
+    }
+
+    /// Tells if there's a cached wave for the specified sound.
+    pub fn contains(&self, path: &str) -> bool {
+        self.get_cache_file_path(path)
+            .is_some_and(|cache_path| cache_path.is_file())
+    }
+
+    /// Returns the cached wave for the specified sound (if it's in the cache).
+    pub fn load(&self, path: &str, num_frames: usize) -> Option<SoundWave> {
+        let cache_path = self.get_cache_file_path(path)?;
+        let data = std::fs::read(&cache_path).ok()?;
+
+        // Check header.
+        let header_size = CACHE_FILE_MAGIC.len() + 12;
+        if data.len() < header_size || &data[..CACHE_FILE_MAGIC.len()] != CACHE_FILE_MAGIC {
+            return None;
+        }
+        let read_u32 = |offset: usize| {
+            u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap_or_default())
+        };
+        if read_u32(CACHE_FILE_MAGIC.len()) != CACHE_FILE_VERSION {
+            return None;
+        }
+
+        let channel_count = read_u32(CACHE_FILE_MAGIC.len() + 4) as usize;
+        let peak_count = read_u32(CACHE_FILE_MAGIC.len() + 8) as usize;
+        if data.len() != header_size + peak_count * 8 {
+            return None;
+        }
+
+        let peaks = data[header_size..]
+            .chunks_exact(8)
+            .map(|peak| WavePeak {
+                min: f32::from_le_bytes(peak[..4].try_into().unwrap_or_default()),
+                max: f32::from_le_bytes(peak[4..].try_into().unwrap_or_default()),
+            })
+            .collect();
+
+        let wave = SoundWave::from_peaks(num_frames, channel_count, peaks)?;
+
+        // Mark as recently used.
+        if let Ok(file) = File::options().write(true).open(&cache_path) {
+            let _ = file.set_modified(SystemTime::now());
+        }
+
+        Some(wave)
+    }
+
+    /// Saves a completely computed wave and removes old entries if the cache is too big.
+    pub fn store(&self, path: &str, wave: &SoundWave) {
+        if !wave.is_complete() {
+            return;
+        }
+        let cache_path = match self.get_cache_file_path(path) {
+            None => return,
+            Some(cache_path) => cache_path,
+        };
+
+        let peaks = wave.get_ready_peaks();
+        let mut data = Vec::with_capacity(CACHE_FILE_MAGIC.len() + 12 + peaks.len() * 8);
+        data.extend_from_slice(CACHE_FILE_MAGIC);
+        data.extend_from_slice(&CACHE_FILE_VERSION.to_le_bytes());
+        data.extend_from_slice(&(wave.get_channel_count() as u32).to_le_bytes());
+        data.extend_from_slice(&(peaks.len() as u32).to_le_bytes());
+        for peak in peaks {
+            data.extend_from_slice(&peak.min.to_le_bytes());
+            data.extend_from_slice(&peak.max.to_le_bytes());
+        }
+
+        // Write to a temporary file first so that other instances never read partial entries.
+        let temp_path = cache_path.with_extension("tmp");
+        let result = File::create(&temp_path)
+            .and_then(|mut file| file.write_all(&data))
+            .and_then(|_| std::fs::rename(&temp_path, &cache_path));
+        if let Err(msg) = result {
+            message_box::log_error(&format!(
+                "failed to write waveform cache entry, error: {}",
+                msg
+            ));
+            let _ = std::fs::remove_file(&temp_path);
+            return;
+        }
+
+        self.remove_least_recently_used();
+    }
+
+    /// Returns path to the cache entry of the specified sound (`None` if the sound does not exist).
+    fn get_cache_file_path(&self, path: &str) -> Option<PathBuf> {
+        let key = get_sound_file_key(path)?;
+
+        Some(
+            self.directory
+                .join(format!("{}.{}", key, CACHE_FILE_EXTENSION)),
+        )
+    }
+
+    /// Removes entries that were not used for the longest time until the cache fits the limit.
+    fn remove_least_recently_used(&self) {
+        let entries = match std::fs::read_dir(&self.directory) {
+            Ok(entries) => entries,
+            Err(_) => return,
+        };
+
+        let mut files: Vec<(SystemTime, u64, PathBuf)> = entries
+            .filter_map(Result::ok)
+            .map(|entry| entry.path())
+            .filter(|path| {
+                path.extension()
+                    .is_some_and(|extension| extension == CACHE_FILE_EXTENSION)
+            })
+            .filter_map(|path| {
+                let metadata = std::fs::metadata(&path).ok()?;
+                Some((metadata.modified().ok()?, metadata.len(), path))
+            })
+            .collect();
+
+        let mut total_size: u64 = files.iter().map(|(_, size, _)| size).sum();
+        if total_size <= self.max_size {
+            return;
+        }
+
+        files.sort_by_key(|(modified, _, _)| *modified);
+        for (_, size, path) in files {
+            if total_size <= self.max_size {
+                break;
+            }
+            if std::fs::remove_file(&path).is_ok() {
+                total_size -= size;
+            }
+        }
+    }
+}
+
+/// Returns a key that identifies the specified sound file by its path, size and modification
+/// time, so that the key changes when the file changes (`None` if the file does not exist).
+pub fn get_sound_file_key(path: &str) -> Option<String> {
+    let metadata = std::fs::metadata(path).ok()?;
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .unwrap_or_default();
+
+    let mut hasher = Sha1::new();
+    hasher.update(path.as_bytes());
+    hasher.update(metadata.len().to_le_bytes());
+    hasher.update(modified.as_nanos().to_le_bytes());
+    Some(
+        hasher
+            .finalize()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect(),
+    )
+}
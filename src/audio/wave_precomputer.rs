@@ -0,0 +1,96 @@
+use super::audio_player::AudioPlayer;
+use super::sound_data::CurrentSoundData;
+use super::sound_decoder::SoundDecoder;
+use super::sound_wave::SoundWave;
+use super::wave_cache::WaveCache;
+use kira::sound::streaming::Decoder;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+/// How often to check the tracklist for new tracks when there's nothing to compute.
+const TRACKLIST_CHECK_INTERVAL_MS: u64 = 1000;
+
+/// Computes waves of tracks in the tracklist in the background and stores them in the cache
+/// so that they are shown instantly when the tracks are played.
+pub struct WavePrecomputer {
+    precompute_thread: Option<JoinHandle<()>>,
+    stop_signal: Arc<AtomicBool>,
+}
+
+impl Drop for WavePrecomputer {
+    fn drop(&mut self) {
+        // Stop thread.
+        self.stop_signal.store(true, Ordering::SeqCst);
+        self.precompute_thread.take().map(JoinHandle::join);
+    }
+}
+
+impl WavePrecomputer {
+    pub fn new(audio_player: Arc<Mutex<AudioPlayer>>, cache: WaveCache) -> Self {
+        let stop_signal = Arc::new(AtomicBool::new(false));
+        let stop_signal_clone = stop_signal.clone();
+
+        let precompute_thread = Some(std::thread::spawn(move || {
+            Self::precompute_thread(audio_player, cache, stop_signal_clone);
+        }));
+
+        Self {
+            precompute_thread,
+            stop_signal,
+        }
+    }
+
+    fn precompute_thread(
+        audio_player: Arc<Mutex<AudioPlayer>>,
+        cache: WaveCache,
+        stop_signal: Arc<AtomicBool>,
+    ) {
+        let mut tracklist_version = None;
+        let mut queue: Vec<String> = Vec::new();
+
+        while !stop_signal.load(Ordering::SeqCst) {
+            // Refill the queue when the tracklist changes.
+            {
+                let audio_player = audio_player.lock().unwrap();
+                if tracklist_version != Some(audio_player.get_tracklist_version()) {
+                    tracklist_version = Some(audio_player.get_tracklist_version());
+                    queue = audio_player
+                        .get_tracklist()
+                        .iter()
+                        .rev()
+                        .map(|track| track.path.clone())
+                        .collect();
+                }
+            }
+
+            let path = match queue.pop() {
+                Some(path) => path,
+                None => {
+                    std::thread::sleep(Duration::from_millis(TRACKLIST_CHECK_INTERVAL_MS));
+                    continue;
+                }
+            };
+            if cache.contains(&path) {
+                continue;
+            }
+
+            let num_frames = match SoundDecoder::new(&path) {
+                Ok(decoder) => decoder.num_frames(),
+                Err(_) => continue,
+            };
+
+            let wave = Arc::new(Mutex::new(SoundWave::new(num_frames)));
+            if CurrentSoundData::try_analyzing_sound(
+                &path,
+                Some(wave.clone()),
+                None,
+                None,
+                stop_signal.clone(),
+            ) {
+                cache.store(&path, &wave.lock().unwrap());
+            }
+        }
+    }
+}
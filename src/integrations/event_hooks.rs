@@ -0,0 +1,104 @@
+use crate::audio::audio_player::AudioPlayer;
+use crate::audio::player_event::{PlayerEvent, PlayingTrackInfo};
+use crate::misc::config_manger::HooksConfig;
+use crate::misc::message_box;
+use std::process::{Command, Stdio};
+
+/// Registers a listener that runs user commands from the settings when player events happen.
+///
+/// Information about the track is passed to commands in environment variables.
+pub fn register(audio_player: &mut AudioPlayer, config: &HooksConfig) {
+    let config = config.clone();
+
+    // Don't listen if nothing is configured.
+    let has_hooks = [
+        &config.on_track_start,
+        &config.on_track_end,
+        &config.on_pause,
+        &config.on_resume,
+        &config.on_stop,
+        &config.on_tracklist_end,
+    ]
+    .iter()
+    .any(|command| !command.trim().is_empty());
+    if !has_hooks {
+        return;
+    }
+
+    audio_player.add_event_listener(Box::new(move |event, info| {
+        let (name, command) = match event {
+            PlayerEvent::TrackStarted => ("track_start", &config.on_track_start),
+            PlayerEvent::TrackEnded => ("track_end", &config.on_track_end),
+            PlayerEvent::TracklistEnded => ("tracklist_end", &config.on_tracklist_end),
+            PlayerEvent::Paused => ("pause", &config.on_pause),
+            PlayerEvent::Resumed => ("resume", &config.on_resume),
+            PlayerEvent::Stopped => ("stop", &config.on_stop),
+        };
+
+        if !command.trim().is_empty() {
+            run_hook(name, command, info);
+        }
+    }));
+}
+
+/// Starts the command without waiting for it to finish.
+fn run_hook(event_name: &str, command: &str, info: &PlayingTrackInfo) {
+    let mut process = if cfg!(windows) {
+        let mut process = Command::new("cmd");
+        process.arg("/C").arg(command);
+        process
+    } else {
+        let mut process = Command::new("sh");
+        process.arg("-c").arg(command);
+        process
+    };
+
+    process
+        .stdin(Stdio::null())
+        .env("TAP_EVENT", event_name)
+        .env("TAP_TRACK_PATH", &info.path)
+        .env("TAP_TRACK_TITLE", &info.title)
+        .env(
+            "TAP_TRACK_ARTIST",
+            info.artist.as_deref().unwrap_or_default(),
+        )
+        .env("TAP_TRACK_ALBUM", info.album.as_deref().unwrap_or_default())
+        .env(
+            "TAP_TRACK_DURATION",
+            format!("{:.3}", info.duration.as_secs_f64()),
+        )
+        .env(
+            "TAP_TRACK_POSITION",
+            format!("{:.3}", info.position.as_secs_f64()),
+        )
+        .env(
+            "TAP_TRACK_INDEX",
+            info.index
+                .map(|index| index.to_string())
+                .unwrap_or_default(),
+        );
+
+    let mut child = match process.spawn() {
+        Ok(child) => child,
+        Err(msg) => {
+            message_box::log_error(&format!(
+                "failed to run \"{}\" hook, error: {}",
+                event_name, msg
+            ));
+            return;
+        }
+    };
+
+    // Wait in a separate thread to not block the player and to not leave zombie processes.
+    let event_name = event_name.to_string();
+    std::thread::spawn(move || match child.wait() {
+        Ok(status) if !status.success() => {
+            message_box::log_error(&format!("\"{}\" hook finished with {}", event_name, status))
+        }
+        Err(msg) => message_box::log_error(&format!(
+            "failed to wait for \"{}\" hook, error: {}",
+            event_name, msg
+        )),
+        _ => {}
+    });
+}
@@ -0,0 +1,2 @@
+pub mod event_hooks;
+pub mod now_playing;
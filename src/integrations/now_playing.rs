@@ -0,0 +1,297 @@
+use crate::audio::audio_player::AudioPlayer;
+use crate::audio::player_event::{PlayerEvent, PlayingTrackInfo};
+use crate::audio::track_metadata::TrackMetadata;
+use crate::misc::config_manger::{NowPlayingConfig, SettingsConfig};
+use crate::misc::message_box;
+use crate::misc::time_format::format_duration;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// Contents of the JSON file (only the state when nothing is playing).
+#[derive(Serialize)]
+struct NowPlayingJson<'a> {
+    state: &'static str,
+    #[serde(flatten)]
+    track: Option<NowPlayingTrackJson<'a>>,
+}
+
+#[derive(Serialize)]
+struct NowPlayingTrackJson<'a> {
+    title: &'a str,
+    artist: Option<&'a str>,
+    album: Option<&'a str>,
+    elapsed: f64,
+    duration: f64,
+    index: Option<usize>,
+    path: &'a str,
+    cover_art: Option<&'a str>,
+}
+
+/// How often the writer thread checks for the stop signal.
+const POLL_INTERVAL_MS: u64 = 100;
+
+/// Name (without extension) of the file that embedded cover art is extracted to.
+const COVER_ART_FILE_NAME: &str = "now_playing_cover";
+
+/// Image files in the track's directory that are used if there's no embedded cover art.
+const COVER_ART_DIRECTORY_FILE_NAMES: [&str; 6] = [
+    "cover.jpg",
+    "cover.png",
+    "folder.jpg",
+    "folder.png",
+    "front.jpg",
+    "front.png",
+];
+
+/// Writes information about the current track to text/JSON files when the track or
+/// playback state changes (for example to show it in stream overlays).
+pub struct NowPlayingWriter {
+    writer_thread: Option<JoinHandle<()>>,
+    stop_signal: Arc<AtomicBool>,
+}
+
+impl Drop for NowPlayingWriter {
+    fn drop(&mut self) {
+        // Stop thread.
+        self.stop_signal.store(true, Ordering::SeqCst);
+        self.writer_thread.take().map(JoinHandle::join);
+    }
+}
+
+/// State of the written files.
+struct NowPlayingFiles {
+    config: NowPlayingConfig,
+    show_milliseconds: bool,
+    track: Option<PlayingTrackInfo>,
+    cover_art_track_path: String,
+    cover_art_path: Option<String>,
+}
+
+impl NowPlayingWriter {
+    /// Returns `None` if disabled in the settings.
+    pub fn new(audio_player: Arc<Mutex<AudioPlayer>>, settings: &SettingsConfig) -> Option<Self> {
+        let config = &settings.now_playing;
+        if !config.enabled || (config.text_file_path.is_empty() && config.json_file_path.is_empty())
+        {
+            return None;
+        }
+
+        // Writing is done in a separate thread to not block the player.
+        let (sender, receiver) = mpsc::channel();
+        audio_player
+            .lock()
+            .unwrap()
+            .add_event_listener(Box::new(move |event, info| {
+                let _ = sender.send((event, info.clone()));
+            }));
+
+        let stop_signal = Arc::new(AtomicBool::new(false));
+        let stop_signal_clone = stop_signal.clone();
+        let files = NowPlayingFiles {
+            config: config.clone(),
+            show_milliseconds: settings.display.show_milliseconds,
+            track: None,
+            cover_art_track_path: String::new(),
+            cover_art_path: None,
+        };
+
+        let writer_thread = Some(std::thread::spawn(move || {
+            Self::writer_thread(files, receiver, audio_player, stop_signal_clone);
+        }));
+
+        Some(Self {
+            writer_thread,
+            stop_signal,
+        })
+    }
+
+    fn writer_thread(
+        mut files: NowPlayingFiles,
+        receiver: Receiver<(PlayerEvent, PlayingTrackInfo)>,
+        audio_player: Arc<Mutex<AudioPlayer>>,
+        stop_signal: Arc<AtomicBool>,
+    ) {
+        // Overwrite files from the previous run.
+        files.write();
+        let mut last_write_time = Instant::now();
+
+        while !stop_signal.load(Ordering::SeqCst) {
+            match receiver.recv_timeout(Duration::from_millis(POLL_INTERVAL_MS)) {
+                Ok((event, info)) => {
+                    files.track = match event {
+                        PlayerEvent::Stopped => None,
+                        _ => Some(info),
+                    };
+                }
+                Err(RecvTimeoutError::Timeout) => {
+                    // Periodically update elapsed time while playing.
+                    let is_playing = files.track.as_ref().is_some_and(|track| !track.is_paused);
+                    if files.config.update_interval_sec == 0
+                        || !is_playing
+                        || last_write_time.elapsed()
+                            < Duration::from_secs(files.config.update_interval_sec)
+                    {
+                        continue;
+                    }
+
+                    let info = audio_player.lock().unwrap().get_playing_track_info();
+                    files.track = info;
+                }
+                Err(RecvTimeoutError::Disconnected) => return,
+            }
+
+            files.write();
+            last_write_time = Instant::now();
+        }
+    }
+}
+
+impl NowPlayingFiles {
+    fn write(&mut self) {
+        self.update_cover_art();
+
+        if !self.config.text_file_path.is_empty() {
+            let text = self.format_text();
+            write_file_atomically(&self.config.text_file_path, text.as_bytes());
+        }
+
+        if !self.config.json_file_path.is_empty() {
+            let json = serde_json::to_string(&self.format_json()).unwrap_or_default();
+            write_file_atomically(&self.config.json_file_path, json.as_bytes());
+        }
+    }
+
+    fn format_text(&self) -> String {
+        let track = match self.track.as_ref() {
+            None => return String::new(),
+            Some(track) => track,
+        };
+
+        self.config
+            .text_template
+            .replace("{title}", &track.title)
+            .replace("{artist}", track.artist.as_deref().unwrap_or_default())
+            .replace("{album}", track.album.as_deref().unwrap_or_default())
+            .replace(
+                "{elapsed}",
+                &format_duration(track.position, self.show_milliseconds),
+            )
+            .replace(
+                "{duration}",
+                &format_duration(track.duration, self.show_milliseconds),
+            )
+            .replace("{state}", get_state_name(track))
+    }
+
+    fn format_json(&self) -> NowPlayingJson<'_> {
+        let track = match self.track.as_ref() {
+            None => {
+                return NowPlayingJson {
+                    state: "stopped",
+                    track: None,
+                }
+            }
+            Some(track) => track,
+        };
+
+        NowPlayingJson {
+            state: get_state_name(track),
+            track: Some(NowPlayingTrackJson {
+                title: &track.title,
+                artist: track.artist.as_deref(),
+                album: track.album.as_deref(),
+                elapsed: track.position.as_secs_f64(),
+                duration: track.duration.as_secs_f64(),
+                index: track.index,
+                path: &track.path,
+                cover_art: self.cover_art_path.as_deref(),
+            }),
+        }
+    }
+
+    /// Finds cover art of the current track (if the track was changed).
+    fn update_cover_art(&mut self) {
+        let track_path = match self.track.as_ref() {
+            None => return,
+            Some(track) => track.path.clone(),
+        };
+        if track_path == self.cover_art_track_path {
+            return;
+        }
+        self.cover_art_track_path = track_path.clone();
+        self.cover_art_path = None;
+
+        // Prefer embedded image.
+        if let Some(cover_art) = TrackMetadata::read_cover_art(&track_path) {
+            let extension = match cover_art.media_type.as_str() {
+                "image/png" => "png",
+                "image/gif" => "gif",
+                "image/bmp" => "bmp",
+                _ => "jpg",
+            };
+
+            // Put it next to the output files.
+            let output_path = if self.config.json_file_path.is_empty() {
+                &self.config.text_file_path
+            } else {
+                &self.config.json_file_path
+            };
+            let cover_art_path = Path::new(output_path)
+                .parent()
+                .unwrap_or(Path::new(""))
+                .join(format!("{}.{}", COVER_ART_FILE_NAME, extension));
+
+            if write_file_atomically(&cover_art_path.display().to_string(), &cover_art.data) {
+                self.cover_art_path = Some(absolute_path(cover_art_path));
+            }
+            return;
+        }
+
+        // Look for an image in the track's directory.
+        if let Some(directory) = Path::new(&track_path).parent() {
+            self.cover_art_path = COVER_ART_DIRECTORY_FILE_NAMES
+                .iter()
+                .map(|name| directory.join(name))
+                .find(|path| path.is_file())
+                .map(absolute_path);
+        }
+    }
+}
+
+/// Writes to a temporary file and then renames it so that readers never see partial content.
+/// Returns `false` if failed.
+fn write_file_atomically(path: &str, data: &[u8]) -> bool {
+    let temp_path = format!("{}.tmp", path);
+
+    let result = std::fs::write(&temp_path, data).and_then(|_| std::fs::rename(&temp_path, path));
+    if let Err(msg) = result {
+        message_box::log_error(&format!(
+            "failed to write now playing file {}, error: {}",
+            path, msg
+        ));
+        let _ = std::fs::remove_file(&temp_path);
+        return false;
+    }
+
+    true
+}
+
+fn absolute_path(path: PathBuf) -> String {
+    std::fs::canonicalize(&path)
+        .unwrap_or(path)
+        .display()
+        .to_string()
+}
+
+fn get_state_name(track: &PlayingTrackInfo) -> &'static str {
+    if track.is_paused {
+        "paused"
+    } else {
+        "playing"
+    }
+}
@@ -0,0 +1,510 @@
+use crate::{
+    app::application::{ApplicationMessage, Layout},
+    audio::{
+        audio_player::AudioPlayer,
+        equalizer::{BUILTIN_EQ_PRESETS, EQ_BAND_FREQUENCIES_HZ, EQ_GAIN_RANGE_DB},
+    },
+    misc::{
+        config_manger::{
+            CompressorConfig, EqPresetConfig, EqualizerConfig, SettingsStore, StereoConfig,
+        },
+        message_box,
+        settings::*,
+    },
+};
+use iced::{
+    alignment::{Horizontal, Vertical},
+    widget::{
+        text::Shaping, Button, Column, Container, Row, Scrollable, Slider, Text, TextInput,
+        VerticalSlider,
+    },
+    Background, Border, Color, Element, Length, Renderer, Shadow, Task, Theme,
+};
+use std::sync::{Arc, Mutex};
+
+// Layout customization.
+const BANDS_HEIGHT_PORTION: u16 = 5;
+const USER_PRESETS_HEIGHT_PORTION: u16 = 2;
+const WIDGET_BACKGROUND_DARK_ALPHA: f32 = 0.4;
+
+#[derive(Debug, Clone)]
+pub enum EqualizerLayoutMessage {
+    BandGainChanged(usize, f32),
+    /// The user stopped dragging a slider.
+    SliderReleased,
+    ToggleBypass,
+    ApplyBuiltinPreset(usize),
+    ApplyUserPreset(usize),
+    DeleteUserPreset(usize),
+    PresetNameChanged(String),
+    SaveUserPreset,
+    BalanceChanged(f32),
+    ToggleMono,
+    ToggleSwapChannels,
+    ToggleMuteLeft,
+    ToggleMuteRight,
+    ToggleNightMode,
+    CompressorThresholdChanged(f32),
+    CompressorRatioChanged(f32),
+    CompressorAttackChanged(f32),
+    CompressorReleaseChanged(f32),
+    CompressorMakeupGainChanged(f32),
+    Close,
+}
+
+pub struct EqualizerLayout {
+    audio_player: Arc<Mutex<AudioPlayer>>,
+    settings: Arc<Mutex<SettingsStore>>,
+    user_presets: Vec<EqPresetConfig>,
+    /// Name of the preset to save.
+    preset_name: String,
+}
+
+impl EqualizerLayout {
+    pub fn new(audio_player: Arc<Mutex<AudioPlayer>>, settings: Arc<Mutex<SettingsStore>>) -> Self {
+        let user_presets = settings
+            .lock()
+            .unwrap()
+            .get()
+            .equalizer
+            .user_presets
+            .clone();
+        Self {
+            audio_player,
+            settings,
+            user_presets,
+            preset_name: String::new(),
+        }
+    }
+
+    pub fn view(&self) -> Element<'_, EqualizerLayoutMessage, Theme, Renderer> {
+        let audio_player = self.audio_player.lock().unwrap();
+        let equalizer = audio_player.get_equalizer();
+
+        // Prepare top block.
+        let top_block = Row::new()
+            .push(
+                Text::new("Equalizer")
+                    .size(TEXT_SIZE)
+                    .align_y(Vertical::Center)
+                    .width(Length::Fill),
+            )
+            .spacing(HORIZONTAL_ELEMENT_SPACING / 4)
+            .push(
+                Button::new(
+                    Text::new(if equalizer.is_bypassed() {
+                        "Bypass: On"
+                    } else {
+                        "Bypass: Off"
+                    })
+                    .size(TEXT_SIZE),
+                )
+                .on_press(EqualizerLayoutMessage::ToggleBypass),
+            )
+            .spacing(HORIZONTAL_ELEMENT_SPACING / 4)
+            .push(
+                Button::new(Text::new("Back").size(TEXT_SIZE))
+                    .on_press(EqualizerLayoutMessage::Close),
+            );
+
+        // Prepare band sliders.
+        let mut bands_row = Row::new();
+        for (band, (&gain, &frequency)) in equalizer
+            .get_gains()
+            .iter()
+            .zip(EQ_BAND_FREQUENCIES_HZ.iter())
+            .enumerate()
+        {
+            bands_row = bands_row
+                .push(
+                    Column::new()
+                        .push(Text::new(format!("{:+.1}", gain)).size(TEXT_SIZE))
+                        .spacing(VERTICAL_ELEMENT_SPACING)
+                        .push(
+                            VerticalSlider::new(EQ_GAIN_RANGE_DB, gain, move |gain| {
+                                EqualizerLayoutMessage::BandGainChanged(band, gain)
+                            })
+                            .step(0.5)
+                            .on_release(EqualizerLayoutMessage::SliderReleased)
+                            .height(Length::Fill),
+                        )
+                        .push(Text::new(format_frequency(frequency)).size(TEXT_SIZE))
+                        .align_x(Horizontal::Center)
+                        .width(Length::Fill),
+                )
+                .spacing(VERTICAL_ELEMENT_SPACING);
+        }
+        let bands_block = Container::new(bands_row.padding(10))
+            .style(get_block_style)
+            .width(Length::Fill)
+            .height(Length::FillPortion(BANDS_HEIGHT_PORTION));
+
+        // Prepare built-in presets.
+        let mut builtin_presets_row = Row::new();
+        for (index, preset) in BUILTIN_EQ_PRESETS.iter().enumerate() {
+            builtin_presets_row = builtin_presets_row
+                .push(
+                    Button::new(
+                        Text::new(preset.name)
+                            .align_x(Horizontal::Center)
+                            .size(TEXT_SIZE),
+                    )
+                    .width(Length::Fill)
+                    .on_press(EqualizerLayoutMessage::ApplyBuiltinPreset(index)),
+                )
+                .spacing(HORIZONTAL_ELEMENT_SPACING / 4);
+        }
+
+        // Prepare channel options.
+        let stereo_config = audio_player.get_stereo_config();
+        let toggle_button = |label: &str, is_on: bool, message: EqualizerLayoutMessage| {
+            Button::new(
+                Text::new(format!("{}: {}", label, if is_on { "On" } else { "Off" }))
+                    .size(TEXT_SIZE),
+            )
+            .on_press(message)
+        };
+        let stereo_row = Row::new()
+            .push(
+                Text::new(format_balance(stereo_config.balance))
+                    .size(TEXT_SIZE)
+                    .align_y(Vertical::Center),
+            )
+            .spacing(HORIZONTAL_ELEMENT_SPACING / 4)
+            .push(
+                Slider::new(
+                    -1.0..=1.0,
+                    stereo_config.balance,
+                    EqualizerLayoutMessage::BalanceChanged,
+                )
+                .step(0.01)
+                .on_release(EqualizerLayoutMessage::SliderReleased)
+                .width(Length::Fill),
+            )
+            .spacing(HORIZONTAL_ELEMENT_SPACING / 4)
+            .push(toggle_button(
+                "Mono",
+                stereo_config.mono,
+                EqualizerLayoutMessage::ToggleMono,
+            ))
+            .push(toggle_button(
+                "Swap L/R",
+                stereo_config.swap_channels,
+                EqualizerLayoutMessage::ToggleSwapChannels,
+            ))
+            .push(toggle_button(
+                "Mute L",
+                stereo_config.mute_left,
+                EqualizerLayoutMessage::ToggleMuteLeft,
+            ))
+            .push(toggle_button(
+                "Mute R",
+                stereo_config.mute_right,
+                EqualizerLayoutMessage::ToggleMuteRight,
+            ))
+            .align_y(Vertical::Center);
+
+        // Prepare compressor options.
+        let compressor_config = audio_player.get_compressor_config();
+        let compressor_slider =
+            |label: String,
+             range: std::ops::RangeInclusive<f32>,
+             value: f32,
+             step: f32,
+             on_change: fn(f32) -> EqualizerLayoutMessage| {
+                Column::new()
+                    .push(Text::new(label).size(TEXT_SIZE))
+                    .push(
+                        Slider::new(range, value, on_change)
+                            .step(step)
+                            .on_release(EqualizerLayoutMessage::SliderReleased),
+                    )
+                    .width(Length::Fill)
+            };
+        let compressor_row = Row::new()
+            .push(toggle_button(
+                "Night Mode",
+                compressor_config.night_mode,
+                EqualizerLayoutMessage::ToggleNightMode,
+            ))
+            .spacing(HORIZONTAL_ELEMENT_SPACING / 2)
+            .push(compressor_slider(
+                format!("Threshold: {:.0} dB", compressor_config.threshold_db),
+                -60.0..=0.0,
+                compressor_config.threshold_db,
+                1.0,
+                EqualizerLayoutMessage::CompressorThresholdChanged,
+            ))
+            .push(compressor_slider(
+                format!("Ratio: {:.1}:1", compressor_config.ratio),
+                1.0..=20.0,
+                compressor_config.ratio,
+                0.1,
+                EqualizerLayoutMessage::CompressorRatioChanged,
+            ))
+            .push(compressor_slider(
+                format!("Attack: {:.0} ms", compressor_config.attack_ms),
+                1.0..=200.0,
+                compressor_config.attack_ms,
+                1.0,
+                EqualizerLayoutMessage::CompressorAttackChanged,
+            ))
+            .push(compressor_slider(
+                format!("Release: {:.0} ms", compressor_config.release_ms),
+                10.0..=2000.0,
+                compressor_config.release_ms,
+                10.0,
+                EqualizerLayoutMessage::CompressorReleaseChanged,
+            ))
+            .push(compressor_slider(
+                format!("Makeup: {:+.0} dB", compressor_config.makeup_gain_db),
+                0.0..=24.0,
+                compressor_config.makeup_gain_db,
+                1.0,
+                EqualizerLayoutMessage::CompressorMakeupGainChanged,
+            ))
+            .align_y(Vertical::Center);
+
+        // Prepare user presets.
+        let mut user_presets_column = Column::new();
+        for (index, preset) in self.user_presets.iter().enumerate() {
+            user_presets_column = user_presets_column
+                .push(
+                    Row::new()
+                        .push(
+                            Button::new(
+                                Text::new(preset.name.clone())
+                                    .shaping(Shaping::Advanced)
+                                    .size(TEXT_SIZE),
+                            )
+                            .width(Length::Fill)
+                            .on_press(EqualizerLayoutMessage::ApplyUserPreset(index)),
+                        )
+                        .spacing(HORIZONTAL_ELEMENT_SPACING / 4)
+                        .push(
+                            Button::new(Text::new("Delete").size(TEXT_SIZE))
+                                .on_press(EqualizerLayoutMessage::DeleteUserPreset(index)),
+                        ),
+                )
+                .spacing(VERTICAL_ELEMENT_SPACING);
+        }
+        let user_presets_block =
+            Container::new(Scrollable::new(user_presets_column.padding(10)).height(Length::Fill))
+                .style(get_block_style)
+                .width(Length::Fill)
+                .height(Length::FillPortion(USER_PRESETS_HEIGHT_PORTION));
+
+        let save_preset_block = Row::new()
+            .push(
+                TextInput::new("Preset name", &self.preset_name)
+                    .size(TEXT_SIZE)
+                    .on_input(EqualizerLayoutMessage::PresetNameChanged)
+                    .on_submit(EqualizerLayoutMessage::SaveUserPreset),
+            )
+            .spacing(HORIZONTAL_ELEMENT_SPACING / 4)
+            .push(
+                Button::new(Text::new("Save Preset").size(TEXT_SIZE))
+                    .on_press(EqualizerLayoutMessage::SaveUserPreset),
+            );
+
+        // Construct the final layout.
+        Column::new()
+            .push(top_block)
+            .push(bands_block)
+            .push(builtin_presets_row)
+            .push(stereo_row)
+            .push(compressor_row)
+            .push(user_presets_block)
+            .push(save_preset_block)
+            .spacing(VERTICAL_ELEMENT_SPACING)
+            .padding(10)
+            .into()
+    }
+
+    pub fn update(&mut self, message: EqualizerLayoutMessage) -> Task<ApplicationMessage> {
+        match message {
+            EqualizerLayoutMessage::BandGainChanged(band, gain) => {
+                let mut audio_player = self.audio_player.lock().unwrap();
+                audio_player.set_equalizer_band_gain(band, gain);
+            }
+            EqualizerLayoutMessage::SliderReleased => self.save_settings(),
+            EqualizerLayoutMessage::ToggleBypass => {
+                {
+                    let mut audio_player = self.audio_player.lock().unwrap();
+                    let bypass = !audio_player.get_equalizer().is_bypassed();
+                    audio_player.set_equalizer_bypass(bypass);
+                }
+                self.save_settings();
+            }
+            EqualizerLayoutMessage::ApplyBuiltinPreset(index) => {
+                if let Some(preset) = BUILTIN_EQ_PRESETS.get(index) {
+                    self.apply_gains(&preset.gains);
+                }
+            }
+            EqualizerLayoutMessage::ApplyUserPreset(index) => {
+                if let Some(preset) = self.user_presets.get(index) {
+                    self.apply_gains(&preset.gains_db.clone());
+                }
+            }
+            EqualizerLayoutMessage::DeleteUserPreset(index) => {
+                if index < self.user_presets.len() {
+                    self.user_presets.remove(index);
+                    self.save_settings();
+                }
+            }
+            EqualizerLayoutMessage::PresetNameChanged(name) => self.preset_name = name,
+            EqualizerLayoutMessage::SaveUserPreset => {
+                let name = self.preset_name.trim().to_string();
+
+                // Make sure the name is specified.
+                if name.is_empty() {
+                    message_box::show_info("Info", "Enter a name of the preset to save it.");
+                    return Task::none();
+                }
+
+                let gains_db = self
+                    .audio_player
+                    .lock()
+                    .unwrap()
+                    .get_equalizer()
+                    .get_gains()
+                    .to_vec();
+
+                // Replace a preset with the same name.
+                match self
+                    .user_presets
+                    .iter_mut()
+                    .find(|preset| preset.name == name)
+                {
+                    Some(preset) => preset.gains_db = gains_db,
+                    None => self.user_presets.push(EqPresetConfig { name, gains_db }),
+                }
+                self.preset_name.clear();
+                self.save_settings();
+            }
+            EqualizerLayoutMessage::BalanceChanged(balance) => {
+                self.change_stereo_config(|config| config.balance = balance);
+            }
+            EqualizerLayoutMessage::ToggleMono => {
+                self.change_stereo_config(|config| config.mono = !config.mono);
+                self.save_settings();
+            }
+            EqualizerLayoutMessage::ToggleSwapChannels => {
+                self.change_stereo_config(|config| config.swap_channels = !config.swap_channels);
+                self.save_settings();
+            }
+            EqualizerLayoutMessage::ToggleMuteLeft => {
+                self.change_stereo_config(|config| config.mute_left = !config.mute_left);
+                self.save_settings();
+            }
+            EqualizerLayoutMessage::ToggleMuteRight => {
+                self.change_stereo_config(|config| config.mute_right = !config.mute_right);
+                self.save_settings();
+            }
+            EqualizerLayoutMessage::ToggleNightMode => {
+                self.change_compressor_config(|config| config.night_mode = !config.night_mode);
+                self.save_settings();
+            }
+            EqualizerLayoutMessage::CompressorThresholdChanged(threshold_db) => {
+                self.change_compressor_config(|config| config.threshold_db = threshold_db);
+            }
+            EqualizerLayoutMessage::CompressorRatioChanged(ratio) => {
+                self.change_compressor_config(|config| config.ratio = ratio);
+            }
+            EqualizerLayoutMessage::CompressorAttackChanged(attack_ms) => {
+                self.change_compressor_config(|config| config.attack_ms = attack_ms);
+            }
+            EqualizerLayoutMessage::CompressorReleaseChanged(release_ms) => {
+                self.change_compressor_config(|config| config.release_ms = release_ms);
+            }
+            EqualizerLayoutMessage::CompressorMakeupGainChanged(makeup_gain_db) => {
+                self.change_compressor_config(|config| config.makeup_gain_db = makeup_gain_db);
+            }
+            EqualizerLayoutMessage::Close => {
+                return Task::done(ApplicationMessage::SwitchLayout(Layout::Main));
+            }
+        }
+
+        Task::none()
+    }
+
+    fn apply_gains(&mut self, gains: &[f32]) {
+        self.audio_player.lock().unwrap().set_equalizer_gains(gains);
+        self.save_settings();
+    }
+
+    fn change_stereo_config(&mut self, change: impl FnOnce(&mut StereoConfig)) {
+        let mut audio_player = self.audio_player.lock().unwrap();
+        let mut config = audio_player.get_stereo_config().clone();
+        change(&mut config);
+        audio_player.set_stereo_config(config);
+    }
+
+    fn change_compressor_config(&mut self, change: impl FnOnce(&mut CompressorConfig)) {
+        let mut audio_player = self.audio_player.lock().unwrap();
+        let mut config = audio_player.get_compressor_config().clone();
+        change(&mut config);
+        audio_player.set_compressor_config(config);
+    }
+
+    /// Saves the equalizer (with user presets), channel and compressor options to the settings.
+    fn save_settings(&self) {
+        // The file is written after the player is unlocked.
+        let (equalizer, stereo, compressor) = {
+            let audio_player = self.audio_player.lock().unwrap();
+            let equalizer = audio_player.get_equalizer();
+            (
+                EqualizerConfig {
+                    bypass: equalizer.is_bypassed(),
+                    gains_db: equalizer.get_gains().to_vec(),
+                    user_presets: self.user_presets.clone(),
+                },
+                audio_player.get_stereo_config().clone(),
+                audio_player.get_compressor_config().clone(),
+            )
+        };
+
+        self.settings.lock().unwrap().change(|settings| {
+            settings.equalizer = equalizer;
+            settings.stereo = stereo;
+            settings.compressor = compressor;
+        });
+    }
+}
+
+/// Formats balance like "Balance: L 20%".
+fn format_balance(balance: f32) -> String {
+    let percent = (balance.abs() * 100.0).round();
+    if percent == 0.0 {
+        "Balance: Center".to_string()
+    } else if balance < 0.0 {
+        format!("Balance: L {:.0}%", percent)
+    } else {
+        format!("Balance: R {:.0}%", percent)
+    }
+}
+
+/// Formats a frequency like "125" or "2k".
+fn format_frequency(frequency: f64) -> String {
+    if frequency >= 1000.0 {
+        format!("{}k", frequency / 1000.0)
+    } else {
+        format!("{}", frequency)
+    }
+}
+
+fn get_block_style(_theme: &Theme) -> iced::widget::container::Style {
+    iced::widget::container::Style {
+        text_color: None,
+        background: Some(Background::Color(Color {
+            a: WIDGET_BACKGROUND_DARK_ALPHA,
+            ..Color::BLACK
+        })),
+        border: Border {
+            color: crate::theme::style::get_primary_color(),
+            width: 1.0,
+            radius: BORDER_RADIUS.into(),
+        },
+        shadow: Shadow::default(),
+    }
+}
@@ -1,39 +1,75 @@
 use crate::{
-    app::application::ApplicationMessage,
-    audio::audio_player::AudioPlayer,
+    app::application::{ApplicationMessage, Layout},
+    audio::{
+        ab_loop::LoopMarker,
+        audio_player::{AudioPlayer, MAX_VOLUME, PITCH_CENTS_RANGE},
+        sleep_timer::SleepTarget,
+    },
     misc::{
-        config_manger::{ConfigManager, TracklistConfig, TRACKLIST_EXTENSION},
+        config_manger::{
+            ConfigManager, DisplayConfig, SettingsStore, TimeStretchMode, WaveformMode,
+            TRACKLIST_EXTENSION,
+        },
+        message_box,
         settings::*,
+        time_format::format_duration,
     },
-    widgets::track_pos_slider::TrackPosSlider,
+    widgets::{output_visualizer::OutputVisualizer, track_pos_slider::TrackPosSlider},
 };
 use iced::{
     alignment::{Horizontal, Vertical},
-    widget::{text::Shaping, Button, Column, Container, MouseArea, Row, Scrollable, Slider, Text},
+    widget::{
+        text::Shaping, Button, Column, Container, MouseArea, Row, Scrollable, Slider, Text,
+        TextInput,
+    },
     Background, Border, Color, Element, Length, Renderer, Shadow, Theme,
 };
 use iced::{widget::svg, Task};
-use native_dialog::{FileDialog, MessageDialog, MessageType};
+use native_dialog::FileDialog;
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 const PLAY_PAUSE_SVG: &[u8] = include_bytes!("../../res/play-pause.svg");
 
 // Layout customization.
 const TITLE_BLOCK_PORTION: u16 = 7;
 const PLAYBACK_RATE_BLOCK_PORTION: u16 = 4;
+const PITCH_BLOCK_PORTION: u16 = 4;
 const VOLUME_BLOCK_PORTION: u16 = 4;
 const TRACK_POS_HEIGHT_PORTION: u16 = 2;
+const VISUALIZER_WIDTH: f32 = 220.0;
 const TRACKLIST_HEIGHT_PORTION: u16 = 7;
 const WIDGET_BACKGROUND_DARK_ALPHA: f32 = 0.4;
+const SLEEP_TIME_INPUT_WIDTH: f32 = 60.0;
+
+/// Durations of the sleep timer that the "Sleep" button cycles through (in minutes).
+const SLEEP_TIMER_PRESETS_MIN: [u64; 5] = [15, 30, 45, 60, 90];
 
 #[derive(Debug, Clone)]
 pub enum MainLayoutMessage {
     VolumeChanged(f64),
     PlaybackRateChanged(f64),
+    /// New pitch shift of the current track in cents.
+    PitchChanged(f64),
+    /// Turns vocal reduction of the current track on or off.
+    ToggleVocalReduction,
+    ToggleTimeStretchMode,
+    OpenEqualizer,
     PlayTrackFromStart(usize),
     DeleteTrack(usize),
     ChangeTrackPos(f32),
+    ToggleWaveformMode,
+    /// Place a loop marker at the portion of the track.
+    SetLoopMarker(LoopMarker, f32),
+    /// Place a loop marker at the current position.
+    SetLoopMarkerAtPlayhead(LoopMarker),
+    ClearLoop,
+    /// Switch the sleep timer to the next preset (off, minutes, end of track or tracklist).
+    CycleSleepTimer,
+    SleepTimeChanged(String),
+    /// Start the sleep timer at the entered clock time.
+    SubmitSleepTime,
     MoveTrackUp(usize),
     MoveTrackDown(usize),
     PlayPauseCurrentTrack,
@@ -44,12 +80,24 @@ pub enum MainLayoutMessage {
 
 pub struct MainLayout {
     audio_player: Arc<Mutex<AudioPlayer>>,
+    settings: Arc<Mutex<SettingsStore>>,
+    display_config: DisplayConfig,
+    /// Clock time entered for the sleep timer.
+    sleep_time: String,
 }
 
 impl MainLayout {
-    pub fn new() -> Self {
+    pub fn new(audio_player: Arc<Mutex<AudioPlayer>>, settings: Arc<Mutex<SettingsStore>>) -> Self {
+        let display_config = settings.lock().unwrap().get().display.clone();
+        audio_player
+            .lock()
+            .unwrap()
+            .set_spectrogram_shown(display_config.waveform_mode == WaveformMode::Spectrogram);
         Self {
-            audio_player: AudioPlayer::new(),
+            audio_player,
+            settings,
+            display_config,
+            sleep_time: String::new(),
         }
     }
 
@@ -73,11 +121,26 @@ impl MainLayout {
                     .spacing(VERTICAL_ELEMENT_SPACING)
                     .push(
                         Text::new(format!(
-                            "Time: {}:{} / {}:{}",
-                            audio_player.get_current_sound_position() as usize / 60,
-                            audio_player.get_current_sound_position() as usize % 60,
-                            audio_player.get_current_sound_duration() as usize / 60,
-                            audio_player.get_current_sound_duration() as usize % 60
+                            "Time: {} / {}{}{}",
+                            format_duration(
+                                audio_player.get_current_sound_position(),
+                                self.display_config.show_milliseconds
+                            ),
+                            format_duration(
+                                audio_player.get_current_sound_duration(),
+                                self.display_config.show_milliseconds
+                            ),
+                            match audio_player
+                                .get_ab_loop()
+                                .get_status_text(self.display_config.show_milliseconds)
+                            {
+                                Some(text) => format!("    {}", text),
+                                None => String::new(),
+                            },
+                            match audio_player.get_sleep_timer_status_text() {
+                                Some(text) => format!("    {}", text),
+                                None => String::new(),
+                            }
                         ))
                         .size(TEXT_SIZE),
                     )
@@ -87,17 +150,32 @@ impl MainLayout {
             .push(
                 Column::new()
                     .push(
-                        Text::new(format!(
-                            "Playback Rate: x{:.2}",
-                            audio_player.get_playback_rate()
-                        ))
-                        .size(TEXT_SIZE)
-                        .align_y(Vertical::Center),
+                        Row::new()
+                            .push(
+                                Text::new(format!(
+                                    "Playback Rate: x{:.2}",
+                                    audio_player.get_playback_rate()
+                                ))
+                                .size(TEXT_SIZE)
+                                .align_y(Vertical::Center)
+                                .width(Length::Fill),
+                            )
+                            .push(
+                                Button::new(
+                                    Text::new(match audio_player.get_time_stretch_mode() {
+                                        TimeStretchMode::Tape => "Tape",
+                                        TimeStretchMode::Stretch => "Keep Pitch",
+                                    })
+                                    .size(TEXT_SIZE),
+                                )
+                                .padding([0, 5])
+                                .on_press(MainLayoutMessage::ToggleTimeStretchMode),
+                            ),
                     )
                     .spacing(VERTICAL_ELEMENT_SPACING)
                     .push(
                         Slider::new(
-                            0.6..=1.4,
+                            audio_player.get_playback_rate_range(),
                             audio_player.get_playback_rate(),
                             MainLayoutMessage::PlaybackRateChanged,
                         )
@@ -106,6 +184,52 @@ impl MainLayout {
                     .width(Length::FillPortion(PLAYBACK_RATE_BLOCK_PORTION)),
             )
             .spacing(HORIZONTAL_ELEMENT_SPACING)
+            .push(
+                Column::new()
+                    .push(
+                        Row::new()
+                            .push(
+                                Text::new(format!(
+                                    "Pitch: {:+.2} st",
+                                    audio_player.get_current_pitch_cents() as f64 / 100.0
+                                ))
+                                .size(TEXT_SIZE)
+                                .align_y(Vertical::Center)
+                                .width(Length::Fill),
+                            )
+                            .push(
+                                Button::new(Text::new("Reset").size(TEXT_SIZE))
+                                    .padding([0, 5])
+                                    .on_press(MainLayoutMessage::PitchChanged(0.0)),
+                            )
+                            .spacing(HORIZONTAL_ELEMENT_SPACING)
+                            .push(
+                                Button::new(
+                                    Text::new(
+                                        if audio_player.is_current_vocal_reduction_enabled() {
+                                            "Karaoke: On"
+                                        } else {
+                                            "Karaoke: Off"
+                                        },
+                                    )
+                                    .size(TEXT_SIZE),
+                                )
+                                .padding([0, 5])
+                                .on_press(MainLayoutMessage::ToggleVocalReduction),
+                            ),
+                    )
+                    .spacing(VERTICAL_ELEMENT_SPACING)
+                    .push(
+                        Slider::new(
+                            *PITCH_CENTS_RANGE.start() as f64..=*PITCH_CENTS_RANGE.end() as f64,
+                            audio_player.get_current_pitch_cents() as f64,
+                            MainLayoutMessage::PitchChanged,
+                        )
+                        .step(1.0),
+                    )
+                    .width(Length::FillPortion(PITCH_BLOCK_PORTION)),
+            )
+            .spacing(HORIZONTAL_ELEMENT_SPACING)
             .push(
                 Column::new()
                     .push(
@@ -116,7 +240,7 @@ impl MainLayout {
                     .spacing(VERTICAL_ELEMENT_SPACING)
                     .push(
                         Slider::new(
-                            0.0..=1.25,
+                            0.0..=MAX_VOLUME,
                             audio_player.get_volume(),
                             MainLayoutMessage::VolumeChanged,
                         )
@@ -126,9 +250,15 @@ impl MainLayout {
             );
 
         // Prepare track position block.
-        let track_pos_block = Container::new(
-            TrackPosSlider::new(self.audio_player.clone())
-                .on_clicked(MainLayoutMessage::ChangeTrackPos),
+        let track_pos_slider = Container::new(
+            MouseArea::new(
+                TrackPosSlider::new(self.audio_player.clone())
+                    .waveform_mode(self.display_config.waveform_mode)
+                    .show_milliseconds(self.display_config.show_milliseconds)
+                    .on_seek(MainLayoutMessage::ChangeTrackPos)
+                    .on_loop_marker(MainLayoutMessage::SetLoopMarker),
+            )
+            .on_right_press(MainLayoutMessage::ToggleWaveformMode),
         )
         .padding(1)
         .style(|_| iced::widget::container::Style {
@@ -145,7 +275,34 @@ impl MainLayout {
             shadow: Shadow::default(),
         })
         .width(Length::Fill)
-        .height(Length::FillPortion(TRACK_POS_HEIGHT_PORTION));
+        .height(Length::Fill);
+
+        let mut track_pos_block = Row::new().push(track_pos_slider);
+        if self.display_config.show_visualizer {
+            track_pos_block = track_pos_block.spacing(VERTICAL_ELEMENT_SPACING).push(
+                Container::new(
+                    OutputVisualizer::new(audio_player.get_output_analyzer())
+                        .limiter(audio_player.get_limiter()),
+                )
+                .padding(4)
+                .style(|_| iced::widget::container::Style {
+                    text_color: None,
+                    background: Some(Background::Color(Color {
+                        a: WIDGET_BACKGROUND_DARK_ALPHA,
+                        ..Color::BLACK
+                    })),
+                    border: Border {
+                        color: crate::theme::style::get_primary_color(),
+                        width: 1.0,
+                        radius: BORDER_RADIUS.into(),
+                    },
+                    shadow: Shadow::default(),
+                })
+                .width(Length::Fixed(VISUALIZER_WIDTH))
+                .height(Length::Fill),
+            );
+        }
+        let track_pos_block = track_pos_block.height(Length::FillPortion(TRACK_POS_HEIGHT_PORTION));
 
         // Prepare block above tracklist.
         let above_tracklist_block = Column::new()
@@ -172,6 +329,38 @@ impl MainLayout {
                         .on_press(MainLayoutMessage::PlayPauseCurrentTrack),
                     )
                     .spacing(HORIZONTAL_ELEMENT_SPACING / 4)
+                    .push(Button::new(Text::new("A").size(TEXT_SIZE)).on_press(
+                        MainLayoutMessage::SetLoopMarkerAtPlayhead(LoopMarker::Start),
+                    ))
+                    .spacing(HORIZONTAL_ELEMENT_SPACING / 4)
+                    .push(
+                        Button::new(Text::new("B").size(TEXT_SIZE))
+                            .on_press(MainLayoutMessage::SetLoopMarkerAtPlayhead(LoopMarker::End)),
+                    )
+                    .spacing(HORIZONTAL_ELEMENT_SPACING / 4)
+                    .push(
+                        Button::new(Text::new("Clear A-B").size(TEXT_SIZE))
+                            .on_press(MainLayoutMessage::ClearLoop),
+                    )
+                    .spacing(HORIZONTAL_ELEMENT_SPACING / 4)
+                    .push(
+                        Button::new(Text::new("EQ").size(TEXT_SIZE))
+                            .on_press(MainLayoutMessage::OpenEqualizer),
+                    )
+                    .spacing(HORIZONTAL_ELEMENT_SPACING / 4)
+                    .push(
+                        Button::new(Text::new("Sleep").size(TEXT_SIZE))
+                            .on_press(MainLayoutMessage::CycleSleepTimer),
+                    )
+                    .spacing(HORIZONTAL_ELEMENT_SPACING / 4)
+                    .push(
+                        TextInput::new("hh:mm", &self.sleep_time)
+                            .size(TEXT_SIZE)
+                            .width(Length::Fixed(SLEEP_TIME_INPUT_WIDTH))
+                            .on_input(MainLayoutMessage::SleepTimeChanged)
+                            .on_submit(MainLayoutMessage::SubmitSleepTime),
+                    )
+                    .spacing(HORIZONTAL_ELEMENT_SPACING / 4)
                     .push(
                         Button::new(
                             Text::new("Open Tracklist")
@@ -255,18 +444,54 @@ impl MainLayout {
                 let mut audio_player = self.audio_player.lock().unwrap();
                 audio_player.set_playback_rate(new_rate)
             }
-            MainLayoutMessage::PlayTrackFromStart(track_index) => {
+            MainLayoutMessage::PitchChanged(cents) => {
                 let mut audio_player = self.audio_player.lock().unwrap();
-                audio_player.play_track(track_index);
+                if let Some(index) = audio_player.get_current_track_index() {
+                    let id = audio_player.get_tracklist()[index].id;
+                    audio_player.set_track_pitch(id, cents.round() as i32);
+                }
             }
-            MainLayoutMessage::PlayPauseCurrentTrack => {
+            MainLayoutMessage::ToggleVocalReduction => {
                 let mut audio_player = self.audio_player.lock().unwrap();
-                if audio_player.get_current_track_index().is_some() {
-                    audio_player.pause_resume();
-                } else {
-                    audio_player.play_track(0);
+                if let Some(index) = audio_player.get_current_track_index() {
+                    let id = audio_player.get_tracklist()[index].id;
+                    let enabled = audio_player.is_current_vocal_reduction_enabled();
+                    audio_player.set_track_vocal_reduction(id, !enabled);
                 }
             }
+            MainLayoutMessage::ToggleTimeStretchMode => {
+                let mode = {
+                    let mut audio_player = self.audio_player.lock().unwrap();
+                    let mode = match audio_player.get_time_stretch_mode() {
+                        TimeStretchMode::Tape => TimeStretchMode::Stretch,
+                        TimeStretchMode::Stretch => TimeStretchMode::Tape,
+                    };
+                    audio_player.set_time_stretch_mode(mode);
+                    mode
+                };
+
+                // Remember the choice.
+                self.settings
+                    .lock()
+                    .unwrap()
+                    .change(|settings| settings.playback.time_stretch_mode = mode);
+            }
+            MainLayoutMessage::PlayTrackFromStart(track_index) => {
+                let result = self.audio_player.lock().unwrap().play_track(track_index);
+                show_play_error(result);
+            }
+            MainLayoutMessage::PlayPauseCurrentTrack => {
+                let result = {
+                    let mut audio_player = self.audio_player.lock().unwrap();
+                    if audio_player.get_current_track_index().is_some() {
+                        audio_player.pause_resume();
+                        Ok(())
+                    } else {
+                        audio_player.play_track(0)
+                    }
+                };
+                show_play_error(result);
+            }
             MainLayoutMessage::DeleteTrack(track_index) => {
                 let mut audio_player = self.audio_player.lock().unwrap();
                 audio_player.remove_track(track_index);
@@ -274,9 +499,90 @@ impl MainLayout {
             MainLayoutMessage::ChangeTrackPos(portion) => {
                 let mut audio_player = self.audio_player.lock().unwrap();
 
-                let position = portion as f64 * audio_player.get_current_sound_duration();
+                let position = audio_player
+                    .get_current_sound_duration()
+                    .mul_f64(portion.clamp(0.0, 1.0) as f64);
                 audio_player.set_current_sound_pos(position);
             }
+            MainLayoutMessage::ToggleWaveformMode => {
+                self.display_config.waveform_mode = match self.display_config.waveform_mode {
+                    WaveformMode::Combined => WaveformMode::Channels,
+                    WaveformMode::Channels => WaveformMode::Spectrogram,
+                    WaveformMode::Spectrogram => WaveformMode::Combined,
+                };
+                self.audio_player.lock().unwrap().set_spectrogram_shown(
+                    self.display_config.waveform_mode == WaveformMode::Spectrogram,
+                );
+
+                // Remember the choice.
+                let display_config = self.display_config.clone();
+                self.settings
+                    .lock()
+                    .unwrap()
+                    .change(|settings| settings.display = display_config);
+            }
+            MainLayoutMessage::SetLoopMarker(marker, portion) => {
+                let mut audio_player = self.audio_player.lock().unwrap();
+
+                let position = audio_player
+                    .get_current_sound_duration()
+                    .mul_f64(portion.clamp(0.0, 1.0) as f64);
+                audio_player.set_ab_loop_marker(marker, position);
+            }
+            MainLayoutMessage::SetLoopMarkerAtPlayhead(marker) => {
+                let mut audio_player = self.audio_player.lock().unwrap();
+
+                let position = audio_player.get_current_sound_position();
+                audio_player.set_ab_loop_marker(marker, position);
+            }
+            MainLayoutMessage::OpenEqualizer => {
+                return Task::done(ApplicationMessage::SwitchLayout(Layout::Equalizer));
+            }
+            MainLayoutMessage::ClearLoop => {
+                let mut audio_player = self.audio_player.lock().unwrap();
+                audio_player.clear_ab_loop();
+            }
+            MainLayoutMessage::CycleSleepTimer => {
+                let mut audio_player = self.audio_player.lock().unwrap();
+                match audio_player.get_sleep_timer_target() {
+                    None | Some(SleepTarget::Deadline(_)) => {
+                        // Go to the first preset that is longer than the time left.
+                        let remaining = audio_player.get_sleep_timer_remaining();
+                        let next_duration = SLEEP_TIMER_PRESETS_MIN
+                            .iter()
+                            .map(|minutes| Duration::from_secs(minutes * 60))
+                            .find(|duration| {
+                                remaining.is_none_or(|remaining| {
+                                    *duration > remaining + Duration::from_secs(60)
+                                })
+                            });
+                        match next_duration {
+                            Some(duration) => audio_player.set_sleep_timer_duration(duration),
+                            None => audio_player.set_sleep_timer(SleepTarget::EndOfTrack),
+                        }
+                    }
+                    Some(SleepTarget::EndOfTrack) => {
+                        audio_player.set_sleep_timer(SleepTarget::EndOfTracklist)
+                    }
+                    Some(SleepTarget::EndOfTracklist) => audio_player.cancel_sleep_timer(),
+                }
+            }
+            MainLayoutMessage::SleepTimeChanged(text) => {
+                self.sleep_time = text;
+            }
+            MainLayoutMessage::SubmitSleepTime => {
+                match chrono::NaiveTime::parse_from_str(self.sleep_time.trim(), "%H:%M") {
+                    Ok(time) => {
+                        let mut audio_player = self.audio_player.lock().unwrap();
+                        audio_player.set_sleep_timer_clock_time(time);
+                        self.sleep_time.clear();
+                    }
+                    Err(_) => message_box::show_info(
+                        "Info",
+                        "Enter the time like 23:30 to start the sleep timer.",
+                    ),
+                }
+            }
             MainLayoutMessage::MoveTrackUp(track_index) => {
                 let mut audio_player = self.audio_player.lock().unwrap();
                 audio_player.move_track_up(track_index);
@@ -302,9 +608,10 @@ impl MainLayout {
 
                     self.clear_tracklist();
 
-                    // Import paths.
-                    for path in config.paths {
-                        self.try_importing_track_from_path(PathBuf::from(path.as_str()).as_path())
+                    // Import tracks.
+                    let mut audio_player = self.audio_player.lock().unwrap();
+                    for track in config.get_tracks() {
+                        audio_player.add_track_from_config(&track);
                     }
                 }
             }
@@ -313,12 +620,10 @@ impl MainLayout {
 
                 // Make sure the tracklist is not empty.
                 if audio_player.get_tracklist().is_empty() {
-                    MessageDialog::new()
-                        .set_type(MessageType::Info)
-                        .set_title("Info")
-                        .set_text("Tracklist is empty - there is nothing to save!")
-                        .show_alert()
-                        .unwrap();
+                    message_box::show_info(
+                        "Info",
+                        "Tracklist is empty - there is nothing to save!",
+                    );
                     return Task::none();
                 }
 
@@ -328,12 +633,10 @@ impl MainLayout {
                     .show_save_single_file()
                     .unwrap();
                 if let Some(path) = path {
-                    let mut config = TracklistConfig::new();
-                    config.paths = Vec::with_capacity(audio_player.get_tracklist().len());
-                    for track_info in audio_player.get_tracklist() {
-                        config.paths.push(track_info.path.clone());
-                    }
-                    ConfigManager::save_tracklist(&path.as_path().display().to_string(), config);
+                    ConfigManager::save_tracklist(
+                        &path.as_path().display().to_string(),
+                        audio_player.get_tracklist_config(),
+                    );
                 }
             }
         }
@@ -351,3 +654,11 @@ impl MainLayout {
         audio_player.add_track(path);
     }
 }
+
+/// Shows the error of a track that failed to play (called after the player is unlocked
+/// so that the dialog doesn't block other users of the player).
+fn show_play_error(result: Result<(), String>) {
+    if let Err(msg) = result {
+        message_box::show_error("Error", &msg);
+    }
+}
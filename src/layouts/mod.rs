@@ -1 +1,2 @@
+pub mod equalizer_layout;
 pub mod main_layout;
@@ -2,15 +2,38 @@
 
 use app::application::ApplicationState;
 use iced::Font;
+use misc::command_line;
 
 mod app;
 mod audio;
+mod integrations;
 mod layouts;
 mod misc;
+mod remote;
 mod theme;
+#[cfg(unix)]
+mod tui;
 mod widgets;
 
 fn main() -> iced::Result {
+    if command_line::has_flag(command_line::HEADLESS_FLAG) {
+        app::headless::run();
+        return Ok(());
+    }
+
+    if command_line::has_flag(command_line::TUI_FLAG) {
+        #[cfg(unix)]
+        tui::tui_application::TuiApplication::run();
+
+        #[cfg(not(unix))]
+        misc::message_box::show_error(
+            "Error",
+            "terminal UI is only supported on Unix-like systems",
+        );
+
+        return Ok(());
+    }
+
     iced::application(
         ApplicationState::title,
         ApplicationState::update,
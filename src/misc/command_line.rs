@@ -0,0 +1,18 @@
+/// Runs the player without a window (controlled through remote control servers).
+pub const HEADLESS_FLAG: &str = "--headless";
+
+/// Runs the player in the terminal instead of a window.
+pub const TUI_FLAG: &str = "--tui";
+
+/// Tells if the specified flag was passed on the command line.
+pub fn has_flag(flag: &str) -> bool {
+    std::env::args().skip(1).any(|arg| arg == flag)
+}
+
+/// Returns command line arguments that are not flags (paths to audio files).
+pub fn get_paths() -> Vec<String> {
+    std::env::args()
+        .skip(1)
+        .filter(|arg| !arg.starts_with("--"))
+        .collect()
+}
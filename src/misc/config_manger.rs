@@ -1,18 +1,461 @@
-use native_dialog::{MessageDialog, MessageType};
-use serde::{Deserialize, Serialize};
+use super::message_box;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use std::fs::File;
 use std::io::Write;
+use std::path::PathBuf;
 
 pub const TRACKLIST_EXTENSION: &str = "tapt";
 
+const CONFIG_DIRECTORY_NAME: &str = "tiny-audio-player";
+const SETTINGS_FILE_NAME: &str = "settings.toml";
+const SESSION_FILE_NAME: &str = "session.tapt";
+
 #[derive(Serialize, Deserialize, Default)]
 pub struct TracklistConfig {
+    /// Paths of tracks (kept so that older versions can read the tracklist).
     pub paths: Vec<String>,
+    #[serde(default)]
+    pub tracks: Vec<TrackConfig>,
+}
+
+/// Track of a saved tracklist with its per-track settings.
+#[derive(Serialize, Deserialize, Default, Clone)]
+#[serde(default)]
+pub struct TrackConfig {
+    pub path: String,
+    /// Pitch shift in cents (100 cents is one semitone).
+    pub pitch_cents: i32,
+    /// Reduce vocals when the track is playing.
+    pub vocal_reduction: bool,
 }
 
 impl TracklistConfig {
     pub fn new() -> Self {
-        Self { paths: Vec::new() }
+        Self {
+            paths: Vec::new(),
+            tracks: Vec::new(),
+        }
+    }
+
+    /// Returns tracks of the tracklist (tracklists saved by older versions only have paths).
+    pub fn get_tracks(&self) -> Vec<TrackConfig> {
+        if !self.tracks.is_empty() {
+            return self.tracks.clone();
+        }
+
+        self.paths
+            .iter()
+            .map(|path| TrackConfig {
+                path: path.clone(),
+                ..Default::default()
+            })
+            .collect()
+    }
+}
+
+/// Application settings stored in the user's config directory.
+#[derive(Serialize, Deserialize, Default, Clone)]
+#[serde(default)]
+pub struct SettingsConfig {
+    pub mpd_server: MpdServerConfig,
+    pub http_server: HttpServerConfig,
+    pub hooks: HooksConfig,
+    pub now_playing: NowPlayingConfig,
+    pub display: DisplayConfig,
+    pub waveform_cache: WaveformCacheConfig,
+    pub spectrogram: SpectrogramConfig,
+    pub ab_loop: AbLoopConfig,
+    pub playback: PlaybackConfig,
+    pub fade: FadeConfig,
+    pub equalizer: EqualizerConfig,
+    pub replay_gain: ReplayGainConfig,
+    pub stereo: StereoConfig,
+    pub compressor: CompressorConfig,
+    pub vocal_reduction: VocalReductionConfig,
+}
+
+/// Settings of the optional MPD protocol server.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct MpdServerConfig {
+    pub enabled: bool,
+    pub address: String,
+    pub port: u16,
+}
+
+impl Default for MpdServerConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            address: "127.0.0.1".to_string(),
+            port: 6600,
+        }
+    }
+}
+
+/// Settings of the optional HTTP/WebSocket remote control server.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct HttpServerConfig {
+    pub enabled: bool,
+    pub port: u16,
+    /// Clients must send this token, the server does not start if it's empty.
+    pub token: String,
+}
+
+impl Default for HttpServerConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: 6680,
+            token: String::new(),
+        }
+    }
+}
+
+/// Shell commands that are executed when player events happen (empty to disable).
+#[derive(Serialize, Deserialize, Default, Clone)]
+#[serde(default)]
+pub struct HooksConfig {
+    pub on_track_start: String,
+    pub on_track_end: String,
+    pub on_pause: String,
+    pub on_resume: String,
+    pub on_stop: String,
+    pub on_tracklist_end: String,
+}
+
+/// Settings of text/JSON files with information about the current track.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct NowPlayingConfig {
+    pub enabled: bool,
+    /// Path to the text file (empty to not write it).
+    pub text_file_path: String,
+    /// Contents of the text file, supports `{title}`, `{artist}`, `{album}`,
+    /// `{elapsed}`, `{duration}` and `{state}`.
+    pub text_template: String,
+    /// Path to the JSON file (empty to not write it).
+    pub json_file_path: String,
+    /// How often to update elapsed time while playing (0 to only update on track/state change).
+    pub update_interval_sec: u64,
+}
+
+impl Default for NowPlayingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            text_file_path: String::new(),
+            text_template: "{artist} - {title}".to_string(),
+            json_file_path: String::new(),
+            update_interval_sec: 0,
+        }
+    }
+}
+
+/// Settings of how information is shown in the user interface.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct DisplayConfig {
+    /// Show time with millisecond precision.
+    pub show_milliseconds: bool,
+    pub waveform_mode: WaveformMode,
+    /// Show the live spectrum and level meters next to the waveform.
+    pub show_visualizer: bool,
+}
+
+impl Default for DisplayConfig {
+    fn default() -> Self {
+        Self {
+            show_milliseconds: false,
+            waveform_mode: WaveformMode::default(),
+            show_visualizer: true,
+        }
+    }
+}
+
+/// How the waveform of the current track is drawn.
+#[derive(Serialize, Deserialize, Default, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum WaveformMode {
+    /// All channels in one wave.
+    #[default]
+    Combined,
+    /// Left and right channels mirrored around the center line
+    /// (or stacked if there are more channels).
+    Channels,
+    /// Levels of frequencies over time.
+    Spectrogram,
+}
+
+/// Settings of the on-disk cache of computed waveforms.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct WaveformCacheConfig {
+    pub enabled: bool,
+    pub max_size_mb: u64,
+    /// Compute waveforms of all tracks in the tracklist in the background.
+    pub precompute_tracklist: bool,
+}
+
+impl Default for WaveformCacheConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_size_mb: 100,
+            precompute_tracklist: true,
+        }
+    }
+}
+
+/// Settings of the spectrogram of the current track.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct SpectrogramConfig {
+    /// Compute the spectrogram of the playing track while the spectrogram is shown.
+    pub enabled: bool,
+    /// Number of samples analyzed at once (a power of two), bigger windows give better
+    /// frequency resolution but worse time resolution.
+    pub window_size: usize,
+    pub window_function: WindowFunction,
+    /// Number of bands the frequency axis is split into.
+    pub band_count: usize,
+    /// The lowest shown frequency (the highest one is half of the sample rate).
+    pub min_frequency_hz: f32,
+    /// Levels lower than this number of decibels below full scale are shown as silence.
+    pub dynamic_range_db: f32,
+}
+
+impl Default for SpectrogramConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            window_size: 2048,
+            window_function: WindowFunction::default(),
+            band_count: 96,
+            min_frequency_hz: 30.0,
+            dynamic_range_db: 90.0,
+        }
+    }
+}
+
+/// Window function applied to samples before the Fourier transform.
+#[derive(Serialize, Deserialize, Default, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum WindowFunction {
+    Rectangular,
+    #[default]
+    Hann,
+    Hamming,
+    Blackman,
+}
+
+/// Settings of the A-B loop.
+#[derive(Serialize, Deserialize, Default, Clone)]
+#[serde(default)]
+pub struct AbLoopConfig {
+    /// How many times the part is played (0 to repeat until the loop is cleared).
+    pub repetitions: u32,
+    /// Pause between repetitions.
+    pub gap_sec: f64,
+}
+
+/// Settings of how tracks are played.
+#[derive(Serialize, Deserialize, Default, Clone)]
+#[serde(default)]
+pub struct PlaybackConfig {
+    pub time_stretch_mode: TimeStretchMode,
+}
+
+/// Durations of fades that smooth out starts and stops of the sound (0 to not fade).
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct FadeConfig {
+    pub pause_ms: u64,
+    pub resume_ms: u64,
+    pub stop_ms: u64,
+    /// Crossfade between the old and the new position when seeking.
+    pub seek_crossfade_ms: u64,
+}
+
+impl Default for FadeConfig {
+    fn default() -> Self {
+        Self {
+            pause_ms: 150,
+            resume_ms: 150,
+            stop_ms: 300,
+            seek_crossfade_ms: 20,
+        }
+    }
+}
+
+/// Settings of the equalizer.
+#[derive(Serialize, Deserialize, Default, Clone)]
+#[serde(default)]
+pub struct EqualizerConfig {
+    pub bypass: bool,
+    /// Gains of the bands in decibels (from the lowest frequency).
+    pub gains_db: Vec<f32>,
+    pub user_presets: Vec<EqPresetConfig>,
+}
+
+/// Equalizer preset saved by the user.
+#[derive(Serialize, Deserialize, Default, Clone)]
+#[serde(default)]
+pub struct EqPresetConfig {
+    pub name: String,
+    pub gains_db: Vec<f32>,
+}
+
+/// How the left and right channels are played.
+#[derive(Serialize, Deserialize, Default, Clone, PartialEq, Debug)]
+#[serde(default)]
+pub struct StereoConfig {
+    /// From -1.0 (only the left channel) to 1.0 (only the right channel).
+    pub balance: f32,
+    /// Play the mix of both channels in both speakers.
+    pub mono: bool,
+    pub swap_channels: bool,
+    pub mute_left: bool,
+    pub mute_right: bool,
+}
+
+/// Settings of the compressor that makes quiet parts louder and loud parts quieter.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(default)]
+pub struct CompressorConfig {
+    /// Compress the sound (the compressor does nothing when disabled).
+    pub night_mode: bool,
+    /// Level above which the sound is compressed.
+    pub threshold_db: f32,
+    /// How many decibels over the threshold become one decibel.
+    pub ratio: f32,
+    pub attack_ms: f32,
+    pub release_ms: f32,
+    /// Gain applied after compression to make quiet parts louder.
+    pub makeup_gain_db: f32,
+}
+
+impl Default for CompressorConfig {
+    fn default() -> Self {
+        Self {
+            night_mode: false,
+            threshold_db: -30.0,
+            ratio: 4.0,
+            attack_ms: 10.0,
+            release_ms: 300.0,
+            makeup_gain_db: 12.0,
+        }
+    }
+}
+
+/// Settings of vocal reduction (it's turned on per track).
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(default)]
+pub struct VocalReductionConfig {
+    /// How much of the center is removed (from 0.0 to 1.0).
+    pub strength: f32,
+    /// Keep the center below `bass_cutoff_hz` (bass and kick drums are usually there too).
+    pub preserve_bass: bool,
+    pub bass_cutoff_hz: f32,
+}
+
+impl Default for VocalReductionConfig {
+    fn default() -> Self {
+        Self {
+            strength: 1.0,
+            preserve_bass: true,
+            bass_cutoff_hz: 200.0,
+        }
+    }
+}
+
+/// Settings of loudness normalization.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct ReplayGainConfig {
+    pub mode: ReplayGainMode,
+    /// Added to the gain of every normalized track.
+    pub preamp_db: f32,
+    /// Lower the gain if it would push the peak of the track over full scale.
+    pub prevent_clipping: bool,
+    /// Measure loudness of files without ReplayGain tags (results are cached).
+    pub scan_untagged: bool,
+}
+
+impl Default for ReplayGainConfig {
+    fn default() -> Self {
+        Self {
+            mode: ReplayGainMode::Off,
+            preamp_db: 0.0,
+            prevent_clipping: true,
+            scan_untagged: true,
+        }
+    }
+}
+
+/// Which ReplayGain value is used.
+#[derive(Serialize, Deserialize, Default, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum ReplayGainMode {
+    #[default]
+    Off,
+    /// Every track is played at the same loudness.
+    Track,
+    /// Tracks of an album keep their relative loudness (track gain is used if there's
+    /// no album gain).
+    Album,
+}
+
+/// How the playback rate changes the sound.
+#[derive(Serialize, Deserialize, Default, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum TimeStretchMode {
+    /// Like a tape played at a different speed: slower playback lowers the pitch.
+    #[default]
+    Tape,
+    /// Tempo changes but the pitch stays the same.
+    Stretch,
+}
+
+/// Settings that are loaded once and shared by all parts of the application,
+/// changes made by the user interface are saved to the settings file.
+pub struct SettingsStore {
+    settings: SettingsConfig,
+    /// `false` if the settings file failed to load (saving would replace
+    /// the user's settings with default values).
+    can_save: bool,
+}
+
+impl SettingsStore {
+    pub fn load() -> Self {
+        match ConfigManager::load_settings() {
+            Some(settings) => Self {
+                settings,
+                can_save: true,
+            },
+            None => Self {
+                settings: SettingsConfig::default(),
+                can_save: false,
+            },
+        }
+    }
+
+    pub fn get(&self) -> &SettingsConfig {
+        &self.settings
+    }
+
+    /// Changes the settings and saves them to the settings file.
+    pub fn change(&mut self, change: impl FnOnce(&mut SettingsConfig)) {
+        change(&mut self.settings);
+
+        if !self.can_save {
+            message_box::log_error(
+                "settings are not saved because the settings file failed to load",
+            );
+            return;
+        }
+        ConfigManager::save_settings(&self.settings);
     }
 }
 
@@ -20,15 +463,94 @@ pub struct ConfigManager {}
 
 impl ConfigManager {
     pub fn save_tracklist(path: &str, tracklist: TracklistConfig) {
+        Self::serialize_to_file(path, &tracklist);
+    }
+
+    pub fn load_tracklist(path: &str) -> TracklistConfig {
+        Self::deserialize_from_file(path).unwrap_or_default()
+    }
+
+    /// Returns path to the directory where the application stores its configuration files.
+    pub fn get_config_directory() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join(CONFIG_DIRECTORY_NAME))
+    }
+
+    /// Returns path to the directory where the application stores data that can be regenerated.
+    pub fn get_cache_directory() -> Option<PathBuf> {
+        dirs::cache_dir().map(|dir| dir.join(CONFIG_DIRECTORY_NAME))
+    }
+
+    /// Loads settings from the config directory.
+    ///
+    /// If the settings file does not exist it's created with default values
+    /// so that the user has something to edit. Returns `None` if the file can't be read.
+    pub fn load_settings() -> Option<SettingsConfig> {
+        let path = Self::get_config_directory()?.join(SETTINGS_FILE_NAME);
+
+        if !path.exists() {
+            let settings = SettingsConfig::default();
+            Self::save_settings(&settings);
+            return Some(settings);
+        }
+
+        Self::deserialize_from_file(&path.display().to_string())
+    }
+
+    /// Saves settings to the config directory.
+    pub fn save_settings(settings: &SettingsConfig) {
+        if let Some(path) = Self::get_path_in_config_directory(SETTINGS_FILE_NAME) {
+            Self::serialize_to_file(&path, settings);
+        }
+    }
+
+    /// Saves the tracklist of the current session to the config directory
+    /// so that it can be restored later.
+    ///
+    /// Called from a background thread so errors are returned instead of showing dialogs.
+    pub fn save_session(tracklist: &TracklistConfig) -> Result<(), String> {
+        let directory = Self::get_config_directory().ok_or("failed to find config directory")?;
+        std::fs::create_dir_all(&directory)
+            .map_err(|msg| format!("failed to create config directory, error: {}", msg))?;
+
+        let toml = toml::to_string(tracklist)
+            .map_err(|msg| format!("failed to serialize session, error: {}", msg))?;
+        std::fs::write(directory.join(SESSION_FILE_NAME), toml)
+            .map_err(|msg| format!("failed to write session, error: {}", msg))
+    }
+
+    /// Loads the tracklist of the last session (if it was saved).
+    pub fn load_session() -> Option<TracklistConfig> {
+        let path = Self::get_config_directory()?.join(SESSION_FILE_NAME);
+        if !path.exists() {
+            return None;
+        }
+
+        Self::deserialize_from_file(&path.display().to_string())
+    }
+
+    /// Creates the config directory (if needed) and returns path to a file in it.
+    fn get_path_in_config_directory(file_name: &str) -> Option<String> {
+        let directory = Self::get_config_directory()?;
+
+        if let Err(msg) = std::fs::create_dir_all(&directory) {
+            message_box::show_warning(
+                "Error",
+                &format!("failed to create config directory, error: {}", msg),
+            );
+            return None;
+        }
+
+        Some(directory.join(file_name).display().to_string())
+    }
+
+    fn serialize_to_file<T: Serialize>(path: &str, data: &T) {
         // Serialize to TOML.
-        let toml = match toml::to_string(&tracklist) {
+        let toml = match toml::to_string(data) {
             Err(msg) => {
-                MessageDialog::new()
-                    .set_type(MessageType::Warning)
-                    .set_title("Error")
-                    .set_text(&format!("failed to serialize data, error: {}", msg))
-                    .show_alert()
-                    .unwrap();
+                message_box::show_warning(
+                    "Error",
+                    &format!("failed to serialize data, error: {}", msg),
+                );
                 return;
             }
             Ok(t) => t,
@@ -37,59 +559,46 @@ impl ConfigManager {
         // Write to file.
         let mut file = match File::create(path) {
             Err(msg) => {
-                MessageDialog::new()
-                    .set_type(MessageType::Warning)
-                    .set_title("Error")
-                    .set_text(&format!("failed to create a file, error: {}", msg))
-                    .show_alert()
-                    .unwrap();
+                message_box::show_warning(
+                    "Error",
+                    &format!("failed to create a file, error: {}", msg),
+                );
                 return;
             }
             Ok(f) => f,
         };
 
         if let Err(msg) = write!(file, "{}", toml) {
-            MessageDialog::new()
-                .set_type(MessageType::Warning)
-                .set_title("Error")
-                .set_text(&format!("failed to write to a file, error: {}", msg))
-                .show_alert()
-                .unwrap()
+            message_box::show_warning(
+                "Error",
+                &format!("failed to write to a file, error: {}", msg),
+            )
         }
     }
 
-    pub fn load_tracklist(path: &str) -> TracklistConfig {
+    fn deserialize_from_file<T: DeserializeOwned>(path: &str) -> Option<T> {
         // Read file.
         let file_content = match std::fs::read_to_string(path) {
             Ok(v) => v,
             Err(msg) => {
-                MessageDialog::new()
-                    .set_type(MessageType::Warning)
-                    .set_title("Error")
-                    .set_text(&format!("failed to read from a file, error: {}", msg))
-                    .show_alert()
-                    .unwrap();
-                return TracklistConfig::default();
+                message_box::show_warning(
+                    "Error",
+                    &format!("failed to read from a file, error: {}", msg),
+                );
+                return None;
             }
         };
 
         // Deserialize.
-        let config: TracklistConfig = match toml::from_str(&file_content) {
-            Ok(config) => config,
+        match toml::from_str(&file_content) {
+            Ok(config) => Some(config),
             Err(msg) => {
-                MessageDialog::new()
-                    .set_type(MessageType::Warning)
-                    .set_title("Error")
-                    .set_text(&format!(
-                        "failed to deserialize from a file, error: {}",
-                        msg
-                    ))
-                    .show_alert()
-                    .unwrap();
-                return TracklistConfig::default();
+                message_box::show_warning(
+                    "Error",
+                    &format!("failed to deserialize from a file, error: {}", msg),
+                );
+                None
             }
-        };
-
-        config
+        }
     }
 }
@@ -0,0 +1,57 @@
+use native_dialog::{MessageDialog, MessageType};
+use std::sync::Mutex;
+
+type MessageHandler = Box<dyn Fn(&str, &str) + Send>;
+
+/// If set, messages are passed to this handler instead of showing dialogs.
+static MESSAGE_HANDLER: Mutex<Option<MessageHandler>> = Mutex::new(None);
+
+/// Makes all further messages to be printed to stderr instead of showing dialogs
+/// (used when we don't have a window).
+pub fn disable_dialogs() {
+    set_message_handler(|title, text| eprintln!("{}: {}", title, text));
+}
+
+/// Makes all further messages (title and text) to be passed to the specified handler
+/// instead of showing dialogs.
+pub fn set_message_handler<H: Fn(&str, &str) + Send + 'static>(handler: H) {
+    *MESSAGE_HANDLER.lock().unwrap() = Some(Box::new(handler));
+}
+
+pub fn show_info(title: &str, text: &str) {
+    show_message(MessageType::Info, title, text);
+}
+
+pub fn show_warning(title: &str, text: &str) {
+    show_message(MessageType::Warning, title, text);
+}
+
+pub fn show_error(title: &str, text: &str) {
+    show_message(MessageType::Error, title, text);
+}
+
+/// Reports an error of a background task that should not interrupt the user with a dialog,
+/// the error is passed to the message handler if it's set (so the terminal UI can show it)
+/// or printed to stderr.
+pub fn log_error(text: &str) {
+    if let Some(handler) = MESSAGE_HANDLER.lock().unwrap().as_ref() {
+        handler("Error", text);
+        return;
+    }
+
+    eprintln!("error: {}", text);
+}
+
+fn show_message(message_type: MessageType, title: &str, text: &str) {
+    if let Some(handler) = MESSAGE_HANDLER.lock().unwrap().as_ref() {
+        handler(title, text);
+        return;
+    }
+
+    MessageDialog::new()
+        .set_type(message_type)
+        .set_title(title)
+        .set_text(text)
+        .show_alert()
+        .unwrap();
+}
@@ -1,2 +1,5 @@
+pub mod command_line;
 pub mod config_manger;
+pub mod message_box;
 pub mod settings;
+pub mod time_format;
@@ -0,0 +1,17 @@
+use std::time::Duration;
+
+/// Formats time as "minutes:seconds" (with milliseconds if `show_milliseconds` is `true`).
+pub fn format_duration(duration: Duration, show_milliseconds: bool) -> String {
+    let seconds = duration.as_secs();
+
+    if show_milliseconds {
+        format!(
+            "{:02}:{:02}.{:03}",
+            seconds / 60,
+            seconds % 60,
+            duration.subsec_millis()
+        )
+    } else {
+        format!("{:02}:{:02}", seconds / 60, seconds % 60)
+    }
+}
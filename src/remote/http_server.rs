@@ -0,0 +1,983 @@
+use base64::Engine;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha1::{Digest, Sha1};
+use std::io::{ErrorKind, Read, Write};
+use std::net::TcpStream;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use super::tcp_server::{TcpServer, POLL_INTERVAL_MS};
+use crate::audio::audio_player::{AudioPlayer, MAX_VOLUME};
+use crate::misc::config_manger::HttpServerConfig;
+use crate::misc::message_box;
+
+/// Requests bigger than this are rejected.
+const MAX_REQUEST_SIZE: usize = 64 * 1024;
+
+/// Stop waiting for a request after this time.
+const REQUEST_TIMEOUT_SEC: u64 = 10;
+
+/// How often position updates are pushed to WebSocket clients.
+const POSITION_UPDATE_INTERVAL_MS: u64 = 250;
+
+/// Number of parts that the waveform is split into for clients.
+const WAVEFORM_PEAK_COUNT: usize = 1024;
+
+/// Magic string from RFC 6455 used to compute `Sec-WebSocket-Accept`.
+const WEBSOCKET_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+const WEBSOCKET_OPCODE_TEXT: u8 = 0x1;
+const WEBSOCKET_OPCODE_CLOSE: u8 = 0x8;
+const WEBSOCKET_OPCODE_PING: u8 = 0x9;
+const WEBSOCKET_OPCODE_PONG: u8 = 0xA;
+
+/// Close status sent when the client's frame is bigger than `MAX_REQUEST_SIZE`.
+const WEBSOCKET_STATUS_MESSAGE_TOO_BIG: u16 = 1009;
+
+/// Body of `POST /api/tracklist`.
+#[derive(Deserialize)]
+struct AddTrackRequest {
+    path: String,
+}
+
+/// Body of `POST /api/tracklist/{id}/move`.
+#[derive(Deserialize)]
+struct MoveTrackRequest {
+    index: usize,
+}
+
+/// Body of `POST /api/play` (without the ID the current track is resumed or started).
+#[derive(Deserialize)]
+struct PlayRequest {
+    id: Option<u32>,
+}
+
+#[derive(Deserialize)]
+struct SeekRequest {
+    /// In seconds.
+    position: f64,
+}
+
+#[derive(Deserialize)]
+struct VolumeRequest {
+    volume: f64,
+}
+
+#[derive(Deserialize)]
+struct RateRequest {
+    rate: f64,
+}
+
+#[derive(Serialize, Clone, PartialEq)]
+struct TrackJson {
+    id: u32,
+    index: usize,
+    name: String,
+    path: String,
+}
+
+#[derive(Serialize, Clone, PartialEq)]
+struct StatusJson {
+    state: &'static str,
+    current_track_index: Option<usize>,
+    current_track: Option<TrackJson>,
+    /// Not sent in WebSocket status events (position updates are sent separately).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    position: Option<f64>,
+    duration: f64,
+    volume: f64,
+    playback_rate: f64,
+    tracklist_version: u32,
+}
+
+#[derive(Serialize)]
+struct WaveformJson {
+    peak_count: usize,
+    /// Minimum and maximum of every part.
+    peaks: Vec<[f32; 2]>,
+}
+
+/// Events pushed to WebSocket clients.
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum WebSocketEvent {
+    TrackChanged { track: Option<TrackJson> },
+    Status { status: StatusJson },
+    WaveformProgress { ready_peaks: usize },
+    Position { position: f64, duration: f64 },
+}
+
+/// Embedded HTTP server (bound to localhost) that exposes a JSON API to control the player
+/// and a WebSocket feed with live status updates.
+pub struct HttpServer {
+    _server: TcpServer,
+}
+
+impl HttpServer {
+    /// Starts listening for clients, returns `None` if failed to start.
+    pub fn new(audio_player: Arc<Mutex<AudioPlayer>>, config: &HttpServerConfig) -> Option<Self> {
+        if config.token.is_empty() {
+            message_box::show_warning(
+                "Error",
+                "HTTP server is enabled but no token is specified in the settings",
+            );
+            return None;
+        }
+
+        let token = config.token.clone();
+        let server = TcpServer::new(
+            "HTTP server",
+            &format!("127.0.0.1:{}", config.port),
+            move |stream, stop_signal| {
+                let mut connection = HttpConnection {
+                    stream,
+                    audio_player: audio_player.clone(),
+                    stop_signal,
+                    token: token.clone(),
+                    pending_input: Vec::new(),
+                };
+                connection.run();
+            },
+        )?;
+
+        Some(Self { _server: server })
+    }
+}
+
+struct HttpRequest {
+    method: String,
+    path: String,
+    query: Vec<(String, String)>,
+    /// Header names are lowercase.
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+impl HttpRequest {
+    /// Parses the request line and headers (without the empty line after them),
+    /// the body is left empty.
+    fn parse_head(head: &str) -> Option<Self> {
+        let mut lines = head.split("\r\n");
+
+        // Parse request line.
+        let mut request_line = lines.next()?.split_whitespace();
+        let method = request_line.next()?.to_string();
+        let target = request_line.next()?;
+        let (path, query) = target.split_once('?').unwrap_or((target, ""));
+
+        let query = query
+            .split('&')
+            .filter(|pair| !pair.is_empty())
+            .filter_map(|pair| {
+                let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+                Some((decode_query_component(key)?, decode_query_component(value)?))
+            })
+            .collect();
+
+        let headers = lines
+            .filter_map(|line| line.split_once(':'))
+            .map(|(name, value)| (name.trim().to_lowercase(), value.trim().to_string()))
+            .collect();
+
+        Some(Self {
+            method,
+            path: percent_decode(path)?,
+            query,
+            headers,
+            body: Vec::new(),
+        })
+    }
+
+    fn get_header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn get_query(&self, name: &str) -> Option<&str> {
+        self.query
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Returns body parsed as JSON (empty body is treated as an empty object),
+    /// the error is the response to send.
+    fn get_json_body<T: DeserializeOwned>(&self) -> Result<T, (u16, Value)> {
+        let body = if self.body.trim_ascii().is_empty() {
+            b"{}".as_slice()
+        } else {
+            &self.body
+        };
+        serde_json::from_slice(body)
+            .map_err(|error| (400, error_json(&format!("invalid JSON: {}", error))))
+    }
+}
+
+struct HttpConnection {
+    stream: TcpStream,
+    audio_player: Arc<Mutex<AudioPlayer>>,
+    stop_signal: Arc<AtomicBool>,
+    token: String,
+    pending_input: Vec<u8>,
+}
+
+impl HttpConnection {
+    fn run(&mut self) {
+        if self
+            .stream
+            .set_read_timeout(Some(Duration::from_millis(POLL_INTERVAL_MS)))
+            .is_err()
+        {
+            return;
+        }
+
+        let request = match self.read_request() {
+            None => return,
+            Some(request) => request,
+        };
+
+        // Answer CORS preflight requests so that browser remotes work.
+        if request.method == "OPTIONS" {
+            self.send_response(204, None);
+            return;
+        }
+
+        if !self.is_authorized(&request) {
+            self.send_response(401, Some(&error_json("invalid or missing token")));
+            return;
+        }
+
+        if request.path == "/api/events" {
+            if request
+                .get_header("upgrade")
+                .is_some_and(|value| value.eq_ignore_ascii_case("websocket"))
+            {
+                self.run_websocket(&request);
+            } else {
+                self.send_response(426, Some(&error_json("WebSocket upgrade expected")));
+            }
+            return;
+        }
+
+        // A track that the request asks to play is opened after the player is unlocked.
+        let mut track_to_play = None;
+        let (status, body) = {
+            let mut audio_player = self.audio_player.lock().unwrap();
+            Self::handle_request(&mut audio_player, &request, &mut track_to_play)
+        };
+        let (status, body) = match track_to_play {
+            None => (status, body),
+            Some(id) => self.play_track(id),
+        };
+        self.send_response(status, Some(&body));
+    }
+
+    /// Plays the track with the specified ID, the player is not locked while the file is opened.
+    fn play_track(&self, id: u32) -> (u16, Value) {
+        let path = {
+            let audio_player = self.audio_player.lock().unwrap();
+            match audio_player.find_track_index(id) {
+                None => return (404, error_json("no such track")),
+                Some(index) => audio_player.get_tracklist()[index].path.clone(),
+            }
+        };
+
+        let decoder = AudioPlayer::open_sound(&path);
+
+        // The tracklist might have changed in the meantime.
+        let mut audio_player = self.audio_player.lock().unwrap();
+        let index = match audio_player.find_track_index(id) {
+            None => return (404, error_json("no such track")),
+            Some(index) => index,
+        };
+        match audio_player.play_track_with_decoder(index, decoder) {
+            Err(msg) => player_error(msg),
+            Ok(()) => (200, ok_json()),
+        }
+    }
+
+    fn is_authorized(&self, request: &HttpRequest) -> bool {
+        if let Some(value) = request.get_header("authorization") {
+            if value.strip_prefix("Bearer ") == Some(self.token.as_str()) {
+                return true;
+            }
+        }
+
+        // Browsers can't set headers for WebSocket connections so also accept a query parameter.
+        request.get_query("token") == Some(self.token.as_str())
+    }
+
+    /// Returns the response, `track_to_play` is set to the ID of the track that
+    /// the request asks to play (it's played by the caller).
+    fn handle_request(
+        audio_player: &mut AudioPlayer,
+        request: &HttpRequest,
+        track_to_play: &mut Option<u32>,
+    ) -> (u16, Value) {
+        match Self::handle_api_request(audio_player, request, track_to_play) {
+            Ok(response) | Err(response) => response,
+        }
+    }
+
+    /// Returns the response, errors are returned as `Err` so that `?` can be used.
+    fn handle_api_request(
+        audio_player: &mut AudioPlayer,
+        request: &HttpRequest,
+        track_to_play: &mut Option<u32>,
+    ) -> Result<(u16, Value), (u16, Value)> {
+        let segments: Vec<&str> = request
+            .path
+            .trim_matches('/')
+            .split('/')
+            .filter(|segment| !segment.is_empty())
+            .collect();
+
+        Ok(match (request.method.as_str(), segments.as_slice()) {
+            ("GET", ["api", "status"]) => (200, to_json(get_status_json(audio_player))),
+            ("GET", ["api", "tracklist"]) => (200, to_json(get_tracklist_json(audio_player))),
+            ("GET", ["api", "waveform"]) => {
+                // Only computed peaks are returned (they are computed from the start).
+                let sound_wave = audio_player.get_current_sound_wave();
+                let wave = sound_wave.lock().unwrap();
+                let peaks = (0..WAVEFORM_PEAK_COUNT)
+                    .map_while(|index| {
+                        wave.get_peak(
+                            index as f64 / WAVEFORM_PEAK_COUNT as f64,
+                            (index + 1) as f64 / WAVEFORM_PEAK_COUNT as f64,
+                        )
+                    })
+                    .map(|peak| [peak.min, peak.max])
+                    .collect();
+                (
+                    200,
+                    to_json(WaveformJson {
+                        peak_count: WAVEFORM_PEAK_COUNT,
+                        peaks,
+                    }),
+                )
+            }
+            ("POST", ["api", "tracklist"]) => {
+                let body: AddTrackRequest = request.get_json_body()?;
+                match audio_player.add_track(PathBuf::from(body.path).as_path()) {
+                    None => (400, error_json("file does not exist or is not supported")),
+                    Some(id) => (201, json!({ "id": id })),
+                }
+            }
+            ("DELETE", ["api", "tracklist"]) => {
+                audio_player.clear_tracklist();
+                (200, ok_json())
+            }
+            ("DELETE", ["api", "tracklist", id]) => match find_track(audio_player, id) {
+                None => (404, error_json("no such track")),
+                Some(index) => {
+                    audio_player.remove_track(index);
+                    (200, ok_json())
+                }
+            },
+            ("POST", ["api", "tracklist", id, "move"]) => {
+                let from = match find_track(audio_player, id) {
+                    None => return Err((404, error_json("no such track"))),
+                    Some(index) => index,
+                };
+                let to = request.get_json_body::<MoveTrackRequest>()?.index;
+                if to >= audio_player.get_tracklist().len() {
+                    return Err((400, error_json("index is out of bounds")));
+                }
+                audio_player.move_track(from, to);
+                (200, ok_json())
+            }
+            ("POST", ["api", "play"]) => {
+                match request.get_json_body::<PlayRequest>()?.id {
+                    Some(id) => match audio_player.find_track_index(id) {
+                        None => return Err((404, error_json("no such track"))),
+                        Some(_) => *track_to_play = Some(id),
+                    },
+                    None => {
+                        if audio_player.is_sound_loaded() {
+                            audio_player.set_paused(false);
+                        } else {
+                            let index = audio_player.get_current_track_index().unwrap_or(0);
+                            *track_to_play = get_track_id(audio_player, Some(index));
+                        }
+                    }
+                }
+                (200, ok_json())
+            }
+            ("POST", ["api", "pause"]) => {
+                audio_player.set_paused(true);
+                (200, ok_json())
+            }
+            ("POST", ["api", "toggle"]) => {
+                audio_player.pause_resume();
+                (200, ok_json())
+            }
+            ("POST", ["api", "stop"]) => {
+                audio_player.stop();
+                (200, ok_json())
+            }
+            ("POST", ["api", "next"]) => {
+                *track_to_play = get_track_id(audio_player, audio_player.get_next_track_index());
+                (200, ok_json())
+            }
+            ("POST", ["api", "previous"]) => {
+                *track_to_play =
+                    get_track_id(audio_player, audio_player.get_previous_track_index());
+                (200, ok_json())
+            }
+            ("POST", ["api", "seek"]) => {
+                let position = request.get_json_body::<SeekRequest>()?.position;
+                let position = match Duration::try_from_secs_f64(position.max(0.0)) {
+                    Err(_) => return Err((400, error_json("invalid position"))),
+                    Ok(position) => position,
+                };
+                audio_player.set_current_sound_pos(position);
+                (200, ok_json())
+            }
+            ("PUT", ["api", "volume"]) => {
+                let volume = request.get_json_body::<VolumeRequest>()?.volume;
+                audio_player.set_volume(volume.clamp(0.0, MAX_VOLUME));
+                (200, ok_json())
+            }
+            ("PUT", ["api", "rate"]) => {
+                // The rate is clamped by the player.
+                let rate = request.get_json_body::<RateRequest>()?.rate;
+                audio_player.set_playback_rate(rate);
+                (200, ok_json())
+            }
+            _ => (404, error_json("not found")),
+        })
+    }
+
+    /// Pushes status updates to the client until it disconnects.
+    fn run_websocket(&mut self, request: &HttpRequest) {
+        let key = match request.get_header("sec-websocket-key") {
+            None => {
+                self.send_response(400, Some(&error_json("missing Sec-WebSocket-Key")));
+                return;
+            }
+            Some(key) => key,
+        };
+
+        // Complete the handshake.
+        let mut hasher = Sha1::new();
+        hasher.update(key.as_bytes());
+        hasher.update(WEBSOCKET_GUID.as_bytes());
+        let accept = base64::engine::general_purpose::STANDARD.encode(hasher.finalize());
+
+        let response = format!(
+            "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+            accept
+        );
+        if self.stream.write_all(response.as_bytes()).is_err() {
+            return;
+        }
+
+        let mut last_snapshot: Option<StatusSnapshot> = None;
+        let mut last_position_update = Instant::now();
+
+        while !self.stop_signal.load(Ordering::SeqCst) {
+            // Process incoming frames (clients don't send us any commands through the socket).
+            match self.read_websocket_frame() {
+                Err(error) => {
+                    if error.kind() == ErrorKind::InvalidData {
+                        let _ = self.write_websocket_frame(
+                            WEBSOCKET_OPCODE_CLOSE,
+                            &WEBSOCKET_STATUS_MESSAGE_TOO_BIG.to_be_bytes(),
+                        );
+                    }
+                    return;
+                }
+                Ok(None) => {}
+                Ok(Some((WEBSOCKET_OPCODE_CLOSE, payload))) => {
+                    let _ = self.write_websocket_frame(WEBSOCKET_OPCODE_CLOSE, &payload);
+                    return;
+                }
+                Ok(Some((WEBSOCKET_OPCODE_PING, payload))) => {
+                    if self
+                        .write_websocket_frame(WEBSOCKET_OPCODE_PONG, &payload)
+                        .is_err()
+                    {
+                        return;
+                    }
+                }
+                Ok(Some(_)) => {}
+            }
+
+            // Collect events.
+            let mut events = Vec::new();
+            {
+                let audio_player = self.audio_player.lock().unwrap();
+                let snapshot = StatusSnapshot::new(&audio_player);
+
+                let previous = last_snapshot.unwrap_or(StatusSnapshot {
+                    current_track_id: None,
+                    status_json: None,
+                    wave_ready_peaks: 0,
+                });
+
+                if snapshot.current_track_id != previous.current_track_id {
+                    events.push(WebSocketEvent::TrackChanged {
+                        track: get_current_track_json(&audio_player),
+                    });
+                }
+
+                if snapshot.status_json != previous.status_json {
+                    if let Some(status) = snapshot.status_json.clone() {
+                        events.push(WebSocketEvent::Status { status });
+                    }
+                }
+
+                if snapshot.wave_ready_peaks != previous.wave_ready_peaks {
+                    events.push(WebSocketEvent::WaveformProgress {
+                        ready_peaks: snapshot.wave_ready_peaks,
+                    });
+                }
+
+                if audio_player.is_sound_loaded()
+                    && last_position_update.elapsed()
+                        >= Duration::from_millis(POSITION_UPDATE_INTERVAL_MS)
+                {
+                    last_position_update = Instant::now();
+                    events.push(WebSocketEvent::Position {
+                        position: audio_player.get_current_sound_position().as_secs_f64(),
+                        duration: audio_player.get_current_sound_duration().as_secs_f64(),
+                    });
+                }
+
+                last_snapshot = Some(snapshot);
+            }
+
+            for event in events {
+                let text = serde_json::to_string(&event).unwrap_or_default();
+                if self
+                    .write_websocket_frame(WEBSOCKET_OPCODE_TEXT, text.as_bytes())
+                    .is_err()
+                {
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Returns the next complete frame (opcode and unmasked payload), `None` if nothing
+    /// was received before the timeout and an error if the client disconnected
+    /// (`ErrorKind::InvalidData` if the frame is bigger than `MAX_REQUEST_SIZE`).
+    fn read_websocket_frame(&mut self) -> std::io::Result<Option<(u8, Vec<u8>)>> {
+        loop {
+            if Self::parse_websocket_payload_size(&self.pending_input)
+                .is_some_and(|(_, payload_size)| payload_size > MAX_REQUEST_SIZE as u64)
+            {
+                return Err(ErrorKind::InvalidData.into());
+            }
+
+            if let Some((frame, frame_size)) = Self::parse_websocket_frame(&self.pending_input) {
+                self.pending_input.drain(..frame_size);
+                return Ok(Some(frame));
+            }
+
+            match self.read_some()? {
+                0 => return Ok(None),
+                _ => continue,
+            }
+        }
+    }
+
+    /// Returns the offset after the size fields and the payload size announced in the frame
+    /// header (`None` if the header is not received yet).
+    fn parse_websocket_payload_size(data: &[u8]) -> Option<(usize, u64)> {
+        match data.get(1)? & 0x7F {
+            126 => {
+                let bytes = data.get(2..4)?;
+                Some((4, u16::from_be_bytes([bytes[0], bytes[1]]) as u64))
+            }
+            127 => {
+                let bytes = data.get(2..10)?;
+                Some((10, u64::from_be_bytes(bytes.try_into().ok()?)))
+            }
+            size => Some((2, size as u64)),
+        }
+    }
+
+    fn parse_websocket_frame(data: &[u8]) -> Option<((u8, Vec<u8>), usize)> {
+        let (mut offset, payload_size) = Self::parse_websocket_payload_size(data)?;
+        let payload_size = usize::try_from(payload_size).ok()?;
+
+        let opcode = data[0] & 0x0F;
+        let is_masked = data[1] & 0x80 != 0;
+
+        let mask = if is_masked {
+            let bytes = data.get(offset..offset + 4)?;
+            offset += 4;
+            [bytes[0], bytes[1], bytes[2], bytes[3]]
+        } else {
+            [0; 4]
+        };
+
+        let payload = data.get(offset..offset.checked_add(payload_size)?)?;
+        let payload: Vec<u8> = payload
+            .iter()
+            .enumerate()
+            .map(|(i, byte)| byte ^ mask[i % 4])
+            .collect();
+
+        Some(((opcode, payload), offset + payload_size))
+    }
+
+    fn write_websocket_frame(&mut self, opcode: u8, payload: &[u8]) -> std::io::Result<()> {
+        let mut frame = vec![0x80 | opcode];
+
+        if payload.len() < 126 {
+            frame.push(payload.len() as u8);
+        } else if payload.len() <= u16::MAX as usize {
+            frame.push(126);
+            frame.extend_from_slice(&(payload.len() as u16).to_be_bytes());
+        } else {
+            frame.push(127);
+            frame.extend_from_slice(&(payload.len() as u64).to_be_bytes());
+        }
+        frame.extend_from_slice(payload);
+
+        self.stream.write_all(&frame)
+    }
+
+    /// Reads available data into the pending input, returns `0` on timeout.
+    fn read_some(&mut self) -> std::io::Result<usize> {
+        let mut buf = [0; 4096];
+        match self.stream.read(&mut buf) {
+            Ok(0) => Err(ErrorKind::UnexpectedEof.into()),
+            Ok(count) => {
+                self.pending_input.extend_from_slice(&buf[..count]);
+                Ok(count)
+            }
+            Err(error) => {
+                if error.kind() == ErrorKind::WouldBlock || error.kind() == ErrorKind::TimedOut {
+                    Ok(0)
+                } else {
+                    Err(error)
+                }
+            }
+        }
+    }
+
+    fn read_request(&mut self) -> Option<HttpRequest> {
+        let started_at = Instant::now();
+
+        // Read headers.
+        let headers_end = loop {
+            if let Some(pos) = self
+                .pending_input
+                .windows(4)
+                .position(|window| window == b"\r\n\r\n")
+            {
+                break pos;
+            }
+
+            if self.pending_input.len() > MAX_REQUEST_SIZE
+                || started_at.elapsed() > Duration::from_secs(REQUEST_TIMEOUT_SEC)
+                || self.stop_signal.load(Ordering::SeqCst)
+            {
+                return None;
+            }
+
+            self.read_some().ok()?;
+        };
+
+        let mut request =
+            HttpRequest::parse_head(&String::from_utf8_lossy(&self.pending_input[..headers_end]))?;
+
+        let content_length = request
+            .get_header("content-length")
+            .and_then(|value| value.parse::<usize>().ok())
+            .unwrap_or(0);
+        if content_length > MAX_REQUEST_SIZE {
+            return None;
+        }
+
+        // Read body.
+        let body_start = headers_end + 4;
+        while self.pending_input.len() < body_start + content_length {
+            if started_at.elapsed() > Duration::from_secs(REQUEST_TIMEOUT_SEC)
+                || self.stop_signal.load(Ordering::SeqCst)
+            {
+                return None;
+            }
+            self.read_some().ok()?;
+        }
+        request.body = self.pending_input[body_start..body_start + content_length].to_vec();
+        self.pending_input.drain(..body_start + content_length);
+
+        Some(request)
+    }
+
+    fn send_response(&mut self, status: u16, body: Option<&Value>) {
+        let reason = match status {
+            200 => "OK",
+            201 => "Created",
+            204 => "No Content",
+            400 => "Bad Request",
+            401 => "Unauthorized",
+            404 => "Not Found",
+            426 => "Upgrade Required",
+            _ => "",
+        };
+
+        let body = body.map(|body| body.to_string()).unwrap_or_default();
+        let response = format!(
+            "HTTP/1.1 {} {}\r\n\
+             Content-Type: application/json\r\n\
+             Content-Length: {}\r\n\
+             Access-Control-Allow-Origin: *\r\n\
+             Access-Control-Allow-Methods: GET, POST, PUT, DELETE, OPTIONS\r\n\
+             Access-Control-Allow-Headers: Authorization, Content-Type\r\n\
+             Connection: close\r\n\r\n{}",
+            status,
+            reason,
+            body.len(),
+            body
+        );
+
+        let _ = self.stream.write_all(response.as_bytes());
+    }
+}
+
+/// Part of the player state that we compare to send WebSocket events.
+struct StatusSnapshot {
+    current_track_id: Option<u32>,
+    /// Status without the position (position updates are sent separately),
+    /// `None` before the first snapshot.
+    status_json: Option<StatusJson>,
+    /// Number of computed peaks of the waveform returned to clients.
+    wave_ready_peaks: usize,
+}
+
+impl StatusSnapshot {
+    fn new(audio_player: &AudioPlayer) -> Self {
+        let current_track_id = audio_player
+            .get_current_track_index()
+            .map(|index| audio_player.get_tracklist()[index].id);
+
+        let status_json = StatusJson {
+            position: None,
+            ..get_status_json(audio_player)
+        };
+
+        let sound_wave = audio_player.get_current_sound_wave();
+        let wave = sound_wave.lock().unwrap();
+        let wave_ready_peaks = (wave.get_ready_bucket_count() * WAVEFORM_PEAK_COUNT)
+            .checked_div(wave.get_bucket_count())
+            .unwrap_or(0);
+
+        Self {
+            current_track_id,
+            status_json: Some(status_json),
+            wave_ready_peaks,
+        }
+    }
+}
+
+fn get_status_json(audio_player: &AudioPlayer) -> StatusJson {
+    let state = if !audio_player.is_sound_loaded() {
+        "stopped"
+    } else if audio_player.is_paused() {
+        "paused"
+    } else {
+        "playing"
+    };
+
+    StatusJson {
+        state,
+        current_track_index: audio_player.get_current_track_index(),
+        current_track: get_current_track_json(audio_player),
+        position: Some(audio_player.get_current_sound_position().as_secs_f64()),
+        duration: audio_player.get_current_sound_duration().as_secs_f64(),
+        volume: audio_player.get_volume(),
+        playback_rate: audio_player.get_playback_rate(),
+        tracklist_version: audio_player.get_tracklist_version(),
+    }
+}
+
+fn get_current_track_json(audio_player: &AudioPlayer) -> Option<TrackJson> {
+    audio_player
+        .get_current_track_index()
+        .map(|index| get_track_json(audio_player, index))
+}
+
+fn get_track_json(audio_player: &AudioPlayer, index: usize) -> TrackJson {
+    let track = &audio_player.get_tracklist()[index];
+    TrackJson {
+        id: track.id,
+        index,
+        name: track.name.clone(),
+        path: track.path.clone(),
+    }
+}
+
+fn get_tracklist_json(audio_player: &AudioPlayer) -> Vec<TrackJson> {
+    (0..audio_player.get_tracklist().len())
+        .map(|index| get_track_json(audio_player, index))
+        .collect()
+}
+
+fn find_track(audio_player: &AudioPlayer, id: &str) -> Option<usize> {
+    audio_player.find_track_index(id.parse().ok()?)
+}
+
+fn get_track_id(audio_player: &AudioPlayer, index: Option<usize>) -> Option<u32> {
+    Some(audio_player.get_tracklist().get(index?)?.id)
+}
+
+/// Converts a response struct to JSON (the structs are always serializable).
+fn to_json<T: Serialize>(value: T) -> Value {
+    serde_json::to_value(value).unwrap_or_default()
+}
+
+fn ok_json() -> Value {
+    json!({ "ok": true })
+}
+
+fn error_json(message: &str) -> Value {
+    json!({ "error": message })
+}
+
+/// Response to an error of the player (for example a file that can't be decoded).
+fn player_error(message: String) -> (u16, Value) {
+    (500, error_json(&message))
+}
+
+/// Decodes `%XX` sequences, returns `None` if the result is not valid UTF-8.
+fn percent_decode(text: &str) -> Option<String> {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' if i + 2 < bytes.len() => {
+                let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).ok()?;
+                decoded.push(u8::from_str_radix(hex, 16).ok()?);
+                i += 3;
+            }
+            byte => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+
+    String::from_utf8(decoded).ok()
+}
+
+/// Decodes a key or a value of the query string where `+` also means space
+/// (unlike in the path).
+fn decode_query_component(text: &str) -> Option<String> {
+    percent_decode(&text.replace('+', " "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_head_reads_request_line_and_headers() {
+        let request = HttpRequest::parse_head(
+            "POST /api/tracks/My%20Song HTTP/1.1\r\nHost: localhost\r\nContent-Length:  12 \r\nX-Token: a:b",
+        )
+        .unwrap();
+
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.path, "/api/tracks/My Song");
+        assert!(request.query.is_empty());
+        assert_eq!(request.get_header("host"), Some("localhost"));
+        assert_eq!(request.get_header("content-length"), Some("12"));
+        assert_eq!(request.get_header("x-token"), Some("a:b"));
+        assert!(request.body.is_empty());
+    }
+
+    #[test]
+    fn parse_head_decodes_plus_only_in_query() {
+        let request =
+            HttpRequest::parse_head("GET /a+b%2B?token=x%2By+z&flag&name=%C3%A9 HTTP/1.1").unwrap();
+
+        assert_eq!(request.path, "/a+b+");
+        assert_eq!(request.get_query("token"), Some("x+y z"));
+        assert_eq!(request.get_query("flag"), Some(""));
+        assert_eq!(request.get_query("name"), Some("é"));
+        assert_eq!(request.get_query("missing"), None);
+    }
+
+    #[test]
+    fn parse_head_rejects_malformed_request() {
+        assert!(HttpRequest::parse_head("").is_none());
+        assert!(HttpRequest::parse_head("GET").is_none());
+        assert!(HttpRequest::parse_head("GET /%FF HTTP/1.1").is_none());
+
+        // An invalid query parameter is skipped.
+        let request = HttpRequest::parse_head("GET /?a=%FF&b=1 HTTP/1.1").unwrap();
+        assert_eq!(request.get_query("a"), None);
+        assert_eq!(request.get_query("b"), Some("1"));
+    }
+
+    #[test]
+    fn parse_websocket_frame_unmasks_payload() {
+        // Examples from RFC 6455, section 5.7.
+        let masked = [
+            0x81, 0x85, 0x37, 0xfa, 0x21, 0x3d, 0x7f, 0x9f, 0x4d, 0x51, 0x58,
+        ];
+        assert_eq!(
+            HttpConnection::parse_websocket_frame(&masked),
+            Some(((WEBSOCKET_OPCODE_TEXT, b"Hello".to_vec()), masked.len()))
+        );
+
+        let unmasked = [0x81, 0x05, 0x48, 0x65, 0x6c, 0x6c, 0x6f, 0x89, 0x00];
+        assert_eq!(
+            HttpConnection::parse_websocket_frame(&unmasked),
+            Some(((WEBSOCKET_OPCODE_TEXT, b"Hello".to_vec()), 7))
+        );
+        assert_eq!(
+            HttpConnection::parse_websocket_frame(&unmasked[7..]),
+            Some(((WEBSOCKET_OPCODE_PING, Vec::new()), 2))
+        );
+    }
+
+    #[test]
+    fn parse_websocket_frame_reads_extended_size() {
+        let mut frame = vec![0x82, 0x7E, 0x01, 0x00];
+        frame.extend(std::iter::repeat_n(0xAB, 256));
+        assert_eq!(
+            HttpConnection::parse_websocket_frame(&frame),
+            Some(((0x2, vec![0xAB; 256]), 260))
+        );
+
+        let header = [0x82, 0x7F, 0, 0, 0, 1, 0, 0, 0, 0];
+        assert_eq!(
+            HttpConnection::parse_websocket_payload_size(&header),
+            Some((10, 1 << 32))
+        );
+    }
+
+    #[test]
+    fn parse_websocket_frame_waits_for_whole_frame() {
+        let masked = [
+            0x81, 0x85, 0x37, 0xfa, 0x21, 0x3d, 0x7f, 0x9f, 0x4d, 0x51, 0x58,
+        ];
+        for size in 0..masked.len() {
+            assert_eq!(HttpConnection::parse_websocket_frame(&masked[..size]), None);
+        }
+
+        assert_eq!(HttpConnection::parse_websocket_payload_size(&[0x81]), None);
+        assert_eq!(
+            HttpConnection::parse_websocket_payload_size(&[0x81, 0xFE, 0x01]),
+            None
+        );
+        assert_eq!(
+            HttpConnection::parse_websocket_payload_size(&[0x81, 0xFF, 0, 0, 0, 0]),
+            None
+        );
+    }
+}
@@ -0,0 +1,4 @@
+pub mod http_server;
+pub mod mpd_server;
+pub mod remote_servers;
+pub mod tcp_server;
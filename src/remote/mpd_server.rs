@@ -0,0 +1,845 @@
+use std::io::{ErrorKind, Read, Write};
+use std::net::TcpStream;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use super::tcp_server::{TcpServer, POLL_INTERVAL_MS};
+use crate::audio::audio_player::AudioPlayer;
+use crate::misc::config_manger::MpdServerConfig;
+
+/// Protocol version we report to clients (we implement a subset of its commands).
+const MPD_PROTOCOL_VERSION: &str = "0.23.0";
+
+const READ_BUFFER_SIZE: usize = 4096;
+
+/// The connection is closed if a command line is longer than this.
+const MAX_LINE_SIZE: usize = 64 * 1024;
+
+// Error codes from MPD's `ack.h`.
+const ACK_ERROR_NOT_LIST: u32 = 1;
+const ACK_ERROR_ARG: u32 = 2;
+const ACK_ERROR_UNKNOWN: u32 = 5;
+const ACK_ERROR_NO_EXIST: u32 = 50;
+const ACK_ERROR_SYSTEM: u32 = 52;
+
+/// Commands that we support (returned by the `commands` command).
+const SUPPORTED_COMMANDS: &[&str] = &[
+    "add",
+    "addid",
+    "clear",
+    "close",
+    "command_list_begin",
+    "command_list_end",
+    "command_list_ok_begin",
+    "commands",
+    "currentsong",
+    "delete",
+    "deleteid",
+    "getvol",
+    "idle",
+    "move",
+    "moveid",
+    "next",
+    "noidle",
+    "notcommands",
+    "outputs",
+    "pause",
+    "ping",
+    "play",
+    "playid",
+    "playlistid",
+    "playlistinfo",
+    "plchanges",
+    "previous",
+    "seek",
+    "seekcur",
+    "seekid",
+    "setvol",
+    "stats",
+    "status",
+    "stop",
+    "tagtypes",
+    "urlhandlers",
+    "volume",
+];
+
+/// TCP server that implements a subset of the Music Player Daemon protocol
+/// so that existing MPD clients can control the player.
+pub struct MpdServer {
+    _server: TcpServer,
+}
+
+impl MpdServer {
+    /// Starts listening for clients, returns `None` if failed to start.
+    pub fn new(audio_player: Arc<Mutex<AudioPlayer>>, config: &MpdServerConfig) -> Option<Self> {
+        let server = TcpServer::new(
+            "MPD server",
+            &format!("{}:{}", config.address, config.port),
+            move |stream, stop_signal| {
+                if let Some(mut client) = MpdClient::new(stream, audio_player.clone(), stop_signal)
+                {
+                    client.run();
+                }
+            },
+        )?;
+
+        Some(Self { _server: server })
+    }
+}
+
+#[derive(Debug)]
+struct MpdError {
+    code: u32,
+    message: String,
+}
+
+impl MpdError {
+    fn new(code: u32, message: &str) -> Self {
+        Self {
+            code,
+            message: message.to_string(),
+        }
+    }
+
+    /// Error of the player (for example a file that can't be decoded).
+    fn system(message: String) -> Self {
+        Self {
+            code: ACK_ERROR_SYSTEM,
+            message,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum PlayerState {
+    Play,
+    Pause,
+    Stop,
+}
+
+impl PlayerState {
+    fn get(audio_player: &AudioPlayer) -> Self {
+        if !audio_player.is_sound_loaded() {
+            PlayerState::Stop
+        } else if audio_player.is_paused() {
+            PlayerState::Pause
+        } else {
+            PlayerState::Play
+        }
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            PlayerState::Play => "play",
+            PlayerState::Pause => "pause",
+            PlayerState::Stop => "stop",
+        }
+    }
+}
+
+/// Part of the player state that we compare to notify idling clients.
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct PlayerSnapshot {
+    tracklist_version: u32,
+    current_track_index: Option<usize>,
+    state: PlayerState,
+    volume: u32,
+}
+
+impl PlayerSnapshot {
+    fn new(audio_player: &AudioPlayer) -> Self {
+        Self {
+            tracklist_version: audio_player.get_tracklist_version(),
+            current_track_index: audio_player.get_current_track_index(),
+            state: PlayerState::get(audio_player),
+            volume: MpdClient::get_volume_percent(audio_player),
+        }
+    }
+
+    /// Returns names of subsystems that differ between two snapshots.
+    fn get_changed_subsystems(&self, other: &Self) -> Vec<&'static str> {
+        let mut subsystems = Vec::new();
+
+        if self.tracklist_version != other.tracklist_version {
+            subsystems.push("playlist");
+        }
+        if self.current_track_index != other.current_track_index || self.state != other.state {
+            subsystems.push("player");
+        }
+        if self.volume != other.volume {
+            subsystems.push("mixer");
+        }
+
+        subsystems
+    }
+}
+
+struct MpdClient {
+    stream: TcpStream,
+    audio_player: Arc<Mutex<AudioPlayer>>,
+    stop_signal: Arc<AtomicBool>,
+    pending_input: Vec<u8>,
+}
+
+impl MpdClient {
+    fn new(
+        stream: TcpStream,
+        audio_player: Arc<Mutex<AudioPlayer>>,
+        stop_signal: Arc<AtomicBool>,
+    ) -> Option<Self> {
+        // Use timeouts instead of blocking forever so that we can check the stop signal.
+        stream
+            .set_read_timeout(Some(Duration::from_millis(POLL_INTERVAL_MS)))
+            .ok()?;
+
+        Some(Self {
+            stream,
+            audio_player,
+            stop_signal,
+            pending_input: Vec::new(),
+        })
+    }
+
+    fn run(&mut self) {
+        if self
+            .write(&format!("OK MPD {}\n", MPD_PROTOCOL_VERSION))
+            .is_err()
+        {
+            return;
+        }
+
+        // Commands collected between `command_list_begin` and `command_list_end`,
+        // the flag tells if `list_OK` should be sent after each command.
+        let mut command_list: Option<(Vec<String>, bool)> = None;
+
+        while !self.stop_signal.load(Ordering::SeqCst) {
+            let line = match self.read_line() {
+                Err(_) => return,
+                Ok(None) => continue,
+                Ok(Some(line)) => line,
+            };
+
+            if let Some((commands, list_ok)) = command_list.as_mut() {
+                if line != "command_list_end" {
+                    commands.push(line);
+                    continue;
+                }
+
+                let (commands, list_ok) = (std::mem::take(commands), *list_ok);
+                command_list = None;
+
+                let mut response = String::new();
+                let mut failed = false;
+                for (i, command) in commands.iter().enumerate() {
+                    match self.execute_line(command) {
+                        Ok(output) => {
+                            response += &output;
+                            if list_ok {
+                                response += "list_OK\n";
+                            }
+                        }
+                        Err(error) => {
+                            response += &Self::format_error(&error, i, command);
+                            failed = true;
+                            break;
+                        }
+                    }
+                }
+                if !failed {
+                    response += "OK\n";
+                }
+
+                if self.write(&response).is_err() {
+                    return;
+                }
+                continue;
+            }
+
+            match line.as_str() {
+                "command_list_begin" => command_list = Some((Vec::new(), false)),
+                "command_list_ok_begin" => command_list = Some((Vec::new(), true)),
+                "close" => return,
+                "noidle" => {
+                    // Not idling, nothing to interrupt.
+                }
+                _ => {
+                    let response = if line == "idle" || line.starts_with("idle ") {
+                        match self.idle() {
+                            None => return,
+                            Some(response) => response,
+                        }
+                    } else {
+                        match self.execute_line(&line) {
+                            Ok(output) => output + "OK\n",
+                            Err(error) => Self::format_error(&error, 0, &line),
+                        }
+                    };
+
+                    if self.write(&response).is_err() {
+                        return;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Waits until the player state changes or the client sends `noidle`,
+    /// returns `None` if the client disconnected.
+    fn idle(&mut self) -> Option<String> {
+        let initial_snapshot = PlayerSnapshot::new(&self.audio_player.lock().unwrap());
+
+        while !self.stop_signal.load(Ordering::SeqCst) {
+            match self.read_line() {
+                Err(_) => return None,
+                Ok(Some(_)) => {
+                    // Only `noidle` is allowed during idle.
+                    return Some("OK\n".to_string());
+                }
+                Ok(None) => {}
+            }
+
+            let snapshot = PlayerSnapshot::new(&self.audio_player.lock().unwrap());
+            let changed = initial_snapshot.get_changed_subsystems(&snapshot);
+            if !changed.is_empty() {
+                let mut response = String::new();
+                for subsystem in changed {
+                    response += &format!("changed: {}\n", subsystem);
+                }
+                return Some(response + "OK\n");
+            }
+        }
+
+        None
+    }
+
+    /// Returns next line sent by the client, `None` if nothing was received
+    /// before the timeout and an error if the client disconnected
+    /// (or sent a line longer than `MAX_LINE_SIZE`).
+    fn read_line(&mut self) -> std::io::Result<Option<String>> {
+        loop {
+            if let Some(pos) = self.pending_input.iter().position(|byte| *byte == b'\n') {
+                let line: Vec<u8> = self.pending_input.drain(..=pos).collect();
+                let line = String::from_utf8_lossy(&line);
+                return Ok(Some(line.trim_end_matches(['\n', '\r']).to_string()));
+            }
+
+            if self.pending_input.len() > MAX_LINE_SIZE {
+                return Err(ErrorKind::InvalidData.into());
+            }
+
+            let mut buf = [0; READ_BUFFER_SIZE];
+            match self.stream.read(&mut buf) {
+                Ok(0) => return Err(ErrorKind::UnexpectedEof.into()),
+                Ok(count) => self.pending_input.extend_from_slice(&buf[..count]),
+                Err(error) => {
+                    if error.kind() == ErrorKind::WouldBlock || error.kind() == ErrorKind::TimedOut
+                    {
+                        return Ok(None);
+                    }
+                    return Err(error);
+                }
+            }
+        }
+    }
+
+    fn write(&mut self, text: &str) -> std::io::Result<()> {
+        self.stream.write_all(text.as_bytes())
+    }
+
+    fn format_error(error: &MpdError, command_index: usize, line: &str) -> String {
+        let command = line.split_whitespace().next().unwrap_or_default();
+        format!(
+            "ACK [{}@{}] {{{}}} {}\n",
+            error.code, command_index, command, error.message
+        )
+    }
+
+    /// Splits a command line into arguments (supports quoted arguments with escapes).
+    fn tokenize(line: &str) -> Result<Vec<String>, MpdError> {
+        let mut tokens = Vec::new();
+        let mut chars = line.chars().peekable();
+
+        loop {
+            // Skip whitespace.
+            while chars.peek().is_some_and(|c| c.is_whitespace()) {
+                chars.next();
+            }
+
+            let first = match chars.peek() {
+                None => break,
+                Some(c) => *c,
+            };
+
+            let mut token = String::new();
+            if first == '"' {
+                chars.next();
+                loop {
+                    match chars.next() {
+                        None => return Err(MpdError::new(ACK_ERROR_ARG, "Missing closing '\"'")),
+                        Some('"') => break,
+                        Some('\\') => {
+                            if let Some(c) = chars.next() {
+                                token.push(c);
+                            }
+                        }
+                        Some(c) => token.push(c),
+                    }
+                }
+            } else {
+                while let Some(c) = chars.peek() {
+                    if c.is_whitespace() {
+                        break;
+                    }
+                    token.push(*c);
+                    chars.next();
+                }
+            }
+
+            tokens.push(token);
+        }
+
+        Ok(tokens)
+    }
+
+    fn execute_line(&mut self, line: &str) -> Result<String, MpdError> {
+        let tokens = Self::tokenize(line)?;
+        let (command, args) = match tokens.split_first() {
+            None => return Err(MpdError::new(ACK_ERROR_UNKNOWN, "No command given")),
+            Some(split) => split,
+        };
+
+        let mut audio_player = self.audio_player.lock().unwrap();
+        Self::execute(&mut audio_player, command, args)
+    }
+
+    fn execute(
+        audio_player: &mut AudioPlayer,
+        command: &str,
+        args: &[String],
+    ) -> Result<String, MpdError> {
+        let mut output = String::new();
+
+        match command {
+            "ping" => {}
+            "status" => output = Self::format_status(audio_player),
+            "currentsong" => {
+                if let Some(index) = audio_player.get_current_track_index() {
+                    output = Self::format_track(audio_player, index);
+                }
+            }
+            "stats" => {
+                output = format!(
+                    "artists: 0\nalbums: 0\nsongs: {}\nuptime: 0\nplaytime: 0\ndb_playtime: 0\n",
+                    audio_player.get_tracklist().len()
+                );
+            }
+            "play" => match args.first() {
+                Some(pos) => {
+                    let index = Self::parse_index(pos, audio_player.get_tracklist().len())?;
+                    audio_player.play_track(index).map_err(MpdError::system)?;
+                }
+                None => {
+                    if audio_player.is_sound_loaded() {
+                        audio_player.set_paused(false);
+                    } else {
+                        audio_player
+                            .play_track(audio_player.get_current_track_index().unwrap_or(0))
+                            .map_err(MpdError::system)?;
+                    }
+                }
+            },
+            "playid" => match args.first() {
+                Some(id) => {
+                    let index = Self::parse_track_id(audio_player, id)?;
+                    audio_player.play_track(index).map_err(MpdError::system)?;
+                }
+                None => return Self::execute(audio_player, "play", &[]),
+            },
+            "pause" => match args.first() {
+                Some(state) => audio_player.set_paused(Self::parse_number::<u32>(state)? != 0),
+                None => audio_player.pause_resume(),
+            },
+            "stop" => audio_player.stop(),
+            "next" => audio_player.play_next_track().map_err(MpdError::system)?,
+            "previous" => audio_player
+                .play_previous_track()
+                .map_err(MpdError::system)?,
+            "seek" | "seekid" => {
+                let (target, time) = match args {
+                    [target, time] => (target, Self::parse_number::<f64>(time)?),
+                    _ => return Err(MpdError::new(ACK_ERROR_ARG, "wrong number of arguments")),
+                };
+                let index = if command == "seek" {
+                    Self::parse_index(target, audio_player.get_tracklist().len())?
+                } else {
+                    Self::parse_track_id(audio_player, target)?
+                };
+
+                if audio_player.get_current_track_index() != Some(index)
+                    || !audio_player.is_sound_loaded()
+                {
+                    audio_player.play_track(index).map_err(MpdError::system)?;
+                }
+                audio_player.set_current_sound_pos(Duration::from_secs_f64(time.max(0.0)));
+            }
+            "seekcur" => {
+                let time = Self::get_argument(args, 0)?;
+                let mut position = Self::parse_number::<f64>(time)?;
+                if time.starts_with('+') || time.starts_with('-') {
+                    position += audio_player.get_current_sound_position().as_secs_f64();
+                }
+                audio_player.set_current_sound_pos(Duration::from_secs_f64(position.max(0.0)));
+            }
+            "setvol" => {
+                let volume = Self::parse_number::<u32>(Self::get_argument(args, 0)?)?;
+                if volume > 100 {
+                    return Err(MpdError::new(ACK_ERROR_ARG, "Invalid volume value"));
+                }
+                audio_player.set_volume(volume as f64 / 100.0);
+            }
+            "volume" => {
+                let change = Self::parse_number::<i32>(Self::get_argument(args, 0)?)?;
+                let volume = (Self::get_volume_percent(audio_player) as i32 + change).clamp(0, 100);
+                audio_player.set_volume(volume as f64 / 100.0);
+            }
+            "getvol" => output = format!("volume: {}\n", Self::get_volume_percent(audio_player)),
+            "playlistinfo" | "playlistid" => {
+                let range = match args.first() {
+                    None => 0..audio_player.get_tracklist().len(),
+                    Some(arg) => {
+                        if command == "playlistinfo" {
+                            Self::parse_range(arg, audio_player.get_tracklist().len())?
+                        } else {
+                            let index = Self::parse_track_id(audio_player, arg)?;
+                            index..index + 1
+                        }
+                    }
+                };
+                for index in range {
+                    output += &Self::format_track(audio_player, index);
+                }
+            }
+            "plchanges" | "plchangesposid" => {
+                // We don't track per-track changes so report the whole tracklist.
+                for (index, track) in audio_player.get_tracklist().iter().enumerate() {
+                    if command == "plchanges" {
+                        output += &Self::format_track(audio_player, index);
+                    } else {
+                        output += &format!("cpos: {}\nId: {}\n", index, track.id);
+                    }
+                }
+            }
+            "add" | "addid" => {
+                let path = PathBuf::from(Self::get_argument(args, 0)?);
+                if !path.exists() {
+                    return Err(MpdError::new(
+                        ACK_ERROR_NO_EXIST,
+                        "No such file or directory",
+                    ));
+                }
+
+                let ids = Self::add_path(audio_player, &path);
+                if command == "addid" {
+                    match ids.first() {
+                        None => return Err(MpdError::new(ACK_ERROR_ARG, "Unsupported file")),
+                        Some(id) => output = format!("Id: {}\n", id),
+                    }
+                }
+            }
+            "delete" => {
+                let range = Self::parse_range(
+                    Self::get_argument(args, 0)?,
+                    audio_player.get_tracklist().len(),
+                )?;
+                for index in range.rev() {
+                    audio_player.remove_track(index);
+                }
+            }
+            "deleteid" => {
+                let index = Self::parse_track_id(audio_player, Self::get_argument(args, 0)?)?;
+                audio_player.remove_track(index);
+            }
+            "move" | "moveid" => {
+                let track_count = audio_player.get_tracklist().len();
+                let from = if command == "move" {
+                    Self::parse_range(Self::get_argument(args, 0)?, track_count)?
+                } else {
+                    let index = Self::parse_track_id(audio_player, Self::get_argument(args, 0)?)?;
+                    index..index + 1
+                };
+                let to = Self::parse_number::<usize>(Self::get_argument(args, 1)?)?;
+                for (from, to) in Self::get_track_moves(from, to, track_count)? {
+                    audio_player.move_track(from, to);
+                }
+            }
+            "clear" => audio_player.clear_tracklist(),
+            "outputs" => {
+                output = "outputid: 0\noutputname: Default\nplugin: kira\noutputenabled: 1\n"
+                    .to_string();
+            }
+            "commands" => {
+                for command in SUPPORTED_COMMANDS {
+                    output += &format!("command: {}\n", command);
+                }
+            }
+            "notcommands" | "tagtypes" | "urlhandlers" => {}
+            "idle" | "noidle" => {
+                return Err(MpdError::new(
+                    ACK_ERROR_NOT_LIST,
+                    "idle is not allowed in command lists",
+                ))
+            }
+            _ => {
+                return Err(MpdError::new(
+                    ACK_ERROR_UNKNOWN,
+                    &format!("unknown command \"{}\"", command),
+                ))
+            }
+        }
+
+        Ok(output)
+    }
+
+    /// Adds a file or all supported files from a directory (recursively), returns IDs of added tracks.
+    fn add_path(audio_player: &mut AudioPlayer, path: &Path) -> Vec<u32> {
+        if path.is_file() {
+            return audio_player.add_track(path).into_iter().collect();
+        }
+
+        let mut entries: Vec<PathBuf> = match std::fs::read_dir(path) {
+            Err(_) => return Vec::new(),
+            Ok(entries) => entries.flatten().map(|entry| entry.path()).collect(),
+        };
+        entries.sort();
+
+        let mut ids = Vec::new();
+        for entry in entries {
+            ids.append(&mut Self::add_path(audio_player, &entry));
+        }
+        ids
+    }
+
+    fn format_status(audio_player: &AudioPlayer) -> String {
+        let state = PlayerState::get(audio_player);
+
+        let mut output = format!(
+            "volume: {}\nrepeat: 1\nrandom: 0\nsingle: 0\nconsume: 0\nplaylist: {}\nplaylistlength: {}\nstate: {}\n",
+            Self::get_volume_percent(audio_player),
+            audio_player.get_tracklist_version(),
+            audio_player.get_tracklist().len(),
+            state.as_str()
+        );
+
+        if let Some(index) = audio_player.get_current_track_index() {
+            let tracklist = audio_player.get_tracklist();
+            let next_index = (index + 1) % tracklist.len();
+
+            output += &format!(
+                "song: {}\nsongid: {}\nnextsong: {}\nnextsongid: {}\n",
+                index, tracklist[index].id, next_index, tracklist[next_index].id
+            );
+
+            if state != PlayerState::Stop {
+                let elapsed = audio_player.get_current_sound_position().as_secs_f64();
+                let duration = audio_player.get_current_sound_duration().as_secs_f64();
+                output += &format!(
+                    "time: {}:{}\nelapsed: {:.3}\nduration: {:.3}\n",
+                    elapsed as u64, duration as u64, elapsed, duration
+                );
+            }
+        }
+
+        output
+    }
+
+    fn format_track(audio_player: &AudioPlayer, index: usize) -> String {
+        let track = &audio_player.get_tracklist()[index];
+
+        let mut output = format!(
+            "file: {}\nTitle: {}\nPos: {}\nId: {}\n",
+            track.path, track.name, index, track.id
+        );
+
+        // We only know the duration of the track that is loaded.
+        if audio_player.get_current_track_index() == Some(index) && audio_player.is_sound_loaded() {
+            let duration = audio_player.get_current_sound_duration().as_secs_f64();
+            output += &format!("Time: {}\nduration: {:.3}\n", duration as u64, duration);
+        }
+
+        output
+    }
+
+    fn get_volume_percent(audio_player: &AudioPlayer) -> u32 {
+        ((audio_player.get_volume() * 100.0).round() as u32).min(100)
+    }
+
+    fn get_argument(args: &[String], index: usize) -> Result<&str, MpdError> {
+        match args.get(index) {
+            None => Err(MpdError::new(ACK_ERROR_ARG, "too few arguments")),
+            Some(arg) => Ok(arg),
+        }
+    }
+
+    fn parse_number<T: std::str::FromStr>(arg: &str) -> Result<T, MpdError> {
+        arg.trim_start_matches('+')
+            .parse::<T>()
+            .map_err(|_| MpdError::new(ACK_ERROR_ARG, &format!("Number expected: {}", arg)))
+    }
+
+    fn parse_index(arg: &str, track_count: usize) -> Result<usize, MpdError> {
+        let index = Self::parse_number::<usize>(arg)?;
+        if index >= track_count {
+            return Err(MpdError::new(ACK_ERROR_ARG, "Bad song index"));
+        }
+        Ok(index)
+    }
+
+    fn parse_track_id(audio_player: &AudioPlayer, arg: &str) -> Result<usize, MpdError> {
+        let id = Self::parse_number::<u32>(arg)?;
+        audio_player
+            .find_track_index(id)
+            .ok_or_else(|| MpdError::new(ACK_ERROR_NO_EXIST, &format!("No such song: {}", id)))
+    }
+
+    /// Parses `POS` or `START:END` (where `END` is optional).
+    fn parse_range(arg: &str, track_count: usize) -> Result<std::ops::Range<usize>, MpdError> {
+        let range = match arg.split_once(':') {
+            None => {
+                let index = Self::parse_number::<usize>(arg)?;
+                index..index.saturating_add(1)
+            }
+            Some((start, end)) => {
+                let start = Self::parse_number::<usize>(start)?;
+                let end = if end.is_empty() {
+                    track_count
+                } else {
+                    Self::parse_number::<usize>(end)?
+                };
+                start..end
+            }
+        };
+
+        if range.start > range.end || range.end > track_count {
+            return Err(MpdError::new(ACK_ERROR_ARG, "Bad song index"));
+        }
+
+        Ok(range)
+    }
+
+    /// Returns moves of single tracks (from index, to index) that move the range
+    /// so that it starts at `to` while keeping the order of the moved tracks.
+    fn get_track_moves(
+        from: std::ops::Range<usize>,
+        to: usize,
+        track_count: usize,
+    ) -> Result<Vec<(usize, usize)>, MpdError> {
+        if to.saturating_add(from.len()) > track_count {
+            return Err(MpdError::new(ACK_ERROR_ARG, "Bad song index"));
+        }
+
+        if to <= from.start {
+            Ok(from
+                .enumerate()
+                .map(|(offset, index)| (index, to + offset))
+                .collect())
+        } else {
+            Ok(vec![(from.start, to + from.len() - 1); from.len()])
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokenize(line: &str) -> Vec<String> {
+        MpdClient::tokenize(line).unwrap()
+    }
+
+    /// Applies moves like `AudioPlayer::move_track` does.
+    fn move_tracks(range: std::ops::Range<usize>, to: usize) -> Result<String, MpdError> {
+        let mut tracks: Vec<char> = "abcde".chars().collect();
+        for (from, to) in MpdClient::get_track_moves(range, to, tracks.len())? {
+            let track = tracks.remove(from);
+            tracks.insert(to, track);
+        }
+        Ok(tracks.into_iter().collect())
+    }
+
+    #[test]
+    fn tokenize_splits_on_whitespace() {
+        assert_eq!(tokenize("  seek  1 \t 20.5 "), ["seek", "1", "20.5"]);
+        assert!(tokenize("").is_empty());
+    }
+
+    #[test]
+    fn tokenize_keeps_quoted_arguments_together() {
+        assert_eq!(
+            tokenize(r#"add "/music/My Song.mp3""#),
+            ["add", "/music/My Song.mp3"]
+        );
+        assert_eq!(tokenize(r#"find """#), ["find", ""]);
+    }
+
+    #[test]
+    fn tokenize_unescapes_quoted_arguments() {
+        assert_eq!(
+            tokenize(r#"add "say \"hi\" \\ now""#),
+            ["add", r#"say "hi" \ now"#]
+        );
+    }
+
+    #[test]
+    fn tokenize_rejects_missing_closing_quote() {
+        let error = MpdClient::tokenize(r#"add "/music/song.mp3"#).unwrap_err();
+        assert_eq!(error.code, ACK_ERROR_ARG);
+    }
+
+    #[test]
+    fn parse_range_accepts_position_and_ranges() {
+        assert_eq!(MpdClient::parse_range("2", 5).unwrap(), 2..3);
+        assert_eq!(MpdClient::parse_range("1:3", 5).unwrap(), 1..3);
+        assert_eq!(MpdClient::parse_range("0:5", 5).unwrap(), 0..5);
+    }
+
+    #[test]
+    fn parse_range_open_end_goes_to_last_track() {
+        assert_eq!(MpdClient::parse_range("2:", 5).unwrap(), 2..5);
+        assert_eq!(MpdClient::parse_range("5:", 5).unwrap(), 5..5);
+    }
+
+    #[test]
+    fn parse_range_rejects_out_of_range_input() {
+        for arg in [
+            "5",
+            "0:6",
+            "3:1",
+            "6:",
+            "-1",
+            "a:b",
+            "",
+            &usize::MAX.to_string(),
+        ] {
+            let error = MpdClient::parse_range(arg, 5).unwrap_err();
+            assert_eq!(error.code, ACK_ERROR_ARG, "argument {:?}", arg);
+        }
+    }
+
+    #[test]
+    fn move_keeps_order_of_moved_tracks() {
+        assert_eq!(move_tracks(0..2, 3).unwrap(), "cdeab");
+        assert_eq!(move_tracks(3..5, 0).unwrap(), "deabc");
+        assert_eq!(move_tracks(2..3, 4).unwrap(), "abdec");
+        assert_eq!(move_tracks(4..5, 1).unwrap(), "aebcd");
+        assert_eq!(move_tracks(1..2, 1).unwrap(), "abcde");
+    }
+
+    #[test]
+    fn move_rejects_target_past_the_end() {
+        assert!(move_tracks(0..2, 4).is_err());
+        assert!(move_tracks(0..1, 5).is_err());
+        assert!(move_tracks(0..1, usize::MAX).is_err());
+    }
+}
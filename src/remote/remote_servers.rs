@@ -0,0 +1,37 @@
+use std::sync::{Arc, Mutex};
+
+use super::{http_server::HttpServer, mpd_server::MpdServer};
+use crate::audio::audio_player::AudioPlayer;
+use crate::misc::config_manger::SettingsConfig;
+
+/// Remote control servers that are enabled in the settings.
+pub struct RemoteServers {
+    mpd_server: Option<MpdServer>,
+    http_server: Option<HttpServer>,
+}
+
+impl RemoteServers {
+    pub fn new(audio_player: Arc<Mutex<AudioPlayer>>, settings: &SettingsConfig) -> Self {
+        let mpd_server = if settings.mpd_server.enabled {
+            MpdServer::new(audio_player.clone(), &settings.mpd_server)
+        } else {
+            None
+        };
+
+        let http_server = if settings.http_server.enabled {
+            HttpServer::new(audio_player, &settings.http_server)
+        } else {
+            None
+        };
+
+        Self {
+            mpd_server,
+            http_server,
+        }
+    }
+
+    /// Tells if at least one server is running.
+    pub fn is_any_running(&self) -> bool {
+        self.mpd_server.is_some() || self.http_server.is_some()
+    }
+}
@@ -0,0 +1,91 @@
+use crate::misc::message_box;
+use std::io::ErrorKind;
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+/// How often server threads check for the stop signal.
+pub const POLL_INTERVAL_MS: u64 = 100;
+
+/// Accepts TCP connections in a background thread and handles each client in a separate thread.
+pub struct TcpServer {
+    listener_thread: Option<JoinHandle<()>>,
+    stop_signal: Arc<AtomicBool>,
+}
+
+impl Drop for TcpServer {
+    fn drop(&mut self) {
+        // Stop thread.
+        self.stop_signal.store(true, Ordering::SeqCst);
+        self.listener_thread.take().map(JoinHandle::join);
+    }
+}
+
+impl TcpServer {
+    /// Starts listening on the specified address, returns `None` if failed to start.
+    ///
+    /// The handler receives each new client along with the stop signal that client threads
+    /// should check periodically.
+    pub fn new<H>(name: &str, address: &str, handler: H) -> Option<Self>
+    where
+        H: Fn(TcpStream, Arc<AtomicBool>) + Send + Sync + 'static,
+    {
+        let listener = match TcpListener::bind(address) {
+            Ok(listener) => listener,
+            Err(msg) => {
+                message_box::show_warning(
+                    "Error",
+                    &format!("failed to start {} on {}, error: {}", name, address, msg),
+                );
+                return None;
+            }
+        };
+
+        // Don't block on `accept` so that we can check the stop signal.
+        if let Err(msg) = listener.set_nonblocking(true) {
+            message_box::show_warning(
+                "Error",
+                &format!(
+                    "failed to set non-blocking for {} socket, error: {}",
+                    name, msg
+                ),
+            );
+            return None;
+        }
+
+        let stop_signal = Arc::new(AtomicBool::new(false));
+        let stop_signal_clone = stop_signal.clone();
+        let handler = Arc::new(handler);
+        let name = name.to_string();
+
+        let listener_thread = Some(std::thread::spawn(move || {
+            while !stop_signal_clone.load(Ordering::SeqCst) {
+                match listener.accept() {
+                    Ok((stream, _)) => {
+                        // Client sockets should block (with timeouts set by the handler).
+                        if stream.set_nonblocking(false).is_err() {
+                            continue;
+                        }
+
+                        let handler = handler.clone();
+                        let stop_signal = stop_signal_clone.clone();
+                        std::thread::spawn(move || handler(stream, stop_signal));
+                    }
+                    Err(error) => {
+                        if error.kind() != ErrorKind::WouldBlock {
+                            message_box::log_error(&format!("{} error: {}", name, error));
+                        }
+                        std::thread::sleep(Duration::from_millis(POLL_INTERVAL_MS));
+                    }
+                }
+            }
+        }));
+
+        Some(Self {
+            listener_thread,
+            stop_signal,
+        })
+    }
+}
@@ -13,3 +13,40 @@ pub fn dark_orange_palette() -> Palette {
 pub fn get_primary_color() -> Color {
     Color::from_rgb8(140, 62, 0)
 }
+
+/// Color of the markers of the A-B loop.
+pub fn get_loop_marker_color() -> Color {
+    dark_orange_palette().success
+}
+
+/// Color of levels that reach full scale.
+pub fn get_clipping_color() -> Color {
+    Color::from_rgb8(230, 40, 30)
+}
+
+/// Returns the color of a spectrogram level (in range [0.0; 1.0]), quiet levels fade into
+/// the background and loud levels go from the primary color to white.
+pub fn get_spectrogram_color(level: f32) -> Color {
+    let background = dark_orange_palette().background;
+    let stops = [
+        (0.0, background),
+        (0.5, get_primary_color()),
+        (0.85, Color::from_rgb8(255, 170, 40)),
+        (1.0, Color::from_rgb8(255, 245, 210)),
+    ];
+
+    let level = level.clamp(0.0, 1.0);
+    for pair in stops.windows(2) {
+        let ((start, from), (end, to)) = (pair[0], pair[1]);
+        if level <= end {
+            let t = (level - start) / (end - start);
+            return Color::from_rgb(
+                from.r + (to.r - from.r) * t,
+                from.g + (to.g - from.g) * t,
+                from.b + (to.b - from.b) * t,
+            );
+        }
+    }
+
+    stops[stops.len() - 1].1
+}
@@ -0,0 +1,2 @@
+pub mod terminal;
+pub mod tui_application;
@@ -0,0 +1,121 @@
+use std::io::Write;
+
+/// Keys that the terminal UI reacts to.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Key {
+    Char(char),
+    Up,
+    Down,
+    Left,
+    Right,
+    Enter,
+    Escape,
+    Delete,
+    /// `Ctrl+C` (it doesn't send a signal so the terminal is restored before exit).
+    Interrupt,
+}
+
+/// Switches the terminal to raw mode and the alternate screen, restores it when dropped.
+pub struct Terminal {
+    original_mode: libc::termios,
+}
+
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        // Show cursor and leave the alternate screen.
+        Self::write("\x1b[0m\x1b[?25h\x1b[?1049l");
+
+        unsafe {
+            libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &self.original_mode);
+        }
+    }
+}
+
+impl Terminal {
+    /// Returns `None` if stdin is not a terminal.
+    pub fn new() -> Option<Self> {
+        let mut original_mode: libc::termios = unsafe { std::mem::zeroed() };
+        if unsafe { libc::tcgetattr(libc::STDIN_FILENO, &mut original_mode) } != 0 {
+            return None;
+        }
+
+        // Disable line buffering, echo and signal keys (`Ctrl+C` is read as a key),
+        // make reads return after 100 ms if no input.
+        let mut raw_mode = original_mode;
+        raw_mode.c_lflag &= !(libc::ICANON | libc::ECHO | libc::ISIG);
+        raw_mode.c_cc[libc::VMIN] = 0;
+        raw_mode.c_cc[libc::VTIME] = 1;
+        if unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &raw_mode) } != 0 {
+            return None;
+        }
+
+        // Enter the alternate screen and hide cursor.
+        Self::write("\x1b[?1049h\x1b[?25l");
+
+        Some(Self { original_mode })
+    }
+
+    /// Returns terminal size as (columns, rows).
+    pub fn get_size(&self) -> (usize, usize) {
+        let mut size: libc::winsize = unsafe { std::mem::zeroed() };
+        if unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) } != 0
+            || size.ws_col == 0
+        {
+            return (80, 24);
+        }
+
+        (size.ws_col as usize, size.ws_row as usize)
+    }
+
+    /// Waits for a key press for a short time, returns `None` if nothing was pressed.
+    pub fn read_key(&self) -> Option<Key> {
+        let mut buf = [0u8; 16];
+        let count = unsafe {
+            libc::read(
+                libc::STDIN_FILENO,
+                buf.as_mut_ptr() as *mut libc::c_void,
+                buf.len(),
+            )
+        };
+        if count <= 0 {
+            return None;
+        }
+        let input = &buf[..count as usize];
+
+        match input {
+            [b'\x1b', b'[', b'A', ..] => Some(Key::Up),
+            [b'\x1b', b'[', b'B', ..] => Some(Key::Down),
+            [b'\x1b', b'[', b'C', ..] => Some(Key::Right),
+            [b'\x1b', b'[', b'D', ..] => Some(Key::Left),
+            [b'\x1b', b'[', b'3', b'~', ..] => Some(Key::Delete),
+            [b'\x1b'] => Some(Key::Escape),
+            [b'\x03', ..] => Some(Key::Interrupt),
+            [b'\n', ..] | [b'\r', ..] => Some(Key::Enter),
+            _ => std::str::from_utf8(input)
+                .ok()
+                .and_then(|text| text.chars().next())
+                .map(Key::Char),
+        }
+    }
+
+    /// Replaces screen contents with the specified lines.
+    pub fn draw(&self, lines: &[String]) {
+        let mut frame = String::from("\x1b[H");
+        for (i, line) in lines.iter().enumerate() {
+            if i != 0 {
+                frame.push_str("\r\n");
+            }
+            frame.push_str(line);
+            frame.push_str("\x1b[0m\x1b[K");
+        }
+        frame.push_str("\x1b[J");
+
+        Self::write(&frame);
+    }
+
+    fn write(text: &str) {
+        let mut stdout = std::io::stdout();
+        let _ = stdout.write_all(text.as_bytes());
+        let _ = stdout.flush();
+    }
+}
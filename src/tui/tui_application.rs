@@ -0,0 +1,384 @@
+use super::terminal::{Key, Terminal};
+use crate::app::player_core::PlayerCore;
+use crate::audio::ab_loop::LoopMarker;
+use crate::audio::audio_player::{AudioPlayer, MAX_VOLUME};
+use crate::misc::{message_box, time_format::format_duration};
+use iced::Color;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Check for messages from other processes every N seconds.
+const PROCESS_MESSAGES_INTERVAL_SEC: u64 = 1;
+
+/// Seek by this number of seconds when left/right is pressed.
+const SEEK_STEP_SEC: u64 = 5;
+
+const VOLUME_STEP: f64 = 0.05;
+const PLAYBACK_RATE_STEP: f64 = 0.05;
+
+/// Characters used to draw the waveform (from silent to loud).
+const WAVE_CHARS: [char; 9] = [' ', '▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// Character used where the waveform is not computed yet.
+const PENDING_WAVE_CHAR: char = '·';
+
+/// Number of lines that are not used by the tracklist.
+const NON_TRACKLIST_LINE_COUNT: usize = 6;
+
+const HELP_TEXT: &str = "space: play/pause  enter: play selected  n/p: next/previous  \
+    left/right: seek  +/-: volume  [/]: rate  a/b: loop markers  c: clear loop  K/J: move  \
+    d: delete  q: quit";
+
+/// Terminal frontend that shows the tracklist, position, volume/rate and the waveform
+/// and is controlled by the keyboard.
+pub struct TuiApplication {
+    player_core: PlayerCore,
+    audio_player: Arc<Mutex<AudioPlayer>>,
+    terminal: Terminal,
+    selected_track_index: usize,
+    tracklist_scroll: usize,
+    last_message: Arc<Mutex<Option<String>>>,
+    show_milliseconds: bool,
+}
+
+impl TuiApplication {
+    /// Runs the terminal UI until the user quits.
+    pub fn run() {
+        // Show errors in the status line (dialogs would require a window).
+        let last_message = Arc::new(Mutex::new(None));
+        let last_message_clone = last_message.clone();
+        message_box::set_message_handler(move |title, text| {
+            *last_message_clone.lock().unwrap() = Some(format!("{}: {}", title, text));
+        });
+
+        let player_core = match PlayerCore::new(true) {
+            None => return, // another instance is running
+            Some(core) => core,
+        };
+
+        let terminal = match Terminal::new() {
+            None => {
+                eprintln!("Error: terminal UI requires an interactive terminal");
+                return;
+            }
+            Some(terminal) => terminal,
+        };
+
+        let mut app = Self {
+            audio_player: player_core.get_audio_player(),
+            show_milliseconds: player_core
+                .get_settings()
+                .lock()
+                .unwrap()
+                .get()
+                .display
+                .show_milliseconds,
+            player_core,
+            terminal,
+            selected_track_index: 0,
+            tracklist_scroll: 0,
+            last_message,
+        };
+        app.main_loop();
+    }
+
+    fn main_loop(&mut self) {
+        let mut last_process_messages_time = Instant::now();
+
+        loop {
+            if last_process_messages_time.elapsed()
+                >= Duration::from_secs(PROCESS_MESSAGES_INTERVAL_SEC)
+            {
+                last_process_messages_time = Instant::now();
+                self.player_core.process_messages();
+            }
+
+            self.draw();
+
+            // Waits for a short time so it also limits the redraw rate.
+            if let Some(key) = self.terminal.read_key() {
+                if matches!(key, Key::Char('q') | Key::Escape | Key::Interrupt) {
+                    return;
+                }
+                self.process_key(key);
+            }
+        }
+    }
+
+    fn process_key(&mut self, key: Key) {
+        let mut audio_player = self.audio_player.lock().unwrap();
+        let track_count = audio_player.get_tracklist().len();
+
+        // Clear old message on any key press.
+        *self.last_message.lock().unwrap() = None;
+
+        match key {
+            Key::Up | Key::Char('k') => {
+                self.selected_track_index = self.selected_track_index.saturating_sub(1);
+            }
+            Key::Down | Key::Char('j') if self.selected_track_index + 1 < track_count => {
+                self.selected_track_index += 1;
+            }
+            Key::Enter => show_play_error(audio_player.play_track(self.selected_track_index)),
+            Key::Char(' ') => {
+                if audio_player.is_sound_loaded() {
+                    audio_player.pause_resume();
+                } else {
+                    show_play_error(audio_player.play_track(self.selected_track_index));
+                }
+            }
+            Key::Char('n') => show_play_error(audio_player.play_next_track()),
+            Key::Char('p') => show_play_error(audio_player.play_previous_track()),
+            Key::Left | Key::Right => {
+                let step = Duration::from_secs(SEEK_STEP_SEC);
+                let position = audio_player.get_current_sound_position();
+                let position = if key == Key::Left {
+                    position.saturating_sub(step)
+                } else {
+                    position + step
+                };
+                audio_player.set_current_sound_pos(position);
+            }
+            Key::Char('+') | Key::Char('=') => {
+                let volume = (audio_player.get_volume() + VOLUME_STEP).min(MAX_VOLUME);
+                audio_player.set_volume(volume);
+            }
+            Key::Char('-') => {
+                let volume = (audio_player.get_volume() - VOLUME_STEP).max(0.0);
+                audio_player.set_volume(volume);
+            }
+            Key::Char(']') | Key::Char('[') => {
+                let step = if key == Key::Char(']') {
+                    PLAYBACK_RATE_STEP
+                } else {
+                    -PLAYBACK_RATE_STEP
+                };
+                // The rate is clamped by the player.
+                let rate = audio_player.get_playback_rate() + step;
+                audio_player.set_playback_rate(rate);
+            }
+            Key::Char('K') if self.selected_track_index > 0 => {
+                audio_player.move_track(self.selected_track_index, self.selected_track_index - 1);
+                self.selected_track_index -= 1;
+            }
+            Key::Char('J') if self.selected_track_index + 1 < track_count => {
+                audio_player.move_track(self.selected_track_index, self.selected_track_index + 1);
+                self.selected_track_index += 1;
+            }
+            Key::Char('a') | Key::Char('b') => {
+                let marker = if key == Key::Char('a') {
+                    LoopMarker::Start
+                } else {
+                    LoopMarker::End
+                };
+                let position = audio_player.get_current_sound_position();
+                audio_player.set_ab_loop_marker(marker, position);
+            }
+            Key::Char('c') => audio_player.clear_ab_loop(),
+            Key::Char('d') | Key::Delete if self.selected_track_index < track_count => {
+                audio_player.remove_track(self.selected_track_index);
+            }
+            _ => {}
+        }
+    }
+
+    fn draw(&mut self) {
+        let (width, height) = self.terminal.get_size();
+        let audio_player = self.audio_player.lock().unwrap();
+        let tracklist = audio_player.get_tracklist();
+
+        // Keep selection valid after the tracklist was modified.
+        self.selected_track_index = self
+            .selected_track_index
+            .min(tracklist.len().saturating_sub(1));
+
+        let mut lines = Vec::with_capacity(height);
+
+        // Title.
+        let state = if !audio_player.is_sound_loaded() {
+            "■"
+        } else if audio_player.is_paused() {
+            "‖"
+        } else {
+            "▶"
+        };
+        let track_name = match audio_player.get_current_track_index() {
+            None => String::new(),
+            Some(index) => tracklist[index].name.clone(),
+        };
+        lines.push(truncate(
+            &format!(
+                "\x1b[1m Tiny Audio Player v{}\x1b[0m  {} {}",
+                env!("CARGO_PKG_VERSION"),
+                state,
+                track_name
+            ),
+            width,
+        ));
+
+        // Position, volume and rate.
+        lines.push(truncate(
+            &format!(
+                " Time: {} / {}    Volume: {:.0}%    Playback Rate: x{:.2}{}",
+                format_duration(
+                    audio_player.get_current_sound_position(),
+                    self.show_milliseconds
+                ),
+                format_duration(
+                    audio_player.get_current_sound_duration(),
+                    self.show_milliseconds
+                ),
+                audio_player.get_volume() * 100.0,
+                audio_player.get_playback_rate(),
+                match audio_player
+                    .get_ab_loop()
+                    .get_status_text(self.show_milliseconds)
+                {
+                    Some(text) => format!("    {}", text),
+                    None => String::new(),
+                }
+            ),
+            width,
+        ));
+
+        // Waveform.
+        lines.push(Self::get_wave_line(&audio_player, width.saturating_sub(2)));
+        lines.push(format!(" {}", "─".repeat(width.saturating_sub(2))));
+
+        // Tracklist.
+        let visible_track_count = height.saturating_sub(NON_TRACKLIST_LINE_COUNT).max(1);
+        if self.selected_track_index < self.tracklist_scroll {
+            self.tracklist_scroll = self.selected_track_index;
+        } else if self.selected_track_index >= self.tracklist_scroll + visible_track_count {
+            self.tracklist_scroll = self.selected_track_index + 1 - visible_track_count;
+        }
+
+        if tracklist.is_empty() {
+            lines.push(
+                " Tracklist is empty, pass paths to audio files on the command line.".to_string(),
+            );
+        }
+        for (index, track) in tracklist
+            .iter()
+            .enumerate()
+            .skip(self.tracklist_scroll)
+            .take(visible_track_count)
+        {
+            let is_current = audio_player.get_current_track_index() == Some(index);
+            let marker = if is_current { "▶" } else { " " };
+            let line = truncate(
+                &format!(" {} {:>3}. {}", marker, index + 1, track.name),
+                width,
+            );
+
+            if index == self.selected_track_index {
+                lines.push(format!("\x1b[7m{}", line));
+            } else if is_current {
+                lines.push(format!("{}{}", get_primary_color_code(), line));
+            } else {
+                lines.push(line);
+            }
+        }
+
+        // Fill remaining space and show the status line at the bottom.
+        while lines.len() + 2 < height {
+            lines.push(String::new());
+        }
+        lines.push(format!(" {}", "─".repeat(width.saturating_sub(2))));
+        let status = match self.last_message.lock().unwrap().as_ref() {
+            Some(message) => message.clone(),
+            None => HELP_TEXT.to_string(),
+        };
+        lines.push(format!(
+            "\x1b[2m{}",
+            truncate(&format!(" {}", status), width)
+        ));
+
+        self.terminal.draw(&lines);
+    }
+
+    /// Returns a line with the waveform where the played part is highlighted.
+    fn get_wave_line(audio_player: &AudioPlayer, width: usize) -> String {
+        let sound_wave = audio_player.get_current_sound_wave();
+        let wave = sound_wave.lock().unwrap();
+
+        let duration = audio_player.get_current_sound_duration().as_secs_f64();
+        let played_columns = if duration > 0.0 {
+            (audio_player.get_current_sound_position().as_secs_f64() / duration * width as f64)
+                as usize
+        } else {
+            0
+        };
+
+        let mut line = format!(" {}", get_primary_color_code());
+        for column in 0..width {
+            if column == played_columns {
+                line.push_str("\x1b[0m\x1b[2m");
+            }
+
+            // Take the loudest sample that falls into this column.
+            let start = column as f64 / width.max(1) as f64;
+            let end = (column + 1) as f64 / width.max(1) as f64;
+            let amplitude = match wave.get_peak(start, end) {
+                Some(peak) => peak.amplitude(),
+                None if wave.get_bucket_count() > 0 => {
+                    line.push(PENDING_WAVE_CHAR);
+                    continue;
+                }
+                None => 0.0,
+            };
+
+            let char_index = (amplitude * (WAVE_CHARS.len() - 1) as f32).ceil() as usize;
+            line.push(WAVE_CHARS[char_index]);
+        }
+
+        line
+    }
+}
+
+/// Returns an escape sequence that sets text color to the primary color of the theme.
+fn get_primary_color_code() -> String {
+    let Color { r, g, b, .. } = crate::theme::style::get_primary_color();
+    format!(
+        "\x1b[38;2;{};{};{}m",
+        (r * 255.0) as u8,
+        (g * 255.0) as u8,
+        (b * 255.0) as u8
+    )
+}
+
+/// Cuts the text to fit the specified number of visible characters (escape sequences are kept).
+fn truncate(text: &str, width: usize) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut visible_count = 0;
+    let mut in_escape = false;
+
+    for c in text.chars() {
+        if c == '\x1b' {
+            in_escape = true;
+        }
+
+        if in_escape {
+            result.push(c);
+            if c.is_ascii_alphabetic() {
+                in_escape = false;
+            }
+            continue;
+        }
+
+        if visible_count >= width {
+            continue;
+        }
+        result.push(c);
+        visible_count += 1;
+    }
+
+    result
+}
+
+/// Shows the error of a track that failed to play in the status line.
+fn show_play_error(result: Result<(), String>) {
+    if let Err(msg) = result {
+        message_box::show_error("Error", &msg);
+    }
+}
@@ -1 +1,2 @@
+pub mod output_visualizer;
 pub mod track_pos_slider;
@@ -0,0 +1,300 @@
+use iced::advanced::graphics::core::event;
+use iced::advanced::layout::{self, Layout};
+use iced::advanced::renderer;
+use iced::advanced::widget::{self, Widget};
+use iced::window::{self, RedrawRequest};
+use iced::{mouse, Border, Color, Element, Event, Length, Rectangle, Shadow, Size};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use crate::audio::limiter::LimiterHandle;
+use crate::audio::output_analyzer::{ChannelLevel, OutputAnalyzer, DISPLAY_RANGE_DB};
+use crate::theme;
+
+const METER_WIDTH: f32 = 6.0;
+const METER_SPACING: f32 = 2.0;
+/// Space between the level meters and the spectrum.
+const SPECTRUM_SPACING: f32 = 6.0;
+const SPECTRUM_BAR_SPACING: f32 = 1.0;
+const PEAK_HOLD_HEIGHT: f32 = 2.0;
+const METER_BACKGROUND_ALPHA: f32 = 0.15;
+const METER_PEAK_ALPHA: f32 = 0.5;
+
+/// Gain reduction of the limiter that fills the whole height of its meter.
+const GAIN_REDUCTION_RANGE_DB: f32 = 12.0;
+/// Shown gain reduction falls by this number of decibels per second.
+const GAIN_REDUCTION_FALL_DB_PER_SEC: f32 = 20.0;
+
+/// Shows the spectrum and peak/RMS levels of the left and right channels of what is
+/// actually playing, redraws on every frame of the display.
+pub struct OutputVisualizer {
+    analyzer: Arc<Mutex<OutputAnalyzer>>,
+    limiter: Option<LimiterHandle>,
+}
+
+#[derive(Default)]
+struct State {
+    /// Shown gain reduction of the limiter.
+    gain_reduction_db: f32,
+    last_update: Option<Instant>,
+}
+
+impl OutputVisualizer {
+    pub fn new(analyzer: Arc<Mutex<OutputAnalyzer>>) -> Self {
+        Self {
+            analyzer,
+            limiter: None,
+        }
+    }
+
+    /// Shows how much the limiter reduces the gain (next to the level meters).
+    #[must_use]
+    pub fn limiter(mut self, limiter: LimiterHandle) -> Self {
+        self.limiter = Some(limiter);
+        self
+    }
+
+    /// Converts a linear amplitude to a portion of the widget height.
+    fn get_level_height(&self, amplitude: f32) -> f32 {
+        let db = 20.0 * amplitude.max(1e-10).log10();
+        ((db + DISPLAY_RANGE_DB) / DISPLAY_RANGE_DB).clamp(0.0, 1.0)
+    }
+
+    fn draw_meter<Renderer: renderer::Renderer>(
+        &self,
+        renderer: &mut Renderer,
+        level: &ChannelLevel,
+        bounds: Rectangle,
+    ) {
+        let primary_color = theme::style::get_primary_color();
+        let bar = |height: f32| Rectangle {
+            x: bounds.x,
+            y: bounds.y + bounds.height * (1.0 - height),
+            width: bounds.width,
+            height: bounds.height * height,
+        };
+
+        fill_rectangle(
+            renderer,
+            bounds,
+            Color {
+                a: METER_BACKGROUND_ALPHA,
+                ..primary_color
+            },
+        );
+        fill_rectangle(
+            renderer,
+            bar(self.get_level_height(level.peak)),
+            Color {
+                a: METER_PEAK_ALPHA,
+                ..primary_color
+            },
+        );
+        fill_rectangle(
+            renderer,
+            bar(self.get_level_height(level.rms)),
+            primary_color,
+        );
+
+        // Show the recent peak, highlight it if the sound is clipping.
+        let hold_height = self.get_level_height(level.peak_hold);
+        if hold_height > 0.0 {
+            let hold_color = if level.peak_hold >= 1.0 {
+                theme::style::get_clipping_color()
+            } else {
+                Color::WHITE
+            };
+            fill_rectangle(
+                renderer,
+                Rectangle {
+                    y: bounds.y + bounds.height * (1.0 - hold_height),
+                    height: PEAK_HOLD_HEIGHT,
+                    ..bounds
+                },
+                hold_color,
+            );
+        }
+    }
+
+    /// Draws the gain reduction of the limiter from the top of the meter.
+    fn draw_gain_reduction<Renderer: renderer::Renderer>(
+        &self,
+        renderer: &mut Renderer,
+        gain_reduction_db: f32,
+        bounds: Rectangle,
+    ) {
+        let clipping_color = theme::style::get_clipping_color();
+        fill_rectangle(
+            renderer,
+            bounds,
+            Color {
+                a: METER_BACKGROUND_ALPHA,
+                ..clipping_color
+            },
+        );
+
+        let height = (gain_reduction_db / GAIN_REDUCTION_RANGE_DB).clamp(0.0, 1.0);
+        if height > 0.0 {
+            fill_rectangle(
+                renderer,
+                Rectangle {
+                    height: bounds.height * height,
+                    ..bounds
+                },
+                clipping_color,
+            );
+        }
+    }
+}
+
+impl<Message, Theme, Renderer> Widget<Message, Theme, Renderer> for OutputVisualizer
+where
+    Renderer: renderer::Renderer,
+{
+    fn tag(&self) -> widget::tree::Tag {
+        widget::tree::Tag::of::<State>()
+    }
+
+    fn state(&self) -> widget::tree::State {
+        widget::tree::State::new(State::default())
+    }
+
+    fn size(&self) -> Size<Length> {
+        Size {
+            width: Length::Fill,
+            height: Length::Fill,
+        }
+    }
+
+    fn layout(
+        &self,
+        _tree: &mut widget::Tree,
+        _renderer: &Renderer,
+        limits: &layout::Limits,
+    ) -> layout::Node {
+        layout::Node::new(limits.max())
+    }
+
+    fn on_event(
+        &mut self,
+        tree: &mut widget::Tree,
+        event: iced::Event,
+        _layout: Layout<'_>,
+        _cursor: iced::advanced::mouse::Cursor,
+        _renderer: &Renderer,
+        _clipboard: &mut dyn iced::advanced::Clipboard,
+        shell: &mut iced::advanced::Shell<'_, Message>,
+        _viewport: &Rectangle,
+    ) -> event::Status {
+        if let Event::Window(window::Event::RedrawRequested(now)) = event {
+            self.analyzer.lock().unwrap().update();
+
+            // Show the gain reduction of the limiter, let it fall smoothly.
+            if let Some(limiter) = self.limiter.as_ref() {
+                let state = tree.state.downcast_mut::<State>();
+                let elapsed = state.last_update.map_or(0.0, |last_update| {
+                    now.duration_since(last_update).as_secs_f32()
+                });
+                state.gain_reduction_db = limiter
+                    .take_gain_reduction_db()
+                    .max(state.gain_reduction_db - GAIN_REDUCTION_FALL_DB_PER_SEC * elapsed);
+                state.last_update = Some(now);
+            }
+        }
+
+        // Keep redrawing at the display refresh rate.
+        shell.request_redraw(RedrawRequest::NextFrame);
+
+        event::Status::Ignored
+    }
+
+    fn draw(
+        &self,
+        tree: &widget::Tree,
+        renderer: &mut Renderer,
+        _theme: &Theme,
+        _style: &renderer::Style,
+        layout: Layout<'_>,
+        _cursor: mouse::Cursor,
+        _viewport: &Rectangle,
+    ) {
+        let analyzer = self.analyzer.lock().unwrap();
+        let bounds = layout.bounds();
+
+        // Draw level meters of the left and right channels.
+        for (channel, level) in analyzer.get_levels().iter().enumerate() {
+            let meter_bounds = Rectangle {
+                x: bounds.x + (METER_WIDTH + METER_SPACING) * channel as f32,
+                width: METER_WIDTH,
+                ..bounds
+            };
+            self.draw_meter(renderer, level, meter_bounds);
+        }
+        let mut meter_count = analyzer.get_levels().len();
+
+        // Draw gain reduction of the limiter.
+        if self.limiter.is_some() {
+            let meter_bounds = Rectangle {
+                x: bounds.x + (METER_WIDTH + METER_SPACING) * meter_count as f32,
+                width: METER_WIDTH,
+                ..bounds
+            };
+            let state = tree.state.downcast_ref::<State>();
+            self.draw_gain_reduction(renderer, state.gain_reduction_db, meter_bounds);
+            meter_count += 1;
+        }
+
+        // Draw spectrum.
+        let spectrum = analyzer.get_spectrum();
+        let spectrum_x = bounds.x + (METER_WIDTH + METER_SPACING) * meter_count as f32
+            - METER_SPACING
+            + SPECTRUM_SPACING;
+        let spectrum_width = bounds.x + bounds.width - spectrum_x;
+        if spectrum.is_empty() || spectrum_width <= 0.0 {
+            return;
+        }
+
+        let bar_width = spectrum_width / spectrum.len() as f32;
+        for (band, value) in spectrum.iter().enumerate() {
+            let height = value.clamp(0.0, 1.0);
+            if height <= 0.0 {
+                continue;
+            }
+
+            fill_rectangle(
+                renderer,
+                Rectangle {
+                    x: spectrum_x + bar_width * band as f32,
+                    y: bounds.y + bounds.height * (1.0 - height),
+                    width: (bar_width - SPECTRUM_BAR_SPACING).max(1.0),
+                    height: bounds.height * height,
+                },
+                theme::style::get_spectrogram_color(height),
+            );
+        }
+    }
+}
+
+fn fill_rectangle<Renderer: renderer::Renderer>(
+    renderer: &mut Renderer,
+    bounds: Rectangle,
+    color: Color,
+) {
+    renderer.fill_quad(
+        renderer::Quad {
+            bounds,
+            border: Border::default(),
+            shadow: Shadow::default(),
+        },
+        color,
+    );
+}
+
+impl<'a, Message> From<OutputVisualizer> for Element<'a, Message>
+where
+    Message: 'a,
+{
+    fn from(visualizer: OutputVisualizer) -> Self {
+        Self::new(visualizer)
+    }
+}
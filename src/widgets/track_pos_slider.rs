@@ -1,42 +1,374 @@
 use iced::advanced::graphics::core::event;
 use iced::advanced::layout::{self, Layout};
 use iced::advanced::renderer;
-use iced::advanced::widget::{self, Widget};
-use iced::window::RedrawRequest;
-use iced::{mouse, Element, Event, Shadow};
-use iced::{Border, Color, Length, Rectangle, Size};
+use iced::advanced::text::{self, Text};
+use iced::advanced::widget::{self, tree, Widget};
+use iced::alignment::{Horizontal, Vertical};
+use iced::window::{self, RedrawRequest};
+use iced::{keyboard, mouse, Element, Event, Shadow};
+use iced::{Border, Color, Length, Pixels, Rectangle, Size};
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 
+use crate::audio::ab_loop::{AbLoop, LoopMarker};
 use crate::audio::audio_player::AudioPlayer;
+use crate::audio::sound_wave::SoundWave;
+use crate::audio::spectrogram::Spectrogram;
+use crate::misc::config_manger::WaveformMode;
+use crate::misc::{settings::TEXT_SIZE, time_format::format_duration};
 use crate::theme;
 
 const REDRAW_INTERVAL_MS: u64 = 250;
 
+/// Seek by this number of seconds per mouse wheel step.
+const WHEEL_SEEK_STEP_SEC: f64 = 1.0;
+
+/// Number of pixels that touchpads scroll per mouse wheel step.
+const WHEEL_PIXELS_PER_LINE: f32 = 50.0;
+
+const TOOLTIP_PADDING: f32 = 3.0;
+const TOOLTIP_BACKGROUND_ALPHA: f32 = 0.8;
+const PLAYHEAD_WIDTH: f32 = 2.0;
+
+/// Zoom in by this factor per mouse wheel step (while Ctrl is held).
+const WHEEL_ZOOM_FACTOR: f64 = 1.25;
+
+/// Scroll by this portion of the visible part per mouse wheel step (while Shift is held).
+const WHEEL_SCROLL_STEP: f64 = 0.1;
+
+/// While following the playhead the view is moved when the playhead passes this portion
+/// of the visible part, the playhead is then placed at `1.0 - FOLLOW_MARGIN` of the view.
+const FOLLOW_MARGIN: f64 = 0.9;
+
+const LOOP_MARKER_WIDTH: f32 = 2.0;
+const LOOP_REGION_ALPHA: f32 = 0.12;
+
+const SCROLL_INDICATOR_HEIGHT: f32 = 3.0;
+const SCROLL_INDICATOR_ALPHA: f32 = 0.5;
+
+/// Height (in pixels) of the line that is drawn where the wave is not computed yet.
+const PENDING_WAVE_HEIGHT: f32 = 2.0;
+const PENDING_WAVE_ALPHA: f32 = 0.35;
+
+/// Size (in pixels) of one cell of the spectrogram.
+const SPECTROGRAM_CELL_WIDTH: f32 = 2.0;
+const SPECTROGRAM_CELL_HEIGHT: f32 = 2.0;
+
+/// Spectrogram cells with lower levels are not drawn (they are barely visible on the background).
+const SPECTROGRAM_MIN_VISIBLE_LEVEL: f32 = 0.05;
+
+/// How a wave is drawn in a part of the widget.
+enum LaneShape {
+    /// From minimum to maximum around the center of the lane.
+    Centered,
+    /// Amplitude above the center of the lane.
+    Up,
+    /// Amplitude below the center of the lane.
+    Down,
+}
+
+/// Part of the widget where the wave of one channel (or of all channels) is drawn.
+struct WaveLane {
+    /// `None` to draw all channels combined.
+    channel: Option<usize>,
+    center_y: f32,
+    half_height: f32,
+    shape: LaneShape,
+}
+
+/// Mouse interaction and zoom state of the slider.
+struct SliderState {
+    /// Portion of the track under the cursor while the user drags the slider.
+    drag_portion: Option<f32>,
+    /// Portion of the track under the cursor while it's hovering the slider.
+    hover_portion: Option<f32>,
+    /// 1.0 shows the whole track, 2.0 shows half of the track and so on.
+    zoom: f64,
+    /// Portion of the track at the left edge of the slider.
+    view_start: f64,
+    /// Whether the view should scroll to keep the playhead visible.
+    follow: bool,
+    modifiers: keyboard::Modifiers,
+    /// Used to reset the view when another track is started.
+    track_duration: Duration,
+}
+
+impl Default for SliderState {
+    fn default() -> Self {
+        Self {
+            drag_portion: None,
+            hover_portion: None,
+            zoom: 1.0,
+            view_start: 0.0,
+            follow: true,
+            modifiers: keyboard::Modifiers::default(),
+            track_duration: Duration::ZERO,
+        }
+    }
+}
+
+impl SliderState {
+    /// Returns the visible portion of the track.
+    fn get_view_span(&self) -> f64 {
+        1.0 / self.zoom
+    }
+
+    /// Returns portion of the track that corresponds to the specified X coordinate.
+    fn get_portion_at(&self, bounds: Rectangle, x: f32) -> f32 {
+        let relative_x = ((x - bounds.x) / bounds.width).clamp(0.0, 1.0) as f64;
+        (self.view_start + relative_x * self.get_view_span()).clamp(0.0, 1.0) as f32
+    }
+
+    /// Returns X coordinate of the specified portion of the track (may be outside of the bounds).
+    fn get_x_of_portion(&self, bounds: Rectangle, portion: f64) -> f32 {
+        bounds.x + bounds.width * ((portion - self.view_start) / self.get_view_span()) as f32
+    }
+
+    /// Sets the zoom keeping the portion at the specified X coordinate under it.
+    fn set_zoom(&mut self, zoom: f64, max_zoom: f64, bounds: Rectangle, x: f32) {
+        let relative_x = ((x - bounds.x) / bounds.width).clamp(0.0, 1.0) as f64;
+        let anchor = self.view_start + relative_x * self.get_view_span();
+
+        self.zoom = zoom.clamp(1.0, max_zoom.max(1.0));
+        self.set_view_start(anchor - relative_x * self.get_view_span());
+    }
+
+    /// Moves the view keeping it inside of the track.
+    fn set_view_start(&mut self, view_start: f64) {
+        self.view_start = view_start.clamp(0.0, (1.0 - self.get_view_span()).max(0.0));
+    }
+
+    /// Moves the view if the playhead is about to leave it.
+    fn follow_playhead(&mut self, position: f64) {
+        let span = self.get_view_span();
+        if position < self.view_start || position > self.view_start + span * FOLLOW_MARGIN {
+            self.set_view_start(position - span * (1.0 - FOLLOW_MARGIN));
+        }
+    }
+}
+
+/// Shows the waveform and position of the current track, seeks when the user
+/// drags (seek happens on release), clicks or scrolls over it.
+/// Ctrl + mouse wheel zooms the waveform, Shift + mouse wheel scrolls it,
+/// Shift + click places markers of the A-B loop.
 pub struct TrackPosSlider<Message> {
     audio_player: Arc<Mutex<AudioPlayer>>,
-    on_clicked: Option<Box<dyn FnMut(f32) -> Message>>,
+    waveform_mode: WaveformMode,
+    show_milliseconds: bool,
+    on_seek: Option<Box<dyn FnMut(f32) -> Message>>,
+    on_loop_marker: Option<Box<dyn FnMut(LoopMarker, f32) -> Message>>,
 }
 
 impl<Message> TrackPosSlider<Message> {
     pub fn new(audio_player: Arc<Mutex<AudioPlayer>>) -> Self {
         Self {
             audio_player,
-            on_clicked: None,
+            waveform_mode: WaveformMode::default(),
+            show_milliseconds: false,
+            on_seek: None,
+            on_loop_marker: None,
         }
     }
 
     #[must_use]
-    pub fn on_clicked<CB: 'static + Fn(f32) -> Message>(mut self, callback: CB) -> Self {
-        self.on_clicked = Some(Box::new(callback));
+    pub fn waveform_mode(mut self, mode: WaveformMode) -> Self {
+        self.waveform_mode = mode;
         self
     }
+
+    #[must_use]
+    pub fn show_milliseconds(mut self, show: bool) -> Self {
+        self.show_milliseconds = show;
+        self
+    }
+
+    /// Sets a callback that receives the portion of the track (in range [0.0; 1.0])
+    /// to seek to.
+    #[must_use]
+    pub fn on_seek<CB: 'static + Fn(f32) -> Message>(mut self, callback: CB) -> Self {
+        self.on_seek = Some(Box::new(callback));
+        self
+    }
+
+    /// Sets a callback that receives the loop marker to place and the portion of the track
+    /// (in range [0.0; 1.0]) the user shift-clicked.
+    #[must_use]
+    pub fn on_loop_marker<CB: 'static + Fn(LoopMarker, f32) -> Message>(
+        mut self,
+        callback: CB,
+    ) -> Self {
+        self.on_loop_marker = Some(Box::new(callback));
+        self
+    }
+
+    /// Returns which marker a shift-click at the specified portion of the track should place:
+    /// missing markers first, then the nearest one.
+    fn get_loop_marker_to_place(ab_loop: &AbLoop, position: Duration) -> LoopMarker {
+        match (ab_loop.get_start(), ab_loop.get_end()) {
+            (None, _) => LoopMarker::Start,
+            (Some(_), None) => LoopMarker::End,
+            (Some(start), Some(end)) => {
+                if position.abs_diff(start) <= position.abs_diff(end) {
+                    LoopMarker::Start
+                } else {
+                    LoopMarker::End
+                }
+            }
+        }
+    }
+
+    /// Draws markers of the A-B loop and highlights the part between them.
+    fn draw_loop_markers<Renderer: text::Renderer<Font = iced::Font>>(
+        &self,
+        renderer: &mut Renderer,
+        state: &SliderState,
+        bounds: Rectangle,
+        ab_loop: &AbLoop,
+        duration: Duration,
+    ) {
+        let get_x = |position: Duration| {
+            state.get_x_of_portion(bounds, position.as_secs_f64() / duration.as_secs_f64())
+        };
+        let color = theme::style::get_loop_marker_color();
+
+        if let (Some(start), Some(end)) = (ab_loop.get_start(), ab_loop.get_end()) {
+            let start_x = get_x(start).max(bounds.x);
+            let end_x = get_x(end).min(bounds.x + bounds.width);
+            if end_x > start_x {
+                renderer.fill_quad(
+                    renderer::Quad {
+                        bounds: Rectangle {
+                            x: start_x,
+                            width: end_x - start_x,
+                            ..bounds
+                        },
+                        border: Border::default(),
+                        shadow: Shadow::default(),
+                    },
+                    Color {
+                        a: LOOP_REGION_ALPHA,
+                        ..color
+                    },
+                );
+            }
+        }
+
+        let markers = [(ab_loop.get_start(), "A"), (ab_loop.get_end(), "B")];
+        for (position, label) in markers {
+            let x = match position.map(get_x) {
+                Some(x) if x >= bounds.x && x <= bounds.x + bounds.width => x,
+                _ => continue,
+            };
+
+            renderer.fill_quad(
+                renderer::Quad {
+                    bounds: Rectangle {
+                        x: x - LOOP_MARKER_WIDTH / 2.0,
+                        width: LOOP_MARKER_WIDTH,
+                        ..bounds
+                    },
+                    border: Border::default(),
+                    shadow: Shadow::default(),
+                },
+                color,
+            );
+
+            let text_size = TEXT_SIZE as f32;
+            renderer.fill_text(
+                Text {
+                    content: label.to_string(),
+                    bounds: Size {
+                        width: text_size,
+                        height: text_size * 1.3,
+                    },
+                    size: Pixels(text_size),
+                    line_height: text::LineHeight::default(),
+                    font: renderer.default_font(),
+                    horizontal_alignment: Horizontal::Left,
+                    vertical_alignment: Vertical::Bottom,
+                    shaping: text::Shaping::Basic,
+                    wrapping: text::Wrapping::None,
+                },
+                iced::Point {
+                    x: x + LOOP_MARKER_WIDTH,
+                    y: bounds.y + bounds.height,
+                },
+                color,
+                bounds,
+            );
+        }
+    }
+
+    /// Draws a box with the time at the specified portion of the track near the top of the slider
+    /// (next to the specified X coordinate).
+    fn draw_time_tooltip<Renderer: text::Renderer<Font = iced::Font>>(
+        &self,
+        renderer: &mut Renderer,
+        bounds: Rectangle,
+        x: f32,
+        portion: f32,
+        duration: Duration,
+    ) {
+        let content = format_duration(duration.mul_f64(portion as f64), self.show_milliseconds);
+
+        // Estimate text width to keep the tooltip inside of the slider.
+        let text_size = TEXT_SIZE as f32;
+        let size = Size {
+            width: content.chars().count() as f32 * text_size * 0.6 + TOOLTIP_PADDING * 2.0,
+            height: text_size * 1.3 + TOOLTIP_PADDING * 2.0,
+        };
+        let x = (x + PLAYHEAD_WIDTH)
+            .min(bounds.x + bounds.width - size.width)
+            .max(bounds.x);
+        let tooltip_bounds = Rectangle {
+            x,
+            y: bounds.y,
+            width: size.width,
+            height: size.height,
+        };
+
+        renderer.fill_quad(
+            renderer::Quad {
+                bounds: tooltip_bounds,
+                border: Border::default(),
+                shadow: Shadow::default(),
+            },
+            Color {
+                a: TOOLTIP_BACKGROUND_ALPHA,
+                ..Color::BLACK
+            },
+        );
+        renderer.fill_text(
+            Text {
+                content,
+                bounds: size,
+                size: Pixels(text_size),
+                line_height: text::LineHeight::default(),
+                font: renderer.default_font(),
+                horizontal_alignment: Horizontal::Center,
+                vertical_alignment: Vertical::Center,
+                shaping: text::Shaping::Basic,
+                wrapping: text::Wrapping::None,
+            },
+            tooltip_bounds.center(),
+            Color::WHITE,
+            bounds,
+        );
+    }
 }
 
 impl<Message, Theme, Renderer> Widget<Message, Theme, Renderer> for TrackPosSlider<Message>
 where
-    Renderer: renderer::Renderer,
+    Renderer: text::Renderer<Font = iced::Font>,
 {
+    fn tag(&self) -> tree::Tag {
+        tree::Tag::of::<SliderState>()
+    }
+
+    fn state(&self) -> tree::State {
+        tree::State::new(SliderState::default())
+    }
+
     fn size(&self) -> Size<Length> {
         Size {
             width: Length::Fill,
@@ -55,7 +387,7 @@ where
 
     fn on_event(
         &mut self,
-        _state: &mut widget::Tree,
+        tree: &mut widget::Tree,
         event: iced::Event,
         layout: Layout<'_>,
         cursor: iced::advanced::mouse::Cursor,
@@ -64,13 +396,127 @@ where
         shell: &mut iced::advanced::Shell<'_, Message>,
         _viewport: &Rectangle,
     ) -> iced::advanced::graphics::core::event::Status {
+        let state = tree.state.downcast_mut::<SliderState>();
+        let bounds = layout.bounds();
+        let mut status = event::Status::Ignored;
+
         // Process mouse.
-        if let Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Left)) = event {
-            if let Some(on_clicked) = self.on_clicked.as_mut() {
-                if let Some(relative_pos) = cursor.position_in(layout.bounds()) {
-                    shell.publish(on_clicked(relative_pos.x / layout.bounds().width));
+        match event {
+            Event::Keyboard(keyboard::Event::ModifiersChanged(modifiers)) => {
+                state.modifiers = modifiers;
+            }
+            Event::Window(window::Event::RedrawRequested(_)) => {
+                let audio_player = self.audio_player.lock().unwrap();
+                let duration = audio_player.get_current_sound_duration();
+
+                // Show the whole wave of a new track.
+                if duration != state.track_duration {
+                    *state = SliderState {
+                        modifiers: state.modifiers,
+                        track_duration: duration,
+                        ..SliderState::default()
+                    };
+                }
+
+                if state.follow && state.drag_portion.is_none() && !duration.is_zero() {
+                    state.follow_playhead(
+                        audio_player.get_current_sound_position().as_secs_f64()
+                            / duration.as_secs_f64(),
+                    );
+                }
+            }
+            Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Left))
+                if state.modifiers.shift() =>
+            {
+                if let Some(position) = cursor.position_over(bounds) {
+                    let portion = state.get_portion_at(bounds, position.x);
+                    let audio_player = self.audio_player.lock().unwrap();
+                    let clicked_position = audio_player
+                        .get_current_sound_duration()
+                        .mul_f64(portion as f64);
+                    let marker = Self::get_loop_marker_to_place(
+                        audio_player.get_ab_loop(),
+                        clicked_position,
+                    );
+                    if let Some(on_loop_marker) = self.on_loop_marker.as_mut() {
+                        shell.publish(on_loop_marker(marker, portion));
+                    }
+                    status = event::Status::Captured;
+                }
+            }
+            Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Left)) => {
+                if let Some(position) = cursor.position_over(bounds) {
+                    state.drag_portion = Some(state.get_portion_at(bounds, position.x));
+                    status = event::Status::Captured;
+                }
+            }
+            Event::Mouse(mouse::Event::CursorMoved { position }) => {
+                // Keep dragging even if the cursor left the slider.
+                if state.drag_portion.is_some() {
+                    state.drag_portion = Some(state.get_portion_at(bounds, position.x));
+                }
+                state.hover_portion = cursor
+                    .position_over(bounds)
+                    .map(|position| state.get_portion_at(bounds, position.x));
+
+                shell.request_redraw(RedrawRequest::NextFrame);
+            }
+            Event::Mouse(mouse::Event::CursorLeft) => {
+                state.hover_portion = None;
+            }
+            Event::Mouse(mouse::Event::ButtonReleased(mouse::Button::Left)) => {
+                // Seek only when the user releases the button.
+                if let Some(portion) = state.drag_portion.take() {
+                    state.follow = true;
+                    if let Some(on_seek) = self.on_seek.as_mut() {
+                        shell.publish(on_seek(portion));
+                    }
+                    status = event::Status::Captured;
                 }
             }
+            Event::Mouse(mouse::Event::WheelScrolled { delta }) if cursor.is_over(bounds) => {
+                let (lines_x, lines_y) = match delta {
+                    mouse::ScrollDelta::Lines { x, y } => (x, y),
+                    mouse::ScrollDelta::Pixels { x, y } => {
+                        (x / WHEEL_PIXELS_PER_LINE, y / WHEEL_PIXELS_PER_LINE)
+                    }
+                };
+                let audio_player = self.audio_player.lock().unwrap();
+
+                if state.modifiers.command() {
+                    // Don't zoom further than one computed bucket per pixel.
+                    let sound_wave = audio_player.get_current_sound_wave();
+                    let max_zoom =
+                        sound_wave.lock().unwrap().get_bucket_count() as f64 / bounds.width as f64;
+                    let x = cursor.position().map_or(bounds.center_x(), |p| p.x);
+                    let zoom = state.zoom * WHEEL_ZOOM_FACTOR.powf(lines_y as f64);
+                    state.set_zoom(zoom, max_zoom, bounds, x);
+                    state.hover_portion = Some(state.get_portion_at(bounds, x));
+                    shell.request_redraw(RedrawRequest::NextFrame);
+                    return event::Status::Captured;
+                }
+
+                if state.modifiers.shift() || lines_x != 0.0 {
+                    // Scrolling down or left moves the view towards the end of the track.
+                    let lines = if lines_x != 0.0 { lines_x } else { lines_y };
+                    let offset = -lines as f64 * WHEEL_SCROLL_STEP * state.get_view_span();
+                    state.set_view_start(state.view_start + offset);
+                    state.follow = false;
+                    shell.request_redraw(RedrawRequest::NextFrame);
+                    return event::Status::Captured;
+                }
+
+                let lines = lines_y;
+                let duration = audio_player.get_current_sound_duration().as_secs_f64();
+                if let (Some(on_seek), true) = (self.on_seek.as_mut(), duration > 0.0) {
+                    state.follow = true;
+                    let position = audio_player.get_current_sound_position().as_secs_f64()
+                        + lines as f64 * WHEEL_SEEK_STEP_SEC;
+                    shell.publish(on_seek((position / duration).clamp(0.0, 1.0) as f32));
+                    status = event::Status::Captured;
+                }
+            }
+            _ => {}
         }
 
         // Queue a new redraw later.
@@ -78,12 +524,12 @@ where
             Instant::now() + Duration::from_millis(REDRAW_INTERVAL_MS),
         ));
 
-        event::Status::Ignored
+        status
     }
 
     fn draw(
         &self,
-        _state: &widget::Tree,
+        tree: &widget::Tree,
         renderer: &mut Renderer,
         _theme: &Theme,
         _style: &renderer::Style,
@@ -93,47 +539,227 @@ where
     ) {
         let audio_player = self.audio_player.lock().unwrap();
         let sound_wave = audio_player.get_current_sound_wave();
-        let audio_data = sound_wave.lock().unwrap();
+        let wave = sound_wave.lock().unwrap();
 
-        let current_pos_portion =
-            audio_player.get_current_sound_position() / audio_player.get_current_sound_duration();
+        let state = tree.state.downcast_ref::<SliderState>();
+
+        // While dragging show the position that will be set on release.
+        let duration = audio_player.get_current_sound_duration();
+        let current_pos_portion = if duration.is_zero() {
+            0.0
+        } else if let Some(portion) = state.drag_portion {
+            portion as f64
+        } else {
+            audio_player.get_current_sound_position().as_secs_f64() / duration.as_secs_f64()
+        };
 
         let layout_bounds = layout.bounds();
-        let step_width = layout_bounds.width / audio_data.len() as f32;
 
-        // Draw wave.
-        for (i, sample) in audio_data.iter().enumerate() {
-            let portion = *sample as f32 / u8::MAX as f32;
-            let sample_height = layout_bounds.height * portion;
+        // Draw wave or spectrogram.
+        if self.waveform_mode == WaveformMode::Spectrogram {
+            let spectrogram = audio_player.get_current_sound_spectrogram();
+            draw_spectrogram(renderer, &spectrogram.lock().unwrap(), state, layout_bounds);
+        } else {
+            for lane in get_wave_lanes(&wave, self.waveform_mode, layout_bounds) {
+                draw_wave_lane(renderer, &wave, &lane, state, layout_bounds);
+            }
+        }
 
-            // Draw a quad that represents this "sample".
+        // Draw current position quad.
+        let current_pos_x = state
+            .get_x_of_portion(layout_bounds, current_pos_portion)
+            .clamp(layout_bounds.x, layout_bounds.x + layout_bounds.width);
+        renderer.fill_quad(
+            renderer::Quad {
+                bounds: Rectangle {
+                    x: layout_bounds.x,
+                    y: layout_bounds.y,
+                    width: current_pos_x - layout_bounds.x,
+                    height: layout_bounds.height,
+                },
+                border: Border {
+                    radius: 0.0.into(),
+                    width: 0.0,
+                    color: Color::from_rgb(0.0, 0.0, 0.0),
+                },
+                shadow: Shadow::default(),
+            },
+            Color {
+                a: 0.5,
+                ..Color::BLACK
+            },
+        );
+
+        if !duration.is_zero() {
+            self.draw_loop_markers(
+                renderer,
+                state,
+                layout_bounds,
+                audio_player.get_ab_loop(),
+                duration,
+            );
+        }
+
+        // Show which part of the track is visible.
+        if state.zoom > 1.0 {
             renderer.fill_quad(
                 renderer::Quad {
                     bounds: Rectangle {
-                        x: layout_bounds.x + step_width * i as f32,
-                        y: layout_bounds.y + layout_bounds.height - sample_height,
-                        width: step_width,
-                        height: sample_height,
+                        x: layout_bounds.x + layout_bounds.width * state.view_start as f32,
+                        y: layout_bounds.y + layout_bounds.height - SCROLL_INDICATOR_HEIGHT,
+                        width: layout_bounds.width * state.get_view_span() as f32,
+                        height: SCROLL_INDICATOR_HEIGHT,
                     },
-                    border: Border {
-                        radius: 0.0.into(),
-                        width: 0.0,
-                        color: Color::from_rgb(0.0, 0.0, 0.0),
+                    border: Border::default(),
+                    shadow: Shadow::default(),
+                },
+                Color {
+                    a: SCROLL_INDICATOR_ALPHA,
+                    ..Color::WHITE
+                },
+            );
+        }
+
+        // Show target time while dragging or time under the cursor while hovering.
+        if duration.is_zero() {
+            return;
+        }
+        if let Some(portion) = state.drag_portion.or(state.hover_portion) {
+            let x = state.get_x_of_portion(layout_bounds, portion as f64);
+            renderer.fill_quad(
+                renderer::Quad {
+                    bounds: Rectangle {
+                        x: x - PLAYHEAD_WIDTH / 2.0,
+                        y: layout_bounds.y,
+                        width: PLAYHEAD_WIDTH,
+                        height: layout_bounds.height,
                     },
+                    border: Border::default(),
                     shadow: Shadow::default(),
                 },
-                theme::style::get_primary_color(),
+                Color::WHITE,
             );
+            self.draw_time_tooltip(renderer, layout_bounds, x, portion, duration);
         }
+    }
+}
 
-        // Draw current position quad.
+/// Splits the widget into lanes according to the display mode and the number of channels.
+fn get_wave_lanes(wave: &SoundWave, mode: WaveformMode, bounds: Rectangle) -> Vec<WaveLane> {
+    let center_y = bounds.y + bounds.height / 2.0;
+    let half_height = bounds.height / 2.0;
+    let channel_count = wave.get_channel_count();
+
+    if mode == WaveformMode::Combined || channel_count <= 1 {
+        return vec![WaveLane {
+            channel: None,
+            center_y,
+            half_height,
+            shape: LaneShape::Centered,
+        }];
+    }
+
+    // Left channel above the center line and right channel mirrored below it.
+    if channel_count == 2 {
+        return vec![
+            WaveLane {
+                channel: Some(0),
+                center_y,
+                half_height,
+                shape: LaneShape::Up,
+            },
+            WaveLane {
+                channel: Some(1),
+                center_y,
+                half_height,
+                shape: LaneShape::Down,
+            },
+        ];
+    }
+
+    // Stack other channels.
+    let lane_height = bounds.height / channel_count as f32;
+    (0..channel_count)
+        .map(|channel| WaveLane {
+            channel: Some(channel),
+            center_y: bounds.y + lane_height * (channel as f32 + 0.5),
+            half_height: lane_height / 2.0,
+            shape: LaneShape::Centered,
+        })
+        .collect()
+}
+
+/// Draws the visible part of the wave in one quad per pixel column.
+fn draw_wave_lane<Renderer: renderer::Renderer>(
+    renderer: &mut Renderer,
+    wave: &SoundWave,
+    lane: &WaveLane,
+    state: &SliderState,
+    bounds: Rectangle,
+) {
+    if wave.get_bucket_count() == 0 {
+        return;
+    }
+
+    let column_count = bounds.width.max(1.0) as usize;
+    let column_width = bounds.width / column_count as f32;
+    let column_span = state.get_view_span() / column_count as f64;
+    for column in 0..column_count {
+        let start = state.view_start + column_span * column as f64;
+        let end = start + column_span;
+        let x = bounds.x + column_width * column as f32;
+
+        let peak = match lane.channel {
+            None => wave.get_peak(start, end),
+            Some(channel) => wave.get_channel_peak(channel, start, end),
+        };
+        let peak = match peak {
+            Some(peak) => peak,
+            None => {
+                // Buckets are computed from the start so the rest is pending too.
+                renderer.fill_quad(
+                    renderer::Quad {
+                        bounds: Rectangle {
+                            x,
+                            y: lane.center_y - PENDING_WAVE_HEIGHT / 2.0,
+                            width: bounds.x + bounds.width - x,
+                            height: PENDING_WAVE_HEIGHT,
+                        },
+                        border: Border::default(),
+                        shadow: Shadow::default(),
+                    },
+                    Color {
+                        a: PENDING_WAVE_ALPHA,
+                        ..theme::style::get_primary_color()
+                    },
+                );
+                return;
+            }
+        };
+
+        let (top, bottom) = match lane.shape {
+            LaneShape::Centered => (
+                lane.center_y - peak.max.clamp(-1.0, 1.0) * lane.half_height,
+                lane.center_y - peak.min.clamp(-1.0, 1.0) * lane.half_height,
+            ),
+            LaneShape::Up => (
+                lane.center_y - peak.amplitude() * lane.half_height,
+                lane.center_y,
+            ),
+            LaneShape::Down => (
+                lane.center_y,
+                lane.center_y + peak.amplitude() * lane.half_height,
+            ),
+        };
+
+        // Draw a quad that represents this part of the sound.
         renderer.fill_quad(
             renderer::Quad {
                 bounds: Rectangle {
-                    x: layout_bounds.x,
-                    y: layout_bounds.y,
-                    width: layout_bounds.width * current_pos_portion as f32,
-                    height: layout_bounds.height,
+                    x,
+                    y: top,
+                    width: column_width,
+                    height: (bottom - top).max(1.0),
                 },
                 border: Border {
                     radius: 0.0.into(),
@@ -142,14 +768,81 @@ where
                 },
                 shadow: Shadow::default(),
             },
-            Color {
-                a: 0.5,
-                ..Color::BLACK
-            },
+            theme::style::get_primary_color(),
         );
     }
 }
 
+/// Draws the visible part of the spectrogram in cells, low frequencies at the bottom.
+fn draw_spectrogram<Renderer: renderer::Renderer>(
+    renderer: &mut Renderer,
+    spectrogram: &Spectrogram,
+    state: &SliderState,
+    bounds: Rectangle,
+) {
+    if spectrogram.get_column_count() == 0 {
+        return;
+    }
+
+    let band_count = spectrogram.get_band_count();
+    let row_count = ((bounds.height / SPECTROGRAM_CELL_HEIGHT) as usize).clamp(1, band_count);
+    let row_height = bounds.height / row_count as f32;
+    let cell_count = ((bounds.width / SPECTROGRAM_CELL_WIDTH) as usize).max(1);
+    let cell_width = bounds.width / cell_count as f32;
+    let cell_span = state.get_view_span() / cell_count as f64;
+
+    for cell in 0..cell_count {
+        let start = state.view_start + cell_span * cell as f64;
+        let end = start + cell_span;
+        let x = bounds.x + cell_width * cell as f32;
+
+        for row in 0..row_count {
+            let band_start = row * band_count / row_count;
+            let band_end = (row + 1) * band_count / row_count;
+            let level = match spectrogram.get_level(start, end, band_start, band_end) {
+                Some(level) => level,
+                None => {
+                    // Columns are computed from the start so the rest is pending too.
+                    renderer.fill_quad(
+                        renderer::Quad {
+                            bounds: Rectangle {
+                                x,
+                                y: bounds.center_y() - PENDING_WAVE_HEIGHT / 2.0,
+                                width: bounds.x + bounds.width - x,
+                                height: PENDING_WAVE_HEIGHT,
+                            },
+                            border: Border::default(),
+                            shadow: Shadow::default(),
+                        },
+                        Color {
+                            a: PENDING_WAVE_ALPHA,
+                            ..theme::style::get_primary_color()
+                        },
+                    );
+                    return;
+                }
+            };
+            if level < SPECTROGRAM_MIN_VISIBLE_LEVEL {
+                continue;
+            }
+
+            renderer.fill_quad(
+                renderer::Quad {
+                    bounds: Rectangle {
+                        x,
+                        y: bounds.y + bounds.height - row_height * (row + 1) as f32,
+                        width: cell_width,
+                        height: row_height,
+                    },
+                    border: Border::default(),
+                    shadow: Shadow::default(),
+                },
+                theme::style::get_spectrogram_color(level),
+            );
+        }
+    }
+}
+
 impl<'a, Message> From<TrackPosSlider<Message>> for Element<'a, Message>
 where
     Message: 'a + Clone,